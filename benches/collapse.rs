@@ -1,6 +1,7 @@
 use std::time::Duration;
 
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use fast_whitespace_collapse::{collapse_whitespace, collapse_whitespace_cow, collapse_whitespace_with_backend, Backend};
 use regex::Regex;
 
 fn regex_approach(s: &str, re: &Regex) -> String {
@@ -89,7 +90,7 @@ fn benchmark(c: &mut Criterion) {
     group.measurement_time(Duration::from_secs(20));
 
     group.bench_function("regex_approach", |b| {
-        b.iter_batched(|| (s, Regex::new(r"\s\s+").unwrap()), |(s, re)| regex_approach(black_box(&s), black_box(&re)), criterion::BatchSize::LargeInput)
+        b.iter_batched(|| (s, Regex::new(r"\s\s+").unwrap()), |(s, re)| regex_approach(black_box(s), black_box(&re)), criterion::BatchSize::LargeInput)
     });
 
     group.bench_function("iterative_approach", |b| {
@@ -105,11 +106,63 @@ fn benchmark(c: &mut Criterion) {
     });
 
     group.bench_function("fast_whitespace_collapse", |b| {
-        b.iter_batched(|| s, |s| fast_whitespace_collapse::collapse_whitespace(black_box(s)), criterion::BatchSize::LargeInput)
+        b.iter_batched(|| s, |s| collapse_whitespace(black_box(s)), criterion::BatchSize::LargeInput)
     });
 
     group.finish();
 }
 
-criterion_group!(benches, benchmark);
+fn long_documents(c: &mut Criterion) {
+    let paragraph = "Whitespace collapsing is mostly applied to prose, where words are separated by single \
+        spaces and only the occasional  double space,\tstray tab or   indented line needs fixing. ";
+    let prose = paragraph.repeat(1000);
+    let clean = collapse_whitespace(&prose);
+    // Minified records, with a space in every fourth of them
+    let sparse = (0..4000)
+        .map(|n| {
+            let note = if n % 4 == 0 { "in stock" } else { "available" };
+            format!("{{\"id\":{n},\"name\":\"item_{n}\",\"tags\":[\"a\",\"b\"],\"note\":\"{note}\"}}")
+        })
+        .collect::<String>();
+
+    let mut group = c.benchmark_group("long_documents");
+    group.throughput(Throughput::Bytes(prose.len() as u64));
+
+    group.bench_function("prose", |b| b.iter(|| collapse_whitespace(black_box(&prose))));
+
+    group.bench_function("already_collapsed", |b| b.iter(|| collapse_whitespace(black_box(&clean))));
+
+    group.bench_function("already_collapsed_cow", |b| b.iter(|| collapse_whitespace_cow(black_box(&clean)).len()));
+
+    group.throughput(Throughput::Bytes(sparse.len() as u64));
+    group.bench_function("sparse", |b| b.iter(|| collapse_whitespace(black_box(&sparse))));
+
+    group.finish();
+}
+
+fn short_fields(c: &mut Criterion) {
+    let fields = ["  jdoe ", "Jane  Doe\t(admin)", " support team  lead,\tEMEA ", "  a longer job title with  several   words\tin it  "];
+
+    let mut group = c.benchmark_group("short_fields");
+    for field in fields {
+        group.bench_function(format!("collapse_whitespace/{}", field.len()), |b| b.iter(|| collapse_whitespace(black_box(field))));
+        group.bench_function(format!("iterative_bytes/{}", field.len()), |b| b.iter(|| iterative_bytes(black_box(field))));
+    }
+    group.finish();
+}
+
+fn backends(c: &mut Criterion) {
+    let paragraph = "Whitespace collapsing is mostly applied to prose, where words are separated by single \
+        spaces and only the occasional  double space,\tstray tab or   indented line needs fixing. ";
+    let prose = paragraph.repeat(1000);
+
+    let mut group = c.benchmark_group("backends");
+    group.throughput(Throughput::Bytes(prose.len() as u64));
+    for backend in Backend::ALL.into_iter().filter(|backend| backend.is_supported()) {
+        group.bench_function(format!("{backend:?}"), |b| b.iter(|| collapse_whitespace_with_backend(black_box(&prose), backend)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmark, long_documents, short_fields, backends);
 criterion_main!(benches);
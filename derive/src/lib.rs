@@ -0,0 +1,96 @@
+//! The `#[derive(CollapseWhitespace)]` macro of `fast_whitespace_collapse`, re-exported
+//! by its `derive` feature; see the `CollapseWhitespace` trait there.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Field, GenericArgument, Member, PathArguments, Type};
+
+/// Derives `fast_whitespace_collapse::CollapseWhitespace` for a struct, collapsing every
+/// field of type `String`, `Option<String>` or `Vec<String>`, nested to any depth.
+///
+/// A field takes `#[collapse_whitespace(skip)]` to be left as is, and
+/// `#[collapse_whitespace(nested)]` to be collapsed with its own `CollapseWhitespace`
+/// implementation, such as a struct deriving it too.
+#[proc_macro_derive(CollapseWhitespace, attributes(collapse_whitespace))]
+pub fn derive_collapse_whitespace(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(&input.ident, "`CollapseWhitespace` can only be derived for structs"));
+    };
+    let mut generics = input.generics.clone();
+    let mut members = Vec::new();
+    for (index, field) in data.fields.iter().enumerate() {
+        match collapses(field)? {
+            Collapses::No => continue,
+            Collapses::String => {}
+            Collapses::Nested => {
+                // Lets a nested field be of a generic type
+                let ty = &field.ty;
+                generics.make_where_clause().predicates.push(parse_quote!(#ty: ::fast_whitespace_collapse::CollapseWhitespace));
+            }
+        }
+        members.push(field.ident.clone().map_or_else(|| Member::from(index), Member::Named));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::fast_whitespace_collapse::CollapseWhitespace for #name #type_generics #where_clause {
+            fn collapse_whitespace(&mut self) {
+                #( ::fast_whitespace_collapse::CollapseWhitespace::collapse_whitespace(&mut self.#members); )*
+            }
+        }
+    })
+}
+
+/// How a field is collapsed.
+enum Collapses {
+    No,
+    /// As a `String`, or an `Option` or `Vec` of one.
+    String,
+    /// With its own `CollapseWhitespace` implementation.
+    Nested,
+}
+
+/// Returns how `field` is collapsed, going by its attributes, then by its type.
+fn collapses(field: &Field) -> syn::Result<Collapses> {
+    let mut collapses = None;
+    for attribute in field.attrs.iter().filter(|attribute| attribute.path().is_ident("collapse_whitespace")) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                collapses = Some(Collapses::No);
+            } else if meta.path.is_ident("nested") {
+                collapses = Some(Collapses::Nested);
+            } else {
+                return Err(meta.error("expected `skip` or `nested`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(collapses.unwrap_or(if is_string_type(&field.ty) { Collapses::String } else { Collapses::No }))
+}
+
+/// Returns whether `ty` is `String`, or an `Option` or `Vec` of such a type.
+///
+/// Types are only known by name here, so a `String` that is not `std`'s has to be
+/// skipped explicitly.
+fn is_string_type(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    let Some(segment) = path.path.segments.last() else {
+        return false;
+    };
+    match &segment.arguments {
+        PathArguments::None => segment.ident == "String",
+        PathArguments::AngleBracketed(arguments) if segment.ident == "Option" || segment.ident == "Vec" => {
+            matches!(arguments.args.first(), Some(GenericArgument::Type(inner)) if arguments.args.len() == 1 && is_string_type(inner))
+        }
+        _ => false,
+    }
+}
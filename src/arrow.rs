@@ -0,0 +1,80 @@
+//! Collapsing whitespace in every value of an Arrow string array, as a compute kernel.
+
+use arrow_array::{Array, GenericStringArray, OffsetSizeTrait};
+use arrow_buffer::{Buffer, OffsetBuffer, ScalarBuffer};
+
+use crate::kernel;
+
+/// Collapses consecutive spaces and tabs in every value of `array`, like
+/// [`collapse_whitespace`](crate::collapse_whitespace), into a new array.
+///
+/// Works on `StringArray` and `LargeStringArray` alike, and on slices of them.
+///
+/// # Parameters
+/// - `array`: The Arrow string array to collapse.
+///
+/// # Returns
+/// - An array of as many values, collapsed, with the null buffer of `array`.
+///
+/// # Example
+/// ```
+/// use arrow_array::StringArray;
+/// use fast_whitespace_collapse::collapse_whitespace_array;
+///
+/// let names = StringArray::from(vec![Some("  Jane  Doe "), None, Some("\tJohn")]);
+/// let collapsed = collapse_whitespace_array(&names);
+/// assert_eq!(collapsed, StringArray::from(vec![Some("Jane Doe"), None, Some("John")]));
+/// ```
+///
+/// # Performance
+/// - Reads the values buffer and offsets of `array` directly, and collapses every value
+///   with the SIMD kernel of `collapse_whitespace` straight into the values buffer of
+///   the output, reserved once for the length of the input: there is no allocation per
+///   row.
+/// - Builds the output without validating it again, as collapsing keeps every value
+///   valid UTF-8 and can only shrink the offsets.
+pub fn collapse_whitespace_array<O: OffsetSizeTrait>(array: &GenericStringArray<O>) -> GenericStringArray<O> {
+    let offsets = array.value_offsets();
+    let values = array.values().as_slice();
+    let mut collapsed = Vec::with_capacity(offsets[offsets.len() - 1].as_usize() - offsets[0].as_usize());
+    let mut collapsed_offsets = Vec::with_capacity(offsets.len());
+    collapsed_offsets.push(O::usize_as(0));
+    for range in offsets.windows(2) {
+        kernel::collapse_bytes_into(&values[range[0].as_usize()..range[1].as_usize()], &mut collapsed);
+        collapsed_offsets.push(O::usize_as(collapsed.len()));
+    }
+
+    // Safety: The offsets start at 0 and never decrease, and end at the length of the
+    // values, which are at most those of `array`, so their type fits them. The kernel
+    // only ever removes spaces/tabs or writes a space, and appends every value whole, so
+    // every value is valid UTF-8. The null buffer is that of an array of as many values
+    unsafe {
+        let offsets = OffsetBuffer::new_unchecked(ScalarBuffer::from(collapsed_offsets));
+        GenericStringArray::new_unchecked(offsets, Buffer::from_vec(collapsed), array.nulls().cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::collapse_whitespace_array;
+    use arrow_array::{Array, LargeStringArray, StringArray};
+
+    #[test]
+    fn collapses_every_value_and_keeps_nulls() {
+        let array = StringArray::from(vec![Some(""), Some("   "), None, Some("  Jane  Doe "), Some("こんにちは\t\t世界"), Some("clean")]);
+        let collapsed = collapse_whitespace_array(&array);
+        assert_eq!(collapsed, StringArray::from(vec![Some(""), Some(""), None, Some("Jane Doe"), Some("こんにちは 世界"), Some("clean")]));
+        collapsed.to_data().validate_full().unwrap();
+    }
+
+    #[test]
+    fn collapses_slices_and_large_arrays() {
+        let array = LargeStringArray::from(vec!["a  b", " c ", "d\t\te", "f"]);
+        let collapsed = collapse_whitespace_array(&array.slice(1, 2));
+        assert_eq!(collapsed, LargeStringArray::from(vec!["c", "d e"]));
+        assert_eq!(collapsed.value_offsets(), [0, 1, 4]);
+
+        let empty = collapse_whitespace_array(&StringArray::from(Vec::<&str>::new()));
+        assert!(empty.is_empty());
+    }
+}
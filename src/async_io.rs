@@ -0,0 +1,358 @@
+//! Tokio `AsyncRead` and `AsyncWrite` adapters that collapse whitespace on the fly.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::io::READ_CHUNK;
+use crate::{Collapser, StreamCollapser};
+
+/// An async reader that yields the collapsed contents of another async reader.
+///
+/// This is the async equivalent of [`CollapseReader`](crate::CollapseReader): the
+/// underlying reader is read in chunks and collapsed with a [`StreamCollapser`], so a
+/// request body or streamed file is normalized without buffering it fully. It also
+/// implements [`AsyncBufRead`].
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::AsyncCollapseReader;
+/// use tokio::io::AsyncReadExt;
+///
+/// # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+/// let mut reader = AsyncCollapseReader::new("  Hello,   \t world  ".as_bytes());
+/// let mut output = String::new();
+/// reader.read_to_string(&mut output).await?;
+/// assert_eq!(output, "Hello, world");
+/// # Ok::<(), std::io::Error>(())
+/// # })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct AsyncCollapseReader<R> {
+    inner: R,
+    stream: StreamCollapser,
+    /// Bytes read from `inner`.
+    input: Box<[u8]>,
+    /// Collapsed output, of which `output[position..]` has not been read yet.
+    output: Vec<u8>,
+    position: usize,
+    /// `inner` reached its end and the stream has been finished.
+    finished: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncCollapseReader<R> {
+    /// Creates a reader collapsing like [`collapse_whitespace`](crate::collapse_whitespace).
+    pub fn new(inner: R) -> Self {
+        Self::with_collapser(inner, Collapser::new())
+    }
+
+    /// Creates a reader collapsing with the options of `collapser`.
+    pub fn with_collapser(inner: R, collapser: Collapser) -> Self {
+        Self {
+            inner,
+            stream: collapser.stream(),
+            input: vec![0; READ_CHUNK].into_boxed_slice(),
+            output: Vec::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly skips input that has not been collapsed yet.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the underlying reader, discarding collapsed output not read yet.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncCollapseReader<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let available = ready!(Pin::new(&mut *this).poll_fill_buf(cx))?;
+        let n = available.len().min(buf.remaining());
+        buf.put_slice(&available[..n]);
+        this.position += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncBufRead for AsyncCollapseReader<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        // A chunk may collapse to nothing, so keep reading until there is output
+        while this.position == this.output.len() && !this.finished {
+            let mut input = ReadBuf::new(&mut this.input);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut input))?;
+            let n = input.filled().len();
+
+            this.output.clear();
+            this.position = 0;
+            if n == 0 {
+                this.stream.finish(&mut this.output);
+                this.finished = true;
+            } else {
+                this.stream.push_bytes(&this.input[..n], &mut this.output);
+            }
+        }
+        Poll::Ready(Ok(&this.output[this.position..]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amount: usize) {
+        let this = self.get_mut();
+        this.position = (this.position + amount).min(this.output.len());
+    }
+}
+
+/// An async writer that collapses whitespace in everything written through it.
+///
+/// This is the async equivalent of [`CollapseWriter`](crate::CollapseWriter). Shut it
+/// down once everything is written: output that depends on the end of the input, such
+/// as an incomplete UTF-8 sequence or a trailing newline policy, is only written by
+/// [`poll_shutdown`](AsyncWrite::poll_shutdown), before the underlying writer is shut
+/// down.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::AsyncCollapseWriter;
+/// use tokio::io::AsyncWriteExt;
+///
+/// # tokio::runtime::Builder::new_current_thread().build()?.block_on(async {
+/// let mut writer = AsyncCollapseWriter::new(Vec::new());
+/// writer.write_all(b"  Hello, ").await?;
+/// writer.write_all(b"   world  ").await?;
+/// writer.shutdown().await?;
+/// assert_eq!(writer.get_ref(), b"Hello, world");
+/// # Ok::<(), std::io::Error>(())
+/// # })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct AsyncCollapseWriter<W> {
+    inner: W,
+    stream: StreamCollapser,
+    /// Collapsed output not yet accepted by `inner`.
+    buffer: Vec<u8>,
+    /// The stream has been finished by a shutdown.
+    finished: bool,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncCollapseWriter<W> {
+    /// Creates a writer collapsing like [`collapse_whitespace`](crate::collapse_whitespace).
+    pub fn new(inner: W) -> Self {
+        Self::with_collapser(inner, Collapser::new())
+    }
+
+    /// Creates a writer collapsing with the options of `collapser`.
+    pub fn with_collapser(inner: W, collapser: Collapser) -> Self {
+        Self {
+            inner,
+            stream: collapser.stream(),
+            buffer: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly interleaves with collapsed output that is still buffered.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the underlying writer, discarding collapsed output not written yet.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Writes the buffered output to `inner`, keeping whatever it did not accept.
+    fn poll_write_buffer(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut written = 0;
+        let result = loop {
+            if written == self.buffer.len() {
+                break Poll::Ready(Ok(()));
+            }
+            match Pin::new(&mut self.inner).poll_write(cx, &self.buffer[written..]) {
+                Poll::Ready(Ok(0)) => {
+                    break Poll::Ready(Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write collapsed output")))
+                }
+                Poll::Ready(Ok(n)) => written += n,
+                Poll::Ready(Err(e)) => break Poll::Ready(Err(e)),
+                Poll::Pending => break Poll::Pending,
+            }
+        };
+        self.buffer.drain(..written);
+        result
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for AsyncCollapseWriter<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        // Output left over from an earlier write goes first, which bounds the buffer
+        ready!(this.poll_write_buffer(cx))?;
+        this.stream.push_bytes(buf, &mut this.buffer);
+
+        // `buf` is consumed either way; an error resurfaces on the next call
+        if let Poll::Ready(Err(e)) = this.poll_write_buffer(cx) {
+            if e.kind() == io::ErrorKind::WriteZero {
+                return Poll::Ready(Err(e));
+            }
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buffer(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.finished {
+            this.stream.finish(&mut this.buffer);
+            this.finished = true;
+        }
+        ready!(this.poll_write_buffer(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AsyncCollapseReader, AsyncCollapseWriter};
+    use crate::io::READ_CHUNK;
+    use crate::{collapse_whitespace, Collapser, TrailingNewline};
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+    /// A reader returning at most `step` bytes per call, and pending every other call.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+        pending: bool,
+    }
+
+    impl AsyncRead for Trickle<'_> {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let n = self.data.len().min(self.step).min(buf.remaining());
+            buf.put_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// A writer accepting at most one byte per call, and pending every other call.
+    struct Sluggish {
+        written: Vec<u8>,
+        pending: bool,
+        shut_down: bool,
+    }
+
+    impl AsyncWrite for Sluggish {
+        fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            self.pending = !self.pending;
+            if self.pending {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let n = buf.len().min(1);
+            self.written.extend_from_slice(&buf[..n]);
+            Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.shut_down = true;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    const INPUT: &str = "  runs \t  straddle\u{3000} chunks é\u{a0}  😀   here  \n  and  there ";
+
+    #[tokio::test]
+    async fn reads_collapsed_across_refills() {
+        for step in 1..8 {
+            let trickle = Trickle { data: INPUT.as_bytes(), step, pending: false };
+            let mut output = String::new();
+            AsyncCollapseReader::new(trickle).read_to_string(&mut output).await.unwrap();
+            assert_eq!(output, collapse_whitespace(INPUT), "step {step}");
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_inputs_larger_than_a_chunk() {
+        let input = "word   \t ".repeat(READ_CHUNK / 3);
+        let collapser = Collapser::new().trailing_newline(TrailingNewline::Single);
+        let mut reader = AsyncCollapseReader::with_collapser(input.as_bytes(), collapser);
+        let mut output = Vec::new();
+        let mut buf = [0; 100];
+        loop {
+            match reader.read(&mut buf).await.unwrap() {
+                0 => break,
+                n => output.extend_from_slice(&buf[..n]),
+            }
+        }
+        assert_eq!(output, collapser.collapse(&input).as_bytes());
+    }
+
+    #[tokio::test]
+    async fn reads_collapsed_lines() {
+        let mut lines = AsyncCollapseReader::new("  one   line \n   \t two  ".as_bytes()).lines();
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("one line "));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some(" two"));
+        assert_eq!(lines.next_line().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn writes_collapsed_to_a_slow_writer() {
+        let sluggish = Sluggish { written: Vec::new(), pending: false, shut_down: false };
+        let mut writer = AsyncCollapseWriter::new(sluggish);
+        for chunk in INPUT.as_bytes().chunks(3) {
+            writer.write_all(chunk).await.unwrap();
+        }
+        writer.shutdown().await.unwrap();
+        let sluggish = writer.into_inner();
+        assert_eq!(sluggish.written, collapse_whitespace(INPUT).as_bytes());
+        assert!(sluggish.shut_down);
+    }
+
+    #[tokio::test]
+    async fn writes_trailing_newline_on_shutdown() {
+        let collapser = Collapser::new().trailing_newline(TrailingNewline::Single);
+        let mut writer = AsyncCollapseWriter::with_collapser(Vec::new(), collapser);
+        writer.write_all(b"  last  line\n\n\n").await.unwrap();
+        writer.flush().await.unwrap();
+        assert_eq!(writer.get_ref(), b"last line");
+        writer.shutdown().await.unwrap();
+        assert_eq!(writer.get_ref(), collapser.collapse("  last  line\n\n\n").as_bytes());
+    }
+}
@@ -0,0 +1,139 @@
+//! Forcing one kernel behind [`collapse_whitespace`](crate::collapse_whitespace), for
+//! benchmarking, bisecting a miscompare, or running under instrumentation.
+
+use crate::kernel;
+
+/// A kernel that [`collapse_whitespace_with_backend`] can be forced to run.
+///
+/// Every backend returns exactly what [`collapse_whitespace`](crate::collapse_whitespace)
+/// returns; only the code computing it differs. Which backends a build has depends on
+/// the target and the Cargo features, and the x86_64 ones beyond SSE2 on the CPU (see
+/// [`is_supported`](Self::is_supported)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Backend {
+    /// Whatever `collapse_whitespace` runs: the widest kernel the CPU supports, the
+    /// scalar loop for short input, or the `memchr` one for input with hardly any
+    /// whitespace.
+    #[default]
+    Auto,
+    /// One byte at a time, with a lookup table and no branch on the input.
+    Scalar,
+    /// 8 bytes at a time in `u64` words, the kernel of targets without SIMD.
+    Swar,
+    /// 64, then 16 bytes at a time with SSE2 (x86_64).
+    Sse2,
+    /// Like `Sse2`, with the kept bytes left-packed by the `PSHUFB` of SSSE3 (x86_64).
+    Ssse3,
+    /// 64, then 32 bytes at a time with AVX2, left-packed like `Ssse3` (x86_64).
+    Avx2,
+    /// 64 bytes at a time, packed with the `VPCOMPRESSB` of AVX-512 VBMI2 (x86_64,
+    /// `avx512` feature).
+    Avx512,
+    /// 64, then 16 bytes at a time with NEON (aarch64).
+    Neon,
+    /// 64, then 16 bytes at a time with SIMD128 (wasm32 built with `+simd128`).
+    Simd128,
+    /// From blank to blank with `memchr2`, the kernel `Auto` picks for input with hardly
+    /// any whitespace (`memchr` feature).
+    Memchr,
+}
+
+impl Backend {
+    /// Every backend, whether supported or not.
+    pub const ALL: [Backend; 10] = [
+        Backend::Auto,
+        Backend::Scalar,
+        Backend::Swar,
+        Backend::Sse2,
+        Backend::Ssse3,
+        Backend::Avx2,
+        Backend::Avx512,
+        Backend::Neon,
+        Backend::Simd128,
+        Backend::Memchr,
+    ];
+
+    /// Returns `true` if this build has the backend and the CPU can run it.
+    ///
+    /// `Auto`, `Scalar` and `Swar` are always supported.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Backend;
+    /// let supported: Vec<Backend> = Backend::ALL.into_iter().filter(|backend| backend.is_supported()).collect();
+    /// assert!(supported.contains(&Backend::Swar));
+    /// ```
+    pub fn is_supported(self) -> bool {
+        kernel::supports(self)
+    }
+}
+
+/// Collapses consecutive spaces and tabs like [`collapse_whitespace`](crate::collapse_whitespace),
+/// with `backend` rather than the kernel it would pick.
+///
+/// Apart from [`Backend::Auto`], the backend runs on the whole input, even where
+/// `collapse_whitespace` would take a different path for short input or input with
+/// hardly any whitespace.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+/// - `backend`: The kernel to collapse it with.
+///
+/// # Returns
+/// - The same `String` as `collapse_whitespace(input)`.
+///
+/// # Panics
+/// Panics if `backend` is not [supported](Backend::is_supported) by this build or CPU.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::{collapse_whitespace_with_backend, Backend};
+/// for backend in Backend::ALL.into_iter().filter(|backend| backend.is_supported()) {
+///     assert_eq!(collapse_whitespace_with_backend("  some \t text  ", backend), "some text");
+/// }
+/// ```
+pub fn collapse_whitespace_with_backend(input: &str, backend: Backend) -> String {
+    let Some(collapsed) = kernel::collapse_bytes_with_backend(input.as_bytes(), backend) else {
+        panic!("the {backend:?} backend is not supported by this build or CPU");
+    };
+    // Safety: Every kernel only ever removes spaces/tabs or writes a space
+    unsafe { String::from_utf8_unchecked(collapsed) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collapse_whitespace;
+
+    #[test]
+    fn every_supported_backend_matches_collapse_whitespace() {
+        let inputs = [
+            String::new(),
+            "   ".into(),
+            "This   is \t  a   test.".into(),
+            "こんにちは\t\t世界  ".into(),
+            "  The   quick\t\tbrown fox  ".repeat(20),
+            format!("id{:300}name{:200}\n", "", "").repeat(10),
+            format!("{{\"id\":1,\"tags\":[\"a\",\"b\"]}}{}\t\t", "x".repeat(300)).repeat(10),
+        ];
+        for backend in Backend::ALL.into_iter().filter(|backend| backend.is_supported()) {
+            for input in &inputs {
+                assert_eq!(collapse_whitespace_with_backend(input, backend), collapse_whitespace(input), "{backend:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn memchr_backend_needs_its_feature() {
+        assert_eq!(Backend::Memchr.is_supported(), cfg!(feature = "memchr"));
+    }
+
+    #[test]
+    #[should_panic(expected = "backend is not supported")]
+    fn rejects_unsupported_backend() {
+        let unsupported = Backend::ALL.into_iter().find(|backend| !backend.is_supported());
+        // Every build lacks the kernels of the other architectures
+        collapse_whitespace_with_backend("a  b", unsupported.unwrap());
+    }
+}
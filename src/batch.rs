@@ -0,0 +1,264 @@
+//! Collapsing many strings at once, such as the rows of an ETL job.
+
+use std::borrow::Cow;
+
+use crate::kernel;
+
+/// Collapses consecutive spaces and tabs in every string of `inputs`, like
+/// [`collapse_whitespace`](crate::collapse_whitespace) on each of them.
+///
+/// # Parameters
+/// - `inputs`: The strings to collapse, such as a `&[&str]` or a `&[String]`.
+///
+/// # Returns
+/// - The collapsed strings, in the order of `inputs`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_many;
+/// let rows = ["  Jane  Doe ", "clean", "\t(admin)\t\tEMEA"];
+/// assert_eq!(collapse_many(&rows), ["Jane Doe", "clean", "(admin) EMEA"]);
+/// ```
+///
+/// # Performance
+/// - Collapses every string into one scratch buffer shared by the whole batch, and
+///   gives each output an allocation of exactly its length rather than one sized for
+///   its input, so the outputs take less memory; on a million short fields, this
+///   measured a few percent faster than mapping `collapse_whitespace` over them.
+/// - With the `rayon` feature, `collapse_many_parallel`
+///   splits the batch over the Rayon thread pool.
+pub fn collapse_many<S: AsRef<str>>(inputs: &[S]) -> Vec<String> {
+    let mut scratch = Vec::new();
+    inputs.iter().map(|input| collapse_with_scratch(input.as_ref(), &mut scratch)).collect()
+}
+
+/// Collapses consecutive spaces and tabs in every string of `inputs`, like
+/// [`collapse_many`], borrowing the strings that are already collapsed.
+///
+/// # Parameters
+/// - `inputs`: The strings to collapse, such as a `&[&str]` or a `&[String]`.
+///
+/// # Returns
+/// - For every input, in order, `Cow::Borrowed` of it if collapsing would not change
+///   it, or the collapsed `String` otherwise.
+///
+/// # Example
+/// ```
+/// use std::borrow::Cow;
+/// use fast_whitespace_collapse::collapse_many_cow;
+/// let collapsed = collapse_many_cow(&["clean", "  Jane  Doe "]);
+/// assert!(matches!(collapsed[0], Cow::Borrowed("clean")));
+/// assert_eq!(collapsed[1], "Jane Doe");
+/// ```
+///
+/// # Performance
+/// - Clean strings are found with the SIMD scan of
+///   [`collapse_whitespace_cow`](crate::collapse_whitespace_cow), and cost neither an
+///   allocation nor a copy, so a batch that is mostly clean costs little more than
+///   reading it.
+/// - The other strings are collapsed through one scratch buffer shared by the whole
+///   batch, like `collapse_many`.
+pub fn collapse_many_cow<S: AsRef<str>>(inputs: &[S]) -> Vec<Cow<'_, str>> {
+    let mut scratch = Vec::new();
+    inputs
+        .iter()
+        .map(|input| {
+            let input = input.as_ref();
+            if kernel::is_collapsed(input.as_bytes()) {
+                Cow::Borrowed(input)
+            } else {
+                Cow::Owned(collapse_with_scratch(input, &mut scratch))
+            }
+        })
+        .collect()
+}
+
+/// Collapses consecutive spaces and tabs in every string of `inputs`, like
+/// [`collapse_many`], into one contiguous buffer.
+///
+/// # Parameters
+/// - `inputs`: The strings to collapse, such as a `&[&str]` or a `&[String]`.
+///
+/// # Returns
+/// - A [`CollapsedBatch`] holding the collapsed strings back to back, and the offsets
+///   at which each of them starts and ends.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_many_contiguous;
+/// let batch = collapse_many_contiguous(&["  Jane  Doe ", "clean", "\t(admin)\t\tEMEA"]);
+/// assert_eq!(batch.as_str(), "Jane Doeclean(admin) EMEA");
+/// assert_eq!(batch.offsets(), [0, 8, 13, 25]);
+/// assert_eq!(batch.get(1), Some("clean"));
+/// ```
+///
+/// # Performance
+/// - Makes no allocation per string: the buffer is reserved once for the length of all
+///   the inputs, and every string is collapsed straight into it.
+/// - The offsets are laid out like those of an Arrow `Utf8` or `LargeUtf8` array, so
+///   [`into_parts`](CollapsedBatch::into_parts) hands over a column without copying it.
+pub fn collapse_many_contiguous<S: AsRef<str>>(inputs: &[S]) -> CollapsedBatch {
+    let mut buffer = Vec::with_capacity(inputs.iter().map(|input| input.as_ref().len()).sum());
+    let mut offsets = Vec::with_capacity(inputs.len() + 1);
+    offsets.push(0);
+    for input in inputs {
+        kernel::collapse_bytes_into(input.as_ref().as_bytes(), &mut buffer);
+        offsets.push(buffer.len());
+    }
+    // Safety: The kernel only ever removes spaces/tabs or writes a space, and appends
+    // every string whole, so the buffer is a concatenation of valid UTF-8
+    let buffer = unsafe { String::from_utf8_unchecked(buffer) };
+    CollapsedBatch { buffer, offsets }
+}
+
+/// Collapsed strings stored back to back in one buffer, as returned by
+/// [`collapse_many_contiguous`].
+///
+/// String `i` is `as_str()[offsets()[i]..offsets()[i + 1]]`, so there is one more offset
+/// than strings, the first being 0 and the last the length of the buffer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CollapsedBatch {
+    buffer: String,
+    offsets: Vec<usize>,
+}
+
+impl CollapsedBatch {
+    /// Returns the number of strings in the batch.
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Returns `true` if the batch holds no string.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns collapsed string `index`, or `None` if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        let end = *self.offsets.get(index + 1)?;
+        Some(&self.buffer[self.offsets[index]..end])
+    }
+
+    /// Returns an iterator over the collapsed strings, in the order of the inputs.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator + '_ {
+        self.offsets.windows(2).map(|range| &self.buffer[range[0]..range[1]])
+    }
+
+    /// Returns all the collapsed strings, back to back.
+    pub fn as_str(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Returns the offsets at which each string starts, then the length of the buffer.
+    pub fn offsets(&self) -> &[usize] {
+        &self.offsets
+    }
+
+    /// Returns the buffer and the offsets, for handing them over to a columnar format.
+    pub fn into_parts(self) -> (String, Vec<usize>) {
+        (self.buffer, self.offsets)
+    }
+}
+
+/// Collapses consecutive spaces and tabs in every string of `strings` in place, like
+/// [`collapse_whitespace`](crate::collapse_whitespace) on each of them.
+///
+/// # Parameters
+/// - `strings`: The strings to collapse, such as a `&mut Vec<String>`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_all_in_place;
+/// let mut rows = vec!["  Jane  Doe ".to_string(), "clean".into(), "\t(admin)\t\tEMEA".into()];
+/// collapse_all_in_place(&mut rows);
+/// assert_eq!(rows, ["Jane Doe", "clean", "(admin) EMEA"]);
+/// ```
+///
+/// # Performance
+/// - Every string keeps its allocation: collapsing never lengthens a string, so its
+///   output is copied back into it from a scratch buffer shared by the whole batch.
+/// - Strings that are already collapsed are found with the SIMD scan of
+///   [`collapse_whitespace_cow`](crate::collapse_whitespace_cow) and left untouched.
+/// - With the `rayon` feature, `collapse_all_in_place_parallel`
+///   splits the batch over the Rayon thread pool.
+pub fn collapse_all_in_place(strings: &mut [String]) {
+    let mut scratch = Vec::new();
+    for string in strings {
+        collapse_in_place_with_scratch(string, &mut scratch);
+    }
+}
+
+/// Collapses `string` in place, through `scratch`, unless it is already collapsed.
+#[inline]
+pub(crate) fn collapse_in_place_with_scratch(string: &mut String, scratch: &mut Vec<u8>) {
+    if kernel::is_collapsed(string.as_bytes()) {
+        return;
+    }
+    scratch.clear();
+    kernel::collapse_bytes_into(string.as_bytes(), scratch);
+    string.clear();
+    // Safety: The kernel only ever removes spaces/tabs or writes a space
+    string.push_str(unsafe { std::str::from_utf8_unchecked(scratch) });
+}
+
+/// Collapses `input` into `scratch`, and returns a copy of exactly its length.
+#[inline]
+pub(crate) fn collapse_with_scratch(input: &str, scratch: &mut Vec<u8>) -> String {
+    scratch.clear();
+    kernel::collapse_bytes_into(input.as_bytes(), scratch);
+    // Safety: The kernel only ever removes spaces/tabs or writes a space
+    unsafe { std::str::from_utf8_unchecked(scratch) }.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collapse_all_in_place, collapse_many, collapse_many_contiguous, collapse_many_cow};
+    use crate::collapse_whitespace;
+    use std::borrow::Cow;
+
+    #[test]
+    fn matches_collapse_whitespace_per_row() {
+        let rows = ["", "   ", "  Jane  Doe ", "clean", "こんにちは\t\t世界", &"long  row\t".repeat(50)].map(String::from);
+        let collapsed = collapse_many(&rows);
+        assert_eq!(collapsed.len(), rows.len());
+        for (row, collapsed) in rows.iter().zip(&collapsed) {
+            assert_eq!(*collapsed, collapse_whitespace(row));
+            assert_eq!(collapsed.capacity(), collapsed.len());
+        }
+        assert!(collapse_many::<&str>(&[]).is_empty());
+    }
+
+    #[test]
+    fn contiguous_batch_matches_collapse_many() {
+        let rows = ["", "  Jane  Doe ", "   ", "こんにちは\t\t世界", &"long  row\t".repeat(50)].map(String::from);
+        let batch = collapse_many_contiguous(&rows);
+        assert_eq!(batch.len(), rows.len());
+        assert_eq!(batch.iter().collect::<Vec<_>>(), collapse_many(&rows));
+        assert_eq!(batch.iter().next_back(), batch.get(rows.len() - 1));
+        assert_eq!(batch.get(rows.len()), None);
+        assert_eq!(*batch.offsets().last().unwrap(), batch.as_str().len());
+
+        let empty = collapse_many_contiguous::<&str>(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.into_parts(), (String::new(), vec![0]));
+    }
+
+    #[test]
+    fn in_place_keeps_every_allocation() {
+        let mut rows = ["", "   ", "  Jane  Doe ", "clean", "こんにちは\t\t世界", &"long  row\t".repeat(50)].map(String::from).to_vec();
+        let expected = collapse_many(&rows);
+        let buffers: Vec<(*const u8, usize)> = rows.iter().map(|row| (row.as_ptr(), row.capacity())).collect();
+        collapse_all_in_place(&mut rows);
+        assert_eq!(rows, expected);
+        assert_eq!(rows.iter().map(|row| (row.as_ptr(), row.capacity())).collect::<Vec<_>>(), buffers);
+    }
+
+    #[test]
+    fn cow_borrows_only_clean_rows() {
+        let rows = ["", "   ", "  Jane  Doe ", "clean", "a\tb", "こんにちは 世界", &"long  row\t".repeat(50)].map(String::from);
+        let collapsed = collapse_many_cow(&rows);
+        assert_eq!(collapsed, collapse_many(&rows));
+        let borrowed: Vec<bool> = collapsed.iter().map(|row| matches!(row, Cow::Borrowed(_))).collect();
+        assert_eq!(borrowed, [true, false, false, true, false, true, false]);
+    }
+}
@@ -0,0 +1,68 @@
+//! Whitespace collapsing for `bstr` byte strings, which are conventionally but not
+//! necessarily UTF-8.
+
+use bstr::{BStr, BString};
+
+use crate::kernel;
+
+/// Extension trait collapsing whitespace in `bstr` byte strings and byte slices.
+///
+/// Only spaces and tabs are inspected and every other byte is kept as it is, so text
+/// with invalid UTF-8 in it collapses like the rest, its invalid bytes untouched.
+///
+/// # Example
+/// ```
+/// use bstr::{BString, ByteSlice};
+/// use fast_whitespace_collapse::ByteSliceCollapseWhitespaceExt;
+///
+/// let line = b"  name:\t\xffJane  Doe ".as_bstr();
+/// assert_eq!(line.collapse_whitespace(), BString::from(&b"name: \xffJane Doe"[..]));
+/// assert_eq!(b" a \t b".collapse_whitespace(), "a b");
+/// ```
+pub trait ByteSliceCollapseWhitespaceExt {
+    /// Collapses consecutive spaces and tabs into a single space and trims them at both
+    /// ends, like [`collapse_whitespace`](crate::collapse_whitespace).
+    ///
+    /// # Performance
+    /// - Uses the SIMD kernel of `collapse_whitespace`, which never validates UTF-8.
+    fn collapse_whitespace(&self) -> BString;
+}
+
+impl ByteSliceCollapseWhitespaceExt for [u8] {
+    fn collapse_whitespace(&self) -> BString {
+        BString::from(kernel::collapse_bytes(self))
+    }
+}
+
+impl ByteSliceCollapseWhitespaceExt for BStr {
+    fn collapse_whitespace(&self) -> BString {
+        let bytes: &[u8] = self;
+        bytes.collapse_whitespace()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bstr::{BString, ByteSlice};
+
+    use super::ByteSliceCollapseWhitespaceExt;
+    use crate::collapse_whitespace;
+
+    #[test]
+    fn matches_collapse_whitespace_on_utf8() {
+        for text in ["", "   ", "clean text", "  Hello  \t world ", "こんにちは\t\t世界 "] {
+            assert_eq!(text.as_bytes().collapse_whitespace(), collapse_whitespace(text), "{text:?}");
+            assert_eq!(text.as_bytes().as_bstr().collapse_whitespace(), collapse_whitespace(text), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn keeps_invalid_utf8() {
+        let bytes = b"\t\xc3  \x80 \xff\xfe\t\t end \xe3\x81 ";
+        assert_eq!(bytes.collapse_whitespace(), BString::from(&b"\xc3 \x80 \xff\xfe end \xe3\x81"[..]));
+
+        let long = bytes.repeat(50);
+        let expected: Vec<&[u8]> = long.fields_with(|c| c == ' ' || c == '\t').collect();
+        assert_eq!(long.collapse_whitespace(), bstr::join(" ", expected));
+    }
+}
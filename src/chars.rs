@@ -0,0 +1,103 @@
+//! Lazy whitespace collapsing over iterators of `char`s.
+
+use std::iter::FusedIterator;
+
+/// Extension trait collapsing whitespace in any iterator of `char`s.
+///
+/// This is the lazy form of [`collapse_whitespace`](crate::collapse_whitespace), for
+/// text produced by another lazy transformation that never exists as one `&str`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::CollapseWhitespaceExt;
+///
+/// let upper: String = "  shout   \t this  ".chars().map(|c| c.to_ascii_uppercase()).collapse_whitespace().collect();
+/// assert_eq!(upper, "SHOUT THIS");
+/// ```
+pub trait CollapseWhitespaceExt: Iterator<Item = char> + Sized {
+    /// Returns an iterator that collapses consecutive spaces and tabs into a single
+    /// space and skips them at both ends, like `collapse_whitespace`.
+    ///
+    /// # Performance
+    /// - Each `char` is inspected once, and a run of spaces and tabs is only buffered
+    ///   as the one `char` that follows it.
+    fn collapse_whitespace(self) -> CollapseChars<Self> {
+        CollapseChars {
+            chars: self,
+            next: None,
+            started: false,
+        }
+    }
+}
+
+impl<I: Iterator<Item = char>> CollapseWhitespaceExt for I {}
+
+/// Iterator collapsing whitespace in `char`s, created by
+/// [`CollapseWhitespaceExt::collapse_whitespace`].
+#[derive(Debug, Clone)]
+pub struct CollapseChars<I> {
+    chars: I,
+    /// The `char` following a run that was replaced by a space.
+    next: Option<char>,
+    /// A `char` other than a space or tab has been yielded.
+    started: bool,
+}
+
+impl<I: Iterator<Item = char>> Iterator for CollapseChars<I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if let Some(c) = self.next.take() {
+            return Some(c);
+        }
+
+        let mut c = self.chars.next()?;
+        if c == ' ' || c == '\t' {
+            // Skip the run; it is only written between two other `char`s
+            c = self.chars.by_ref().find(|&c| c != ' ' && c != '\t')?;
+            if self.started {
+                self.next = Some(c);
+                return Some(' ');
+            }
+        }
+        self.started = true;
+        Some(c)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, upper) = self.chars.size_hint();
+        let pending = usize::from(self.next.is_some());
+        (pending, upper.and_then(|upper| upper.checked_add(pending)))
+    }
+}
+
+impl<I: FusedIterator<Item = char>> FusedIterator for CollapseChars<I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::CollapseWhitespaceExt;
+    use crate::collapse_whitespace;
+
+    #[test]
+    fn matches_collapse_whitespace() {
+        let inputs = [
+            "",
+            " \t ",
+            "x",
+            "This   is \t  a   test.",
+            "  Leading and trailing  ",
+            "Line1\n   Line2 \t\nLine3",
+            "こんにちは\t\t世界 😀  😃\u{3000} ",
+        ];
+        for input in inputs {
+            let collapsed: String = input.chars().collapse_whitespace().collect();
+            assert_eq!(collapsed, collapse_whitespace(input), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn is_lazy() {
+        let mut chars = "a   b".chars().chain(std::iter::repeat('c')).collapse_whitespace();
+        assert_eq!(chars.by_ref().take(4).collect::<String>(), "a bc");
+    }
+}
@@ -0,0 +1,143 @@
+//! `clap` value parsers for arguments that arrive already collapsed.
+
+use std::ffi::OsString;
+
+use clap::builder::{StringValueParser, TypedValueParser, ValueParserFactory};
+use clap::{Arg, Command, Error};
+
+use crate::{CollapsedString, Collapser};
+
+/// A `clap` value parser collapsing whitespace in the values of an argument, into a
+/// `String`.
+///
+/// Values are collapsed like [`collapse_whitespace`](crate::collapse_whitespace), or by
+/// the [`Collapser`] set with [`collapser`](Self::collapser). Values that are not valid
+/// UTF-8 are rejected like by clap's own `String` parser.
+///
+/// # Example
+/// ```
+/// use clap::{Arg, Command};
+/// use fast_whitespace_collapse::CollapseValueParser;
+///
+/// let command = Command::new("post").arg(Arg::new("title").long("title").value_parser(CollapseValueParser::new()));
+/// let matches = command.get_matches_from(["post", "--title", "  Hello \t world "]);
+/// assert_eq!(matches.get_one::<String>("title").unwrap(), "Hello world");
+/// ```
+///
+/// With clap's derive API, the parser is given as `#[arg(value_parser = CollapseValueParser::new())]`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CollapseValueParser {
+    collapser: Collapser,
+}
+
+impl CollapseValueParser {
+    /// Creates a parser collapsing spaces and tabs in every value.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the collapser the values go through, such as [`Collapser::single_line`].
+    pub fn collapser(mut self, collapser: Collapser) -> Self {
+        self.collapser = collapser;
+        self
+    }
+}
+
+impl TypedValueParser for CollapseValueParser {
+    type Value = String;
+
+    fn parse_ref(&self, cmd: &Command, arg: Option<&Arg>, value: &std::ffi::OsStr) -> Result<String, Error> {
+        self.parse(cmd, arg, value.to_owned())
+    }
+
+    fn parse(&self, cmd: &Command, arg: Option<&Arg>, value: OsString) -> Result<String, Error> {
+        let value = StringValueParser::new().parse(cmd, arg, value)?;
+        Ok(self.collapser.collapse(&value))
+    }
+}
+
+/// A `clap` value parser collapsing whitespace in the values of an argument, into a
+/// [`CollapsedString`].
+///
+/// It is the parser clap picks for `CollapsedString` arguments, so with clap's derive
+/// API a field of that type needs no `value_parser` attribute.
+///
+/// # Example
+/// ```
+/// use clap::{value_parser, Arg, Command};
+/// use fast_whitespace_collapse::CollapsedString;
+///
+/// let command = Command::new("post").arg(Arg::new("title").long("title").value_parser(value_parser!(CollapsedString)));
+/// let matches = command.get_matches_from(["post", "--title", "  Hello \t world "]);
+/// assert_eq!(matches.get_one::<CollapsedString>("title").unwrap().as_str(), "Hello world");
+/// ```
+///
+/// # Performance
+/// - Keeps the allocation of values that are already collapsed, like
+///   `CollapsedString::from(String)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CollapsedStringValueParser;
+
+impl CollapsedStringValueParser {
+    /// Creates a parser collapsing every value into a `CollapsedString`.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl TypedValueParser for CollapsedStringValueParser {
+    type Value = CollapsedString;
+
+    fn parse_ref(&self, cmd: &Command, arg: Option<&Arg>, value: &std::ffi::OsStr) -> Result<CollapsedString, Error> {
+        self.parse(cmd, arg, value.to_owned())
+    }
+
+    fn parse(&self, cmd: &Command, arg: Option<&Arg>, value: OsString) -> Result<CollapsedString, Error> {
+        Ok(CollapsedString::from(StringValueParser::new().parse(cmd, arg, value)?))
+    }
+}
+
+impl ValueParserFactory for CollapsedString {
+    type Parser = CollapsedStringValueParser;
+
+    fn value_parser() -> CollapsedStringValueParser {
+        CollapsedStringValueParser::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{value_parser, Arg, ArgAction, Command};
+
+    use super::CollapseValueParser;
+    use crate::{CollapsedString, Collapser};
+
+    #[test]
+    fn collapses_string_values() {
+        let command = Command::new("app")
+            .arg(Arg::new("title").long("title").value_parser(CollapseValueParser::new()))
+            .arg(Arg::new("body").long("body").value_parser(CollapseValueParser::new().collapser(Collapser::single_line())));
+        let matches = command.try_get_matches_from(["app", "--title", "  a \t b\n  c ", "--body", "  a\n\n  b "]).unwrap();
+        assert_eq!(matches.get_one::<String>("title").unwrap(), "a b\n c");
+        assert_eq!(matches.get_one::<String>("body").unwrap(), "a b");
+    }
+
+    #[test]
+    fn collapses_into_collapsed_strings() {
+        let command = Command::new("app").arg(Arg::new("tag").long("tag").action(ArgAction::Append).value_parser(value_parser!(CollapsedString)));
+        let matches = command.try_get_matches_from(["app", "--tag", " rust  lang ", "--tag", "clean"]).unwrap();
+        let tags: Vec<&str> = matches.get_many::<CollapsedString>("tag").unwrap().map(CollapsedString::as_str).collect();
+        assert_eq!(tags, ["rust lang", "clean"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_invalid_utf8() {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+
+        let command = Command::new("app").arg(Arg::new("title").long("title").value_parser(CollapseValueParser::new()));
+        let value = OsString::from_vec(vec![b'a', 0xff]);
+        assert!(command.try_get_matches_from([OsString::from("app"), OsString::from("--title"), value]).is_err());
+    }
+}
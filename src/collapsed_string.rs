@@ -0,0 +1,195 @@
+//! A string type that is always collapsed.
+
+use std::fmt;
+use std::ops::Deref;
+
+use crate::kernel;
+
+/// A `String` whose whitespace is always collapsed, as by
+/// [`collapse_whitespace`](crate::collapse_whitespace).
+///
+/// Every way of making or changing one collapses its input, so APIs can take or return
+/// a `CollapsedString` to state in their types that a string is normalized. It derefs
+/// to `str`, and with the `serde` feature it serializes as a string and collapses the
+/// strings it is deserialized from.
+///
+/// It is built from a string with `From`, or from string pieces: every piece is
+/// collapsed and trimmed, and the pieces that are not empty afterwards are separated by
+/// exactly one space. Collecting tokens or extending it with fields thus gives a
+/// correctly single-spaced result directly, whatever empty or blank fragments are among
+/// them.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::CollapsedString;
+///
+/// let name = CollapsedString::from("  Jane \t Doe ");
+/// assert_eq!(name.to_string(), "Jane Doe");
+/// assert!(name.starts_with("Jane"));
+///
+/// let tokens = ["  The ", "", "quick\t brown", " \t ", "fox  "];
+/// let mut sentence: CollapsedString = tokens.iter().copied().collect();
+/// assert_eq!(sentence.as_str(), "The quick brown fox");
+/// sentence.extend(["  jumps  ", "over"]);
+/// assert_eq!(sentence.as_str(), "The quick brown fox jumps over");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CollapsedString(String);
+
+impl CollapsedString {
+    /// Creates an empty `CollapsedString`.
+    pub const fn new() -> Self {
+        Self(String::new())
+    }
+
+    /// Wraps `string`, which the caller has already collapsed.
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_collapsed(string: String) -> Self {
+        Self(string)
+    }
+
+    /// Collapses `piece` and appends it, separated from the current content by one space.
+    ///
+    /// A piece that is empty once collapsed leaves the string unchanged.
+    ///
+    /// # Performance
+    /// - The piece is collapsed with the SIMD kernel of `collapse_whitespace`, straight
+    ///   into the string.
+    pub fn push_piece(&mut self, piece: &str) {
+        // Safety: The kernel only ever removes spaces/tabs or writes a space
+        let bytes = unsafe { self.0.as_mut_vec() };
+        let start = bytes.len();
+        if start > 0 {
+            bytes.push(b' ');
+        }
+        let piece_start = bytes.len();
+        kernel::collapse_bytes_into(piece.as_bytes(), bytes);
+        if bytes.len() == piece_start {
+            // The piece was blank, so it gets no separator either
+            bytes.truncate(start);
+        }
+    }
+
+    /// Returns the collapsed content as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the collapsed content as a `String`.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl From<&str> for CollapsedString {
+    fn from(string: &str) -> Self {
+        Self(crate::collapse_whitespace(string))
+    }
+}
+
+impl From<String> for CollapsedString {
+    /// Collapses `string`, keeping its allocation if it is already collapsed.
+    fn from(string: String) -> Self {
+        if kernel::is_collapsed(string.as_bytes()) {
+            Self(string)
+        } else {
+            Self::from(string.as_str())
+        }
+    }
+}
+
+impl From<CollapsedString> for String {
+    fn from(string: CollapsedString) -> Self {
+        string.0
+    }
+}
+
+impl Deref for CollapsedString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for CollapsedString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for CollapsedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<'a> FromIterator<&'a str> for CollapsedString {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(pieces: I) -> Self {
+        let mut result = Self::new();
+        result.extend(pieces);
+        result
+    }
+}
+
+impl FromIterator<String> for CollapsedString {
+    fn from_iter<I: IntoIterator<Item = String>>(pieces: I) -> Self {
+        let mut result = Self::new();
+        result.extend(pieces);
+        result
+    }
+}
+
+impl<'a> Extend<&'a str> for CollapsedString {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, pieces: I) {
+        for piece in pieces {
+            self.push_piece(piece);
+        }
+    }
+}
+
+impl Extend<String> for CollapsedString {
+    fn extend<I: IntoIterator<Item = String>>(&mut self, pieces: I) {
+        for piece in pieces {
+            self.push_piece(&piece);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CollapsedString;
+
+    #[test]
+    fn collects_single_spaced_pieces() {
+        let collect = |pieces: &[&str]| pieces.iter().copied().collect::<CollapsedString>().into_string();
+        assert_eq!(collect(&[]), "");
+        assert_eq!(collect(&["", " \t ", ""]), "");
+        assert_eq!(collect(&[" a  b", "", "c ", " ", "\td"]), "a b c d");
+        assert_eq!(collect(&["  こんにちは\t", "\t世界  "]), "こんにちは 世界");
+    }
+
+    #[test]
+    fn extends_after_blank_pieces() {
+        let mut string: CollapsedString = vec![String::from("  "), String::from("x")].into_iter().collect();
+        string.extend(["", "  y  z "]);
+        string.extend(vec![String::from("\t")]);
+        string.push_piece(" w");
+        assert_eq!(string.as_str(), "x y z w");
+    }
+
+    #[test]
+    fn collapses_on_construction() {
+        assert_eq!(CollapsedString::from("  a \t b ").as_str(), "a b");
+        assert_eq!(CollapsedString::from(" \t ").as_str(), "");
+
+        let clean = String::from("already clean");
+        let pointer = clean.as_ptr();
+        let string = CollapsedString::from(clean);
+        assert_eq!(string.as_ptr(), pointer);
+        assert_eq!(String::from(CollapsedString::from(String::from("x  y"))), "x y");
+
+        assert_eq!(format!("[{string}]"), "[already clean]");
+        assert_eq!(string.len(), 13);
+    }
+}
@@ -0,0 +1,1737 @@
+//! A configurable whitespace collapser.
+
+use crate::grapheme::{joining, Joining};
+use crate::scan::{plain_prefix_len, Stops};
+use crate::unicode::{
+    decode, first_char, incomplete_suffix_len, is_bidi_control, is_confusable_space, is_typographic_space,
+    is_unicode_whitespace, is_zero_width, last_char, utf8_len,
+};
+
+/// A configurable whitespace collapser.
+///
+/// `Collapser` is a small builder: [`Collapser::new`] behaves exactly like
+/// [`collapse_whitespace`](crate::collapse_whitespace), and each option switches on
+/// extra processing that runs in the same pass over the input.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::Collapser;
+/// let collapser = Collapser::new().normalize_line_endings(true);
+/// assert_eq!(collapser.collapse("One  \t line\r\nTwo   lines\r"), "One line\nTwo lines\n");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collapser {
+    line_breaks: LineBreaks,
+    collapse_spaces: bool,
+    trim_line_ends: bool,
+    normalize_line_endings: bool,
+    squeeze_blank_lines: bool,
+    preserve_indentation: bool,
+    unicode_whitespace: bool,
+    nbsp: bool,
+    nbsp_to_space: bool,
+    ideographic_space: bool,
+    typographic_spaces: bool,
+    confusable_spaces: bool,
+    strip_zero_width: bool,
+    strip_bidi_controls: bool,
+    line_separators: Option<LineSeparators>,
+    normalize_commas: bool,
+    quoted_strings: bool,
+    strip_control_chars: bool,
+    indentation_tab_width: Option<usize>,
+    trailing_newline: TrailingNewline,
+    preserve_line_count: bool,
+    separator: char,
+}
+
+impl Collapser {
+    /// Creates a collapser with the default behaviour of
+    /// [`collapse_whitespace`](crate::collapse_whitespace).
+    pub const fn new() -> Self {
+        Self {
+            line_breaks: LineBreaks::Preserve,
+            collapse_spaces: true,
+            trim_line_ends: false,
+            normalize_line_endings: false,
+            squeeze_blank_lines: false,
+            preserve_indentation: false,
+            unicode_whitespace: false,
+            nbsp: false,
+            nbsp_to_space: false,
+            ideographic_space: false,
+            typographic_spaces: false,
+            confusable_spaces: false,
+            strip_zero_width: false,
+            strip_bidi_controls: false,
+            line_separators: None,
+            normalize_commas: false,
+            quoted_strings: false,
+            strip_control_chars: false,
+            indentation_tab_width: None,
+            trailing_newline: TrailingNewline::Keep,
+            preserve_line_count: false,
+            separator: ' ',
+        }
+    }
+
+    /// Creates a collapser that flattens all line breaks, producing a single line.
+    ///
+    /// `\n`, `\r`, spaces and tabs are all treated as one collapsible class, which is
+    /// what log messages and HTML attribute values need.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::single_line();
+    /// assert_eq!(collapser.collapse("  Multi-line\r\n  message \n\n\twith   gaps\n"), "Multi-line message with gaps");
+    /// ```
+    pub const fn single_line() -> Self {
+        Self::new().line_breaks(LineBreaks::Collapse)
+    }
+
+    /// Creates a collapser for security normalization of usernames and identifiers.
+    ///
+    /// Every Unicode whitespace character, every [confusable space](Self::confusable_spaces)
+    /// and every line break collapses to a single ASCII space, and
+    /// [zero-width characters](Self::strip_zero_width) and
+    /// [bidi controls](Self::strip_bidi_controls) are dropped, so visually identical
+    /// variants of a name normalize to the same string.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::security();
+    /// assert_eq!(collapser.collapse("\u{1680}admin\u{3164}\u{200b}user\u{2003}"), "admin user");
+    /// ```
+    pub const fn security() -> Self {
+        Self::single_line()
+            .unicode_whitespace(true)
+            .confusable_spaces(true)
+            .strip_zero_width(true)
+            .strip_bidi_controls(true)
+    }
+
+    /// Creates a collapser that canonicalizes HTTP field values as defined by RFC 9110.
+    ///
+    /// Optional whitespace (OWS) is trimmed and collapsed, the separators of list-valued
+    /// fields are [normalized](Self::normalize_commas) to `", "`, and
+    /// [quoted strings](Self::quoted_strings) are kept verbatim. Obsolete line folding
+    /// is replaced by a space, so equivalent values compare equal and can be used as
+    /// cache keys.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::http_field_value();
+    /// assert_eq!(collapser.collapse(" gzip ,deflate\t,  br "), "gzip, deflate, br");
+    /// assert_eq!(collapser.collapse("W/\"a  , b\" ,  \"c\""), "W/\"a  , b\", \"c\"");
+    /// ```
+    pub const fn http_field_value() -> Self {
+        Self::single_line().normalize_commas(true).quoted_strings(true)
+    }
+
+    /// Creates a collapser that sanitizes log messages into a single line.
+    ///
+    /// Spaces and tabs collapse as usual, line breaks are [escaped](LineBreaks::Escape)
+    /// as a literal `\n` so one event stays on one line, and
+    /// [control characters](Self::strip_control_chars) are dropped so messages cannot
+    /// inject terminal escapes or fake log lines.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::log_line();
+    /// let message = "request  failed:\r\n\tcaused by \x1b[31mtimeout\x1b[0m\n";
+    /// assert_eq!(collapser.collapse(message), "request failed:\\n caused by [31mtimeout[0m");
+    /// ```
+    pub const fn log_line() -> Self {
+        Self::new().line_breaks(LineBreaks::Escape).strip_control_chars(true)
+    }
+
+    /// Creates a collapser that renders text the way browsers do with the CSS default
+    /// `white-space: normal`.
+    ///
+    /// Spaces, tabs and line breaks collapse to a single space and are trimmed from the
+    /// ends, while U+00A0 NO-BREAK SPACE (`&nbsp;`) and every other character are kept,
+    /// as they are rendered. Text extracted from a DOM then reads as it is shown on the
+    /// page.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::html_text();
+    /// let text = "\n    Price:\u{a0}\u{a0}10 €\n\t  <b>incl.</b>  VAT\n  ";
+    /// assert_eq!(collapser.collapse(text), "Price:\u{a0}\u{a0}10 € <b>incl.</b> VAT");
+    /// ```
+    pub const fn html_text() -> Self {
+        Self::single_line()
+    }
+
+    /// Sets how line breaks (`\n`, `\r\n` and lone `\r`) are treated.
+    ///
+    /// Defaults to [`LineBreaks::Preserve`].
+    pub const fn line_breaks(mut self, line_breaks: LineBreaks) -> Self {
+        self.line_breaks = line_breaks;
+        self
+    }
+
+    /// Sets how U+2028 LINE SEPARATOR and U+2029 PARAGRAPH SEPARATOR are treated.
+    ///
+    /// [`LineSeparators::Newline`] handles them exactly like `\n`, so they follow the
+    /// configured [line break](Self::line_breaks) policy. When unset they are kept, unless
+    /// [`unicode_whitespace`](Self::unicode_whitespace) collapses them as spaces.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::{Collapser, LineSeparators};
+    /// let collapser = Collapser::new().line_separators(LineSeparators::Newline);
+    /// assert_eq!(collapser.collapse("first  line\u{2028}second\u{2029}"), "first line\nsecond\n");
+    /// let collapser = Collapser::new().line_separators(LineSeparators::Space);
+    /// assert_eq!(collapser.collapse("first\u{2028}\u{2029}second"), "first second");
+    /// ```
+    pub const fn line_separators(mut self, line_separators: LineSeparators) -> Self {
+        self.line_separators = Some(line_separators);
+        self
+    }
+
+    /// Collapses runs of spaces and tabs into a single space and trims both ends.
+    ///
+    /// Enabled by default. When disabled, spaces and tabs are copied through verbatim,
+    /// which is useful together with [`trim_line_ends`](Self::trim_line_ends) or the
+    /// line ending options.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().collapse_spaces(false).trim_line_ends(true);
+    /// assert_eq!(collapser.collapse("  keep   this \t\nalone  "), "  keep   this\nalone");
+    /// ```
+    pub const fn collapse_spaces(mut self, enabled: bool) -> Self {
+        self.collapse_spaces = enabled;
+        self
+    }
+
+    /// Removes spaces and tabs immediately before every line break.
+    ///
+    /// Disabled by default, in which case a run before a line break collapses to a
+    /// single space like any other run.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().trim_line_ends(true);
+    /// assert_eq!(collapser.collapse("First   line  \t\nSecond \r\n"), "First line\nSecond\r\n");
+    /// ```
+    pub const fn trim_line_ends(mut self, enabled: bool) -> Self {
+        self.trim_line_ends = enabled;
+        self
+    }
+
+    /// Treats every other [`char::is_whitespace`] character as a collapsible space.
+    ///
+    /// This covers vertical tab, form feed and non-ASCII spaces such as U+00A0 NO-BREAK
+    /// SPACE, U+2003 EM SPACE and U+3000 IDEOGRAPHIC SPACE. Line breaks still follow the
+    /// [`line_breaks`](Self::line_breaks) policy.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().unicode_whitespace(true);
+    /// assert_eq!(collapser.collapse("Hello\u{a0}\u{2003} world\u{3000}"), "Hello world");
+    /// ```
+    pub const fn unicode_whitespace(mut self, enabled: bool) -> Self {
+        self.unicode_whitespace = enabled;
+        self
+    }
+
+    /// Treats U+00A0 NO-BREAK SPACE as a collapsible space.
+    ///
+    /// Scraped HTML keeps `&nbsp;` as U+00A0, which otherwise survives collapsing. Its
+    /// two-byte encoding is matched by the SIMD scanner, so text without NBSP keeps
+    /// running at full speed. Implied by [`unicode_whitespace`](Self::unicode_whitespace).
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().nbsp(true);
+    /// assert_eq!(collapser.collapse("Price:\u{a0}\u{a0} 10\u{a0}€"), "Price: 10 €");
+    /// ```
+    pub const fn nbsp(mut self, enabled: bool) -> Self {
+        self.nbsp = enabled;
+        self
+    }
+
+    /// Rewrites every U+00A0 NO-BREAK SPACE to an ASCII space.
+    ///
+    /// Each NBSP is then handled exactly like an ASCII space in the input. Unlike
+    /// [`nbsp`](Self::nbsp), this also applies where whitespace is kept verbatim, such as
+    /// with [`collapse_spaces(false)`](Self::collapse_spaces) or preserved indentation,
+    /// so tokenizers splitting on ASCII space always see one.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().collapse_spaces(false).nbsp_to_space(true);
+    /// assert_eq!(collapser.collapse("10\u{a0}km \u{a0}away"), "10 km  away");
+    /// ```
+    pub const fn nbsp_to_space(mut self, enabled: bool) -> Self {
+        self.nbsp_to_space = enabled;
+        self
+    }
+
+    /// Treats U+3000 IDEOGRAPHIC SPACE as a collapsible space.
+    ///
+    /// Japanese and Chinese text pads with U+3000; with this enabled, any run containing
+    /// it collapses to a single [`separator`](Self::separator). The three-byte sequence
+    /// is matched correctly even when it straddles a SIMD chunk. Implied by
+    /// [`unicode_whitespace`](Self::unicode_whitespace).
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().ideographic_space(true);
+    /// assert_eq!(collapser.collapse("\u{3000}東京\u{3000}\u{3000} 大阪"), "東京 大阪");
+    /// ```
+    pub const fn ideographic_space(mut self, enabled: bool) -> Self {
+        self.ideographic_space = enabled;
+        self
+    }
+
+    /// Treats the typographic spaces of the General Punctuation block as collapsible.
+    ///
+    /// This covers U+2000..=U+200A (en, em, thin, hair spaces and friends), U+202F
+    /// NARROW NO-BREAK SPACE and U+205F MEDIUM MATHEMATICAL SPACE, which typesetting
+    /// tools emit between words. Implied by [`unicode_whitespace`](Self::unicode_whitespace).
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().typographic_spaces(true);
+    /// assert_eq!(collapser.collapse("10\u{202f}000 \u{2009}items"), "10 000 items");
+    /// ```
+    pub const fn typographic_spaces(mut self, enabled: bool) -> Self {
+        self.typographic_spaces = enabled;
+        self
+    }
+
+    /// Treats characters that render as blank space as collapsible spaces.
+    ///
+    /// This covers U+1680 OGHAM SPACE MARK, U+180E MONGOLIAN VOWEL SEPARATOR, the Hangul
+    /// fillers (U+115F, U+1160, U+3164, U+FFA0) and U+2800 BRAILLE PATTERN BLANK. Most
+    /// are not [`char::is_whitespace`], but look identical to a space and are used to
+    /// smuggle variants past whitespace normalization.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().confusable_spaces(true);
+    /// assert_eq!(collapser.collapse("john\u{3164}\u{2800}smith"), "john smith");
+    /// ```
+    pub const fn confusable_spaces(mut self, enabled: bool) -> Self {
+        self.confusable_spaces = enabled;
+        self
+    }
+
+    /// Drops U+200B ZERO WIDTH SPACE, U+2060 WORD JOINER and U+FEFF (BOM) while collapsing.
+    ///
+    /// These characters are invisible but break equality checks. Dropping one never
+    /// splits a whitespace run, so `"a \u{200b} b"` still collapses to `"a b"`.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().strip_zero_width(true);
+    /// assert_eq!(collapser.collapse("\u{feff}zero\u{200b}width \u{2060} text"), "zerowidth text");
+    /// ```
+    pub const fn strip_zero_width(mut self, enabled: bool) -> Self {
+        self.strip_zero_width = enabled;
+        self
+    }
+
+    /// Drops the bidirectional embedding, override and isolate controls while collapsing.
+    ///
+    /// Removes U+202A..=U+202E and U+2066..=U+2069, which can reorder rendered text to
+    /// spoof file names or URLs in user-generated content. Like zero-width characters,
+    /// dropping one never splits a whitespace run.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().strip_bidi_controls(true);
+    /// assert_eq!(collapser.collapse("invoice\u{202e}fdp.exe  \u{2066}x\u{2069}"), "invoicefdp.exe x");
+    /// ```
+    pub const fn strip_bidi_controls(mut self, enabled: bool) -> Self {
+        self.strip_bidi_controls = enabled;
+        self
+    }
+
+    /// Writes every comma as `", "`, dropping the whitespace before it.
+    ///
+    /// This canonicalizes comma-separated lists such as HTTP list-valued fields, so
+    /// `"a,b"`, `"a , b"` and `"a,\tb"` all become `"a, b"`. A trailing comma is not
+    /// followed by a space.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().normalize_commas(true);
+    /// assert_eq!(collapser.collapse("text/html ,application/xhtml+xml,  */*"), "text/html, application/xhtml+xml, */*");
+    /// ```
+    pub const fn normalize_commas(mut self, enabled: bool) -> Self {
+        self.normalize_commas = enabled;
+        self
+    }
+
+    /// Copies double-quoted strings through verbatim.
+    ///
+    /// Whitespace and commas between a `"` and the next unescaped `"` are left alone.
+    /// A backslash escapes the character after it, as in RFC 9110 quoted strings.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().quoted_strings(true);
+    /// assert_eq!(collapser.collapse(r#"name  =  "  two \"  spaces ""#), r#"name = "  two \"  spaces ""#);
+    /// ```
+    pub const fn quoted_strings(mut self, enabled: bool) -> Self {
+        self.quoted_strings = enabled;
+        self
+    }
+
+    /// Drops control characters while collapsing.
+    ///
+    /// Removes the C0 controls other than tab and the line breaks, DEL and the C1
+    /// controls U+0080..=U+009F. Vertical tab and form feed are collapsed instead when
+    /// [`unicode_whitespace`](Self::unicode_whitespace) is enabled. Like zero-width
+    /// characters, dropping one never splits a whitespace run.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().strip_control_chars(true);
+    /// assert_eq!(collapser.collapse("bell\x07  \x00null\x7f\u{9b}"), "bell null");
+    /// ```
+    pub const fn strip_control_chars(mut self, enabled: bool) -> Self {
+        self.strip_control_chars = enabled;
+        self
+    }
+
+    /// Sets the character each collapsed run is replaced with.
+    ///
+    /// Defaults to an ASCII space. Whitespace that is kept verbatim, such as preserved
+    /// indentation, is not affected.
+    ///
+    /// Output never contains broken grapheme clusters:
+    /// - The separator is only written between complete clusters.
+    /// - A whitespace character carrying combining marks or a ZWJ forms a cluster of its
+    ///   own, and is kept verbatim instead of being collapsed, so the marks never move
+    ///   onto the separator or the preceding word. Marks following a collapsed line
+    ///   break get a whitespace character of the run, or a space, as their base in place
+    ///   of the separator.
+    /// - Where the separator would join the preceding cluster (after a `Prepend`
+    ///   character, or a pictographic separator after a ZWJ), the first whitespace
+    ///   character of the run is written instead.
+    ///
+    /// # Panics
+    /// Panics if `separator` always joins a neighbouring cluster: combining marks, ZWJ,
+    /// `Prepend` characters, regional indicators and Hangul jamo or syllables.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().ideographic_space(true).separator('\u{3000}');
+    /// assert_eq!(collapser.collapse("東京 \u{3000}\t大阪"), "東京\u{3000}大阪");
+    /// ```
+    pub const fn separator(mut self, separator: char) -> Self {
+        assert!(
+            matches!(joining(separator), Joining::None | Joining::Pictographic),
+            "separator must not join neighbouring grapheme clusters"
+        );
+        self.separator = separator;
+        self
+    }
+
+    /// Rewrites `\r\n` and lone `\r` line endings to `\n` while collapsing.
+    ///
+    /// Disabled by default, in which case `\r` is copied through untouched.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().normalize_line_endings(true);
+    /// assert_eq!(collapser.collapse("a\r\nb\rc\n"), "a\nb\nc\n");
+    /// ```
+    pub const fn normalize_line_endings(mut self, enabled: bool) -> Self {
+        self.normalize_line_endings = enabled;
+        self
+    }
+
+    /// Squeezes three or more consecutive line breaks down to a single blank line.
+    ///
+    /// Lines containing only spaces and tabs count as blank and lose that whitespace.
+    /// `\r\n` and lone `\r` are each counted as one line break and written unchanged,
+    /// unless [`normalize_line_endings`](Self::normalize_line_endings) is also enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().squeeze_blank_lines(true);
+    /// assert_eq!(collapser.collapse("Title\n\n\n \t \n\nBody   text"), "Title\n\nBody text");
+    /// ```
+    pub const fn squeeze_blank_lines(mut self, enabled: bool) -> Self {
+        self.squeeze_blank_lines = enabled;
+        self
+    }
+
+    /// Leaves the leading spaces and tabs of every line untouched.
+    ///
+    /// Only runs after the first non-whitespace character of a line are collapsed, which
+    /// keeps the structure of indented code, YAML and similar formats intact. Whitespace
+    /// at the very end of the input is still trimmed.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().preserve_indentation(true);
+    /// assert_eq!(collapser.collapse("root:\n  key:   value\n  \tother:  1"), "root:\n  key: value\n  \tother: 1");
+    /// ```
+    pub const fn preserve_indentation(mut self, enabled: bool) -> Self {
+        self.preserve_indentation = enabled;
+        self
+    }
+
+    /// Keeps the indentation of every line, expanding its tabs to spaces.
+    ///
+    /// Like [`preserve_indentation`](Self::preserve_indentation), the leading whitespace of
+    /// each line is kept while interior runs are collapsed, but every leading tab is
+    /// replaced by spaces up to the next multiple of `tab_width`, so the indentation depth
+    /// renders the same everywhere.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::new().expand_indentation_tabs(4);
+    /// assert_eq!(collapser.collapse("fn main() {\n\tlet  x =\t1;\n  \t}"), "fn main() {\n    let x = 1;\n    }");
+    /// ```
+    pub const fn expand_indentation_tabs(mut self, tab_width: usize) -> Self {
+        self.indentation_tab_width = Some(tab_width);
+        self
+    }
+
+    /// Sets how line breaks at the end of the output are treated.
+    ///
+    /// Defaults to [`TrailingNewline::Keep`]. The other policies first remove every
+    /// trailing line break together with the whitespace around it, which makes the
+    /// output independent of how many line breaks the input ended with.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::{Collapser, TrailingNewline};
+    /// let collapser = Collapser::new().trailing_newline(TrailingNewline::Single);
+    /// assert_eq!(collapser.collapse("last  line \n\r\n\n"), "last line\n");
+    /// assert_eq!(collapser.collapse("last  line"), "last line\n");
+    /// ```
+    pub const fn trailing_newline(mut self, trailing_newline: TrailingNewline) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+
+    /// Guarantees that the output has as many line breaks as the input.
+    ///
+    /// Whitespace is only collapsed within lines. Options that would merge or drop lines
+    /// are overridden: line breaks are always [preserved](LineBreaks::Preserve), blank
+    /// lines are not [squeezed](Self::squeeze_blank_lines), trailing line breaks are
+    /// [kept](TrailingNewline::Keep) and U+2028/U+2029 are never turned into `\n`. Each
+    /// `\n`, `\r\n` and lone `\r` counts as one line break, so
+    /// [normalizing line endings](Self::normalize_line_endings) is still allowed.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::Collapser;
+    /// let collapser = Collapser::single_line().squeeze_blank_lines(true).trim_line_ends(true);
+    /// let collapser = collapser.preserve_line_count(true);
+    /// assert_eq!(collapser.collapse("a  \n\n\n\tb \n"), "a\n\n\n b\n");
+    /// ```
+    pub const fn preserve_line_count(mut self, enabled: bool) -> Self {
+        self.preserve_line_count = enabled;
+        self
+    }
+
+    /// Collapses whitespace in `input` according to the configured options.
+    ///
+    /// # Parameters
+    /// - `input`: A string slice (`&str`) containing text with irregular spacing.
+    ///
+    /// # Returns
+    /// - A `String` with collapsed whitespace.
+    ///
+    /// # Performance
+    /// - Options that only affect the end of the output, such as
+    ///   [`trailing_newline`](Self::trailing_newline), leave the collapsing itself to the
+    ///   SIMD kernels of [`collapse_whitespace`](crate::collapse_whitespace).
+    /// - Every other configuration runs one pass that copies the bytes its options
+    ///   cannot rewrite in bulk, with the set of such bytes worked out once per call.
+    /// - The presets, such as [`single_line`](Self::single_line), run a copy of that pass
+    ///   compiled for their options, which then cost no checks per byte.
+    pub fn collapse(&self, input: &str) -> String {
+        let collapser = self.resolved();
+        if collapser.is_kernel_equivalent() {
+            let mut result = crate::kernel::collapse_bytes(input.as_bytes());
+            collapser.end_output(&mut result, 0, false);
+            // Safety: The kernel only ever removes spaces/tabs or writes a space, and
+            // `end_output` only removes or writes ASCII
+            return unsafe { String::from_utf8_unchecked(result) };
+        }
+
+        let mut state = State::default();
+        let mut result = Vec::with_capacity(input.len());
+        collapser.feed(&mut state, input.as_bytes(), &mut result, true);
+        collapser.finish(&mut state, &mut result);
+        collapser.end_output(&mut result, 0, false);
+
+        // Safety: only whole input sequences and ASCII bytes are written
+        unsafe { String::from_utf8_unchecked(result) }
+    }
+
+    /// Returns a [`StreamCollapser`](crate::StreamCollapser) applying these options to
+    /// input that arrives in chunks.
+    pub fn stream(&self) -> crate::StreamCollapser {
+        crate::StreamCollapser::new(*self)
+    }
+
+    /// Returns the options actually applied, with overrides such as
+    /// [`preserve_line_count`](Self::preserve_line_count) resolved.
+    pub(crate) fn resolved(&self) -> Self {
+        if !self.preserve_line_count {
+            return *self;
+        }
+        Self {
+            line_breaks: LineBreaks::Preserve,
+            squeeze_blank_lines: false,
+            trailing_newline: TrailingNewline::Keep,
+            line_separators: match self.line_separators {
+                Some(LineSeparators::Newline) => None,
+                line_separators => line_separators,
+            },
+            ..*self
+        }
+    }
+
+    /// Returns `true` if, apart from [`end_output`](Self::end_output), the options
+    /// collapse exactly like [`collapse_whitespace`](crate::collapse_whitespace).
+    fn is_kernel_equivalent(&self) -> bool {
+        Self { trailing_newline: TrailingNewline::Keep, preserve_line_count: false, ..*self } == Self::new()
+    }
+
+    /// Processes `bytes`, appending the collapsed output to `out`.
+    ///
+    /// Unless `last` is set, processing stops early at a multi-byte sequence or quoted
+    /// pair that may be continued by more input. Returns the number of bytes processed.
+    ///
+    /// The presets are matched once here and run an instance of the loop compiled for
+    /// their options, so none of those is checked per byte.
+    pub(crate) fn feed(&self, state: &mut State, bytes: &[u8], out: &mut Vec<u8>, last: bool) -> usize {
+        match *self {
+            options if options == Self::single_line() => self.feed_with::<SINGLE_LINE>(state, bytes, out, last),
+            options if options == Self::security() => self.feed_with::<SECURITY>(state, bytes, out, last),
+            options if options == Self::http_field_value() => self.feed_with::<HTTP_FIELD_VALUE>(state, bytes, out, last),
+            options if options == Self::log_line() => self.feed_with::<LOG_LINE>(state, bytes, out, last),
+            _ => self.feed_with::<GENERAL>(state, bytes, out, last),
+        }
+    }
+
+    /// Returns the options the `PRESET` instance of [`feed`](Self::feed) runs with: the
+    /// preset, as a constant, or `self` for [`GENERAL`].
+    #[inline(always)]
+    fn options<const PRESET: u8>(&self) -> Self {
+        match PRESET {
+            SINGLE_LINE => Self::single_line(),
+            SECURITY => Self::security(),
+            HTTP_FIELD_VALUE => Self::http_field_value(),
+            LOG_LINE => Self::log_line(),
+            _ => *self,
+        }
+    }
+
+    /// The byte loop of [`feed`](Self::feed), for the options of `PRESET`.
+    fn feed_with<const PRESET: u8>(&self, state: &mut State, bytes: &[u8], out: &mut Vec<u8>, last: bool) -> usize {
+        let options = self.options::<PRESET>();
+        // A character cut short at the end is never copied as part of a plain run
+        let complete = if last { bytes.len() } else { bytes.len() - incomplete_suffix_len(bytes) };
+        let stops = self.stops::<PRESET>();
+        let mut i = 0;
+        while i < bytes.len() {
+            let after_cr = std::mem::take(&mut state.after_cr);
+
+            // Copy everything that cannot need rewriting in one go
+            let plain = plain_prefix_len(&bytes[i..complete], stops);
+            if plain > 0 {
+                self.keep::<PRESET>(state, out, &bytes[i..i + plain]);
+                i += plain;
+                continue;
+            }
+
+            let b = bytes[i];
+            if b == b'\\' && state.in_quotes {
+                // A quoted pair: the backslash and the escaped character are copied as is
+                let len = 1 + bytes.get(i + 1).map_or(1, |&next| utf8_len(next));
+                if !last && i + len > bytes.len() {
+                    state.after_cr = after_cr;
+                    return i;
+                }
+                let len = len.min(bytes.len() - i);
+                self.keep::<PRESET>(state, out, &bytes[i..i + len]);
+                i += len;
+                continue;
+            }
+            if b >= 0x80 {
+                if !last && i + utf8_len(b) > bytes.len() {
+                    state.after_cr = after_cr;
+                    return i;
+                }
+                let len = utf8_len(b).min(bytes.len() - i);
+                let sequence = &bytes[i..i + len];
+                let line_separator = matches!(sequence, [0xE2, 0x80, 0xA8 | 0xA9]);
+                if self.is_stripped::<PRESET>(sequence) {
+                    // Dropped without affecting the surrounding run
+                } else if line_separator && options.line_separators == Some(LineSeparators::Newline) {
+                    self.ascii::<PRESET>(state, out, b'\n', false);
+                } else if line_separator && options.line_separators == Some(LineSeparators::Space) {
+                    self.whitespace::<PRESET>(state, out, b" ");
+                } else if line_separator && options.line_separators == Some(LineSeparators::Preserve) {
+                    self.keep::<PRESET>(state, out, sequence);
+                } else if options.nbsp_to_space && sequence == "\u{a0}".as_bytes() {
+                    self.whitespace::<PRESET>(state, out, b" ");
+                } else if self.is_space_sequence::<PRESET>(sequence) {
+                    self.whitespace::<PRESET>(state, out, sequence);
+                } else {
+                    self.keep::<PRESET>(state, out, sequence);
+                }
+                i += len;
+                continue;
+            }
+
+            self.ascii::<PRESET>(state, out, b, after_cr);
+            i += 1;
+        }
+        bytes.len()
+    }
+
+    /// Processes the ASCII byte `b`; `after_cr` is set if it directly follows a `\r`.
+    fn ascii<const PRESET: u8>(&self, state: &mut State, out: &mut Vec<u8>, b: u8, after_cr: bool) {
+        let options = self.options::<PRESET>();
+        let byte = std::slice::from_ref(&b);
+        match b {
+            b'\n' | b'\r' if options.line_breaks == LineBreaks::Collapse => {
+                state.pending_space = true;
+                state.run_len += 1;
+                state.run_last = None;
+            }
+            b'\n' | b'\r' if options.line_breaks == LineBreaks::Escape => {
+                // Whitespace before an escaped line break is trimmed
+                state.discard_whitespace();
+                if !(b == b'\n' && after_cr) {
+                    state.escaped_breaks += 1;
+                }
+                state.after_cr = b == b'\r';
+            }
+            b'\n' if options.line_breaks == LineBreaks::Unfold && after_cr => {
+                // Second half of `\r\n`, which is still waiting to be written
+                if state.pending_break == Some(b"\r") {
+                    state.pending_break = Some(b"\r\n");
+                }
+            }
+            b'\n' | b'\r' if options.line_breaks == LineBreaks::Unfold => {
+                self.flush_break::<PRESET>(state, out);
+                state.pending_break = Some(match b {
+                    _ if options.normalize_line_endings => b"\n",
+                    b'\r' => b"\r",
+                    _ => b"\n",
+                });
+                state.after_cr = b == b'\r';
+            }
+            b'\n' | b'\r' if options.line_breaks == LineBreaks::Paragraphs => {
+                state.pending_space = true;
+                state.run_len += 1;
+                state.run_last = None;
+                if !(b == b'\n' && after_cr) {
+                    state.paragraph_breaks += 1;
+                }
+                state.after_cr = b == b'\r';
+            }
+            b' ' | b'\t' => self.whitespace::<PRESET>(state, out, byte),
+            b'\x0b' | b'\x0c' if options.unicode_whitespace => self.whitespace::<PRESET>(state, out, byte),
+            b'\0'..=b'\x1f' | b'\x7f' if options.strip_control_chars && !matches!(b, b'\n' | b'\r') => {
+                // Dropped without affecting the surrounding run
+            }
+            b'"' if options.quoted_strings => {
+                self.keep::<PRESET>(state, out, byte);
+                state.in_quotes = !state.in_quotes;
+            }
+            b',' if options.normalize_commas && !state.in_quotes => {
+                state.discard_whitespace();
+                self.keep::<PRESET>(state, out, byte);
+                state.pending_space = true;
+                state.run_len = 1;
+            }
+            b'\r' if options.normalize_line_endings => {
+                state.cr_written = self.line_break::<PRESET>(state, out, b"\n");
+                state.after_cr = true;
+            }
+            b'\r' if self.tracks_line_breaks::<PRESET>() => {
+                state.cr_written = self.line_break::<PRESET>(state, out, b"\r");
+                state.after_cr = true;
+            }
+            b'\n' if after_cr => {
+                // Second half of `\r\n`, which already counted as one line break
+                if state.cr_written && !options.normalize_line_endings {
+                    out.push(b'\n');
+                }
+            }
+            b'\n' if self.tracks_line_breaks::<PRESET>() => {
+                self.line_break::<PRESET>(state, out, b"\n");
+            }
+            _ => self.keep::<PRESET>(state, out, byte),
+        }
+        if matches!(b, b'\n' | b'\r') && options.line_breaks == LineBreaks::Preserve {
+            state.in_line = false;
+        }
+    }
+
+    /// Returns the bytes that need a closer look, beyond those `<= b' '`.
+    fn stops<const PRESET: u8>(&self) -> Stops {
+        let options = self.options::<PRESET>();
+        Stops {
+            non_ascii: options.unicode_whitespace || options.confusable_spaces,
+            nbsp: options.nbsp || options.nbsp_to_space,
+            ideographic_space: options.ideographic_space,
+            general_punctuation: options.typographic_spaces
+                || options.strip_zero_width
+                || options.strip_bidi_controls
+                || options.line_separators.is_some(),
+            bom: options.strip_zero_width,
+            comma: options.normalize_commas,
+            quote: options.quoted_strings,
+            controls: options.strip_control_chars,
+        }
+    }
+
+    /// Returns `true` if the multi-byte `sequence` is dropped from the output.
+    fn is_stripped<const PRESET: u8>(&self, sequence: &[u8]) -> bool {
+        let options = self.options::<PRESET>();
+        let c = decode(sequence);
+        (options.strip_zero_width && c.is_some_and(is_zero_width))
+            || (options.strip_bidi_controls && c.is_some_and(is_bidi_control))
+            || (options.strip_control_chars && c.is_some_and(char::is_control))
+    }
+
+    /// Returns `true` if the multi-byte `sequence` is a collapsible space.
+    fn is_space_sequence<const PRESET: u8>(&self, sequence: &[u8]) -> bool {
+        let options = self.options::<PRESET>();
+        (options.nbsp && sequence == "\u{a0}".as_bytes())
+            || (options.ideographic_space && sequence == "\u{3000}".as_bytes())
+            || (options.typographic_spaces && decode(sequence).is_some_and(is_typographic_space))
+            || (options.confusable_spaces && decode(sequence).is_some_and(is_confusable_space))
+            || (options.unicode_whitespace && is_unicode_whitespace(sequence))
+    }
+
+    /// Records a whitespace character, either as part of a collapsible run or verbatim.
+    fn whitespace<const PRESET: u8>(&self, state: &mut State, out: &mut Vec<u8>, bytes: &[u8]) {
+        let options = self.options::<PRESET>();
+        if state.in_quotes {
+            return self.keep::<PRESET>(state, out, bytes);
+        }
+        if state.pending_break.is_some() {
+            if state.started {
+                // A continuation line: the line break and the whitespace around it
+                // collapse into one run
+                state.pending_break = None;
+                state.discard_whitespace();
+                state.pending_space = true;
+            } else {
+                self.flush_break::<PRESET>(state, out);
+            }
+        }
+        let indentation = (options.preserve_indentation || options.indentation_tab_width.is_some()) && !state.in_line;
+        if let (Some(tab_width), b"\t", true) = (options.indentation_tab_width, bytes, indentation) {
+            // Indentation is whole characters, so it is valid UTF-8
+            let column = std::str::from_utf8(&state.raw_whitespace).map_or(0, |raw| raw.chars().count());
+            let spaces = if tab_width == 0 { 0 } else { tab_width - column % tab_width };
+            state.raw_whitespace.resize(state.raw_whitespace.len() + spaces, b' ');
+        } else if !options.collapse_spaces || indentation {
+            state.raw_whitespace.extend_from_slice(bytes);
+        } else {
+            state.pending_space = true;
+            if options.separator != ' ' {
+                let c = decode(bytes);
+                state.run_first = state.run_first.or(c);
+                state.run_last = c;
+                state.run_len += 1;
+            }
+        }
+    }
+
+    /// Writes `bytes` to `out`, preceded by any whitespace waiting to be written.
+    fn keep<const PRESET: u8>(&self, state: &mut State, out: &mut Vec<u8>, bytes: &[u8]) {
+        let options = self.options::<PRESET>();
+        self.flush_break::<PRESET>(state, out);
+        if state.paragraph_breaks > 1 && state.started {
+            // Whitespace around a paragraph break is dropped with it
+            state.discard_whitespace();
+            out.extend_from_slice(b"\n\n");
+        }
+        state.paragraph_breaks = 0;
+        if state.escaped_breaks > 0 && state.started {
+            out.extend(b"\\n".repeat(state.escaped_breaks));
+        }
+        state.escaped_breaks = 0;
+        out.append(&mut state.raw_whitespace);
+        if state.pending_space {
+            if options.separator == ' ' {
+                if state.started {
+                    out.push(b' ');
+                }
+            } else {
+                self.write_separator::<PRESET>(state, out, bytes);
+            }
+            state.pending_space = false;
+        }
+        out.extend_from_slice(bytes);
+        if options.separator != ' ' {
+            state.last_char = last_char(bytes).or(state.last_char);
+        }
+        state.started = true;
+        state.in_line = true;
+        state.line_breaks = 0;
+    }
+
+    /// Writes the pending run as a custom separator without breaking grapheme clusters.
+    ///
+    /// `next` is the output that follows the run.
+    fn write_separator<const PRESET: u8>(&self, state: &mut State, out: &mut Vec<u8>, next: &[u8]) {
+        let options = self.options::<PRESET>();
+        let mut push = |c: char| out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+
+        // The last whitespace character of the run is the base of any marks that follow.
+        // A line break cannot be one, so after it a whitespace character of the run is
+        // written in place of the separator instead, to keep the marks off it
+        let extended = first_char(next).is_some_and(|c| joining(c) == Joining::Extend);
+        let (carrier, collapsed) = match state.run_last {
+            Some(c) if extended => (Some(c), state.run_len - 1),
+            None if extended && state.started => (Some(state.run_first.unwrap_or(' ')), 0),
+            _ => (None, state.run_len),
+        };
+
+        if state.started && collapsed > 0 {
+            let joins = match state.last_char {
+                Some(c) if joining(c) == Joining::Prepend => true,
+                Some('\u{200d}') => joining(options.separator) == Joining::Pictographic,
+                _ => false,
+            };
+            push(if joins { state.run_first.unwrap_or(' ') } else { options.separator });
+        }
+        if let Some(c) = carrier {
+            push(c);
+        }
+
+        state.run_first = None;
+        state.run_last = None;
+        state.run_len = 0;
+    }
+
+    /// Returns `true` if line breaks need handling beyond being copied through.
+    fn tracks_line_breaks<const PRESET: u8>(&self) -> bool {
+        let options = self.options::<PRESET>();
+        options.squeeze_blank_lines || options.trim_line_ends
+    }
+
+    /// Writes a line break unless blank-line squeezing drops it.
+    ///
+    /// Returns `true` if the line break was written.
+    fn line_break<const PRESET: u8>(&self, state: &mut State, out: &mut Vec<u8>, bytes: &[u8]) -> bool {
+        let options = self.options::<PRESET>();
+        if options.trim_line_ends {
+            state.discard_whitespace();
+        }
+
+        let mut line_breaks = state.line_breaks;
+        if options.squeeze_blank_lines {
+            if line_breaks > 0 {
+                // Whitespace on a blank line is dropped with the line
+                state.discard_whitespace();
+            }
+            line_breaks += 1;
+            if line_breaks > 2 {
+                state.line_breaks = line_breaks;
+                return false;
+            }
+        }
+
+        self.keep::<PRESET>(state, out, bytes);
+        state.line_breaks = line_breaks;
+        true
+    }
+
+    /// Writes the line break held back by [`LineBreaks::Unfold`], if any.
+    ///
+    /// Only called once the next line is known not to be a continuation line, so
+    /// whitespace before the line break is trimmed.
+    fn flush_break<const PRESET: u8>(&self, state: &mut State, out: &mut Vec<u8>) {
+        if let Some(bytes) = state.pending_break.take() {
+            state.discard_whitespace();
+            self.line_break::<PRESET>(state, out, bytes);
+            state.in_line = false;
+        }
+    }
+
+    /// Finishes the output once the whole input has been processed.
+    pub(crate) fn finish(&self, state: &mut State, out: &mut Vec<u8>) {
+        self.flush_break::<GENERAL>(state, out);
+        if !self.collapse_spaces && !self.trim_line_ends {
+            out.append(&mut state.raw_whitespace);
+        }
+
+        // Trailing whitespace is dropped rather than written
+        state.discard_whitespace();
+    }
+
+    /// Applies the [trailing newline](Self::trailing_newline) policy to `out[from..]`,
+    /// the end of the output. `emitted` is set if output before `from` had content.
+    pub(crate) fn end_output(&self, out: &mut Vec<u8>, from: usize, emitted: bool) {
+        if self.trailing_newline != TrailingNewline::Keep {
+            let end = trailing_line_breaks_start(out, from);
+            out.truncate(end);
+            if self.trailing_newline == TrailingNewline::Single && (end > from || emitted) {
+                out.push(b'\n');
+            }
+        }
+    }
+
+    /// Returns `true` if the end of the output may still be rewritten by
+    /// [`end_output`](Self::end_output), so streaming must hold it back.
+    pub(crate) fn rewrites_output_end(&self) -> bool {
+        self.trailing_newline != TrailingNewline::Keep
+    }
+}
+
+/// Returns the start of the trailing spaces, tabs and line breaks in `out[from..]`.
+pub(crate) fn trailing_line_breaks_start(out: &[u8], from: usize) -> usize {
+    out[from..]
+        .iter()
+        .rposition(|b| !matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+        .map_or(from, |i| from + i + 1)
+}
+
+/// The [`Collapser::feed`] instance that reads every option at runtime.
+const GENERAL: u8 = 0;
+/// The [`Collapser::feed`] instance for [`Collapser::single_line`].
+const SINGLE_LINE: u8 = 1;
+/// The [`Collapser::feed`] instance for [`Collapser::security`].
+const SECURITY: u8 = 2;
+/// The [`Collapser::feed`] instance for [`Collapser::http_field_value`].
+const HTTP_FIELD_VALUE: u8 = 3;
+/// The [`Collapser::feed`] instance for [`Collapser::log_line`].
+const LOG_LINE: u8 = 4;
+
+impl Default for Collapser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How a [`Collapser`] treats line breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum LineBreaks {
+    /// Line breaks are kept, and separate runs of spaces and tabs.
+    #[default]
+    Preserve,
+    /// Line breaks are collapsed together with spaces and tabs.
+    Collapse,
+    /// Hard-wrapped text is unwrapped into one line per paragraph.
+    ///
+    /// A single line break collapses together with spaces and tabs, while two or more
+    /// line breaks, with any whitespace between them, become one paragraph break
+    /// written as `\n\n`.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::{Collapser, LineBreaks};
+    /// let collapser = Collapser::new().line_breaks(LineBreaks::Paragraphs);
+    /// let input = "First paragraph,\nwrapped  by hand.\n \n\n\nSecond\r\none.\n";
+    /// assert_eq!(collapser.collapse(input), "First paragraph, wrapped by hand.\n\nSecond one.");
+    /// ```
+    Paragraphs,
+    /// Indented continuation lines are joined to the line they continue.
+    ///
+    /// A line break followed by a space or tab collapses together with the surrounding
+    /// whitespace, so each logical record becomes one line. Other line breaks are kept,
+    /// and whitespace before them is trimmed.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::{Collapser, LineBreaks};
+    /// let collapser = Collapser::new().line_breaks(LineBreaks::Unfold);
+    /// let input = "key = first  part\n    second part \nnext = value\n";
+    /// assert_eq!(collapser.collapse(input), "key = first part second part\nnext = value\n");
+    /// ```
+    Unfold,
+    /// Line breaks are written as the two-character escape `\n`.
+    ///
+    /// Each `\n`, `\r\n` or lone `\r` becomes a literal backslash followed by `n`,
+    /// so multi-line text fits on one line. Whitespace before a line break is trimmed,
+    /// and line breaks at the start or end of the input are dropped.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::{Collapser, LineBreaks};
+    /// let collapser = Collapser::new().line_breaks(LineBreaks::Escape);
+    /// assert_eq!(collapser.collapse("first  \r\nsecond\n\nthird\n"), r"first\nsecond\n\nthird");
+    /// ```
+    Escape,
+}
+
+/// How a [`Collapser`] treats line breaks at the end of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum TrailingNewline {
+    /// Trailing line breaks are written like any other line break.
+    #[default]
+    Keep,
+    /// Non-empty output ends with exactly one `\n`.
+    Single,
+    /// Output never ends with a line break.
+    Strip,
+}
+
+/// How a [`Collapser`] treats U+2028 LINE SEPARATOR and U+2029 PARAGRAPH SEPARATOR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum LineSeparators {
+    /// The separators are copied through verbatim.
+    #[default]
+    Preserve,
+    /// The separators collapse together with spaces and tabs.
+    Space,
+    /// The separators are handled like `\n` and written as `\n`.
+    Newline,
+}
+
+/// Collapsing state carried from one byte to the next.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct State {
+    /// Whitespace to be written verbatim, such as preserved indentation.
+    raw_whitespace: Vec<u8>,
+    /// A run of spaces/tabs is waiting to be written as a single space.
+    pending_space: bool,
+    /// Output has been written, so a pending space is no longer leading.
+    started: bool,
+    /// Output has been written since the last line break.
+    in_line: bool,
+    /// The previous byte was a `\r` line break.
+    after_cr: bool,
+    /// The `\r` line break in `after_cr` was written rather than squeezed.
+    cr_written: bool,
+    /// Line breaks seen since the last non-whitespace byte.
+    line_breaks: usize,
+    /// Line breaks in the pending run, tracked for [`LineBreaks::Paragraphs`].
+    paragraph_breaks: usize,
+    /// Line break held back until the next line shows whether it is a continuation,
+    /// tracked for [`LineBreaks::Unfold`].
+    pending_break: Option<&'static [u8]>,
+    /// Line breaks waiting to be written, tracked for [`LineBreaks::Escape`].
+    escaped_breaks: usize,
+    /// Inside a double-quoted string, tracked for [`Collapser::quoted_strings`].
+    in_quotes: bool,
+    /// First whitespace character of the pending run, tracked for custom separators.
+    run_first: Option<char>,
+    /// Last character of the pending run, if it is whitespace rather than a line break.
+    run_last: Option<char>,
+    /// Characters in the pending run, tracked for custom separators.
+    run_len: usize,
+    /// Last character written, tracked for custom separators.
+    last_char: Option<char>,
+}
+
+impl State {
+    /// Drops any whitespace waiting to be written.
+    fn discard_whitespace(&mut self) {
+        self.raw_whitespace.clear();
+        self.pending_space = false;
+        self.run_first = None;
+        self.run_last = None;
+        self.run_len = 0;
+        self.paragraph_breaks = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Collapser, LineBreaks, LineSeparators, State, TrailingNewline, GENERAL};
+    use crate::collapse_whitespace;
+
+    #[test]
+    fn default_matches_collapse_whitespace() {
+        let inputs = [
+            "",
+            "   ",
+            "This   is \t  a   test.",
+            "  Leading and trailing  ",
+            "Line1\n   Line2\nLine3",
+            "\t\t\tA\t\tB\t\t",
+            "Windows\r\nline   endings\r\n",
+            "こんにちは\t\t世界",
+        ];
+        for input in inputs {
+            assert_eq!(Collapser::new().collapse(input), collapse_whitespace(input));
+            assert_eq!(Collapser::default().collapse(input), collapse_whitespace(input));
+        }
+    }
+
+    #[test]
+    fn kernel_path_matches_general_path() {
+        let inputs = ["", "  \t ", " a  b\t\tc ", "Line1  \n\n\n  Line2\r\n", "x\n", "こんにちは\t\t世界\n\n "];
+        let collapsers = [
+            Collapser::new(),
+            Collapser::new().preserve_line_count(true),
+            Collapser::new().trailing_newline(TrailingNewline::Single),
+            Collapser::new().trailing_newline(TrailingNewline::Strip),
+        ];
+        for collapser in collapsers {
+            assert!(collapser.resolved().is_kernel_equivalent());
+            for input in inputs {
+                // Streaming always takes the general path
+                let mut streamed = String::new();
+                let mut stream = collapser.stream();
+                stream.push_str(input, &mut streamed);
+                stream.finish_str(&mut streamed);
+                assert_eq!(collapser.collapse(input), streamed, "{collapser:?} {input:?}");
+            }
+        }
+        assert!(!Collapser::single_line().is_kernel_equivalent());
+        assert!(!Collapser::new().separator('_').is_kernel_equivalent());
+    }
+
+    #[test]
+    fn preset_instances_match_general_instance() {
+        let inputs = [
+            "",
+            "  a  ,b\t\"c  ,  d\"\r\n  e ",
+            "\u{a0}x\u{2003}y\u{200b}\u{202e}z\u{3164} \x1b[0m\n\n",
+            "W/\"a\\\" ,b\" ,\r\n\t\"c\"",
+        ];
+        let presets = [Collapser::single_line(), Collapser::security(), Collapser::http_field_value(), Collapser::log_line()];
+        for collapser in presets {
+            for input in inputs {
+                let (mut specialized, mut general) = (Vec::new(), Vec::new());
+                let (mut state, mut general_state) = (State::default(), State::default());
+                collapser.feed(&mut state, input.as_bytes(), &mut specialized, true);
+                collapser.finish(&mut state, &mut specialized);
+                collapser.feed_with::<GENERAL>(&mut general_state, input.as_bytes(), &mut general, true);
+                collapser.finish(&mut general_state, &mut general);
+                assert_eq!(specialized, general, "{collapser:?} {input:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn normalizes_crlf_and_lone_cr() {
+        let collapser = Collapser::new().normalize_line_endings(true);
+        assert_eq!(collapser.collapse("a\r\nb"), "a\nb");
+        assert_eq!(collapser.collapse("a\rb"), "a\nb");
+        assert_eq!(collapser.collapse("a\r\r\nb"), "a\n\nb");
+        assert_eq!(collapser.collapse("a\n\rb"), "a\n\nb");
+        assert_eq!(collapser.collapse("\r\n"), "\n");
+        assert_eq!(collapser.collapse("a\r \nb"), "a\n \nb");
+    }
+
+    #[test]
+    fn normalizes_line_endings_while_collapsing() {
+        let collapser = Collapser::new().normalize_line_endings(true);
+        assert_eq!(collapser.collapse("  a   b \r\n   c\t\r"), "a b \n c \n");
+    }
+
+    #[test]
+    fn squeezes_blank_lines() {
+        let collapser = Collapser::new().squeeze_blank_lines(true);
+        assert_eq!(collapser.collapse("a\nb"), "a\nb");
+        assert_eq!(collapser.collapse("a\n\nb"), "a\n\nb");
+        assert_eq!(collapser.collapse("a\n\n\n\n\nb"), "a\n\nb");
+        assert_eq!(collapser.collapse("\n\n\n\na"), "\n\na");
+        assert_eq!(collapser.collapse("a\n\n\n"), "a\n\n");
+    }
+
+    #[test]
+    fn squeezes_whitespace_only_lines() {
+        let collapser = Collapser::new().squeeze_blank_lines(true);
+        assert_eq!(collapser.collapse("a  \n \t \n   \n  b   c"), "a \n\n b c");
+    }
+
+    #[test]
+    fn squeezes_crlf_blank_lines() {
+        let collapser = Collapser::new().squeeze_blank_lines(true);
+        assert_eq!(collapser.collapse("a\r\n\r\n\r\n\r\nb"), "a\r\n\r\nb");
+        assert_eq!(collapser.collapse("a\r\r\r\rb"), "a\r\rb");
+
+        let collapser = collapser.normalize_line_endings(true);
+        assert_eq!(collapser.collapse("a\r\n\r\n\n\rb"), "a\n\nb");
+    }
+
+    #[test]
+    fn preserves_indentation() {
+        let collapser = Collapser::new().preserve_indentation(true);
+        assert_eq!(collapser.collapse("    a   b"), "    a b");
+        assert_eq!(collapser.collapse("a\n\t\t b  \t c\n  d"), "a\n\t\t b c\n  d");
+        assert_eq!(collapser.collapse("a\r\n   b\r  c"), "a\r\n   b\r  c");
+        assert_eq!(collapser.collapse("a\n    "), "a\n");
+    }
+
+    #[test]
+    fn expands_indentation_tabs() {
+        let collapser = Collapser::new().expand_indentation_tabs(4);
+        assert_eq!(collapser.collapse("\ta\t\tb"), "    a b");
+        assert_eq!(collapser.collapse("a\n\t\tb\n \tc\n     \td"), "a\n        b\n    c\n        d");
+        assert_eq!(collapser.collapse("a\r\n\t b\t"), "a\r\n     b");
+        assert_eq!(Collapser::new().expand_indentation_tabs(2).collapse("\n\t\ta"), "\n    a");
+        assert_eq!(Collapser::new().expand_indentation_tabs(0).collapse("\n\t a"), "\n a");
+        assert_eq!(collapser.preserve_indentation(true).collapse("\n\ta"), "\n    a");
+    }
+
+    #[test]
+    fn preserves_indentation_of_whitespace_only_lines() {
+        let collapser = Collapser::new().preserve_indentation(true);
+        assert_eq!(collapser.collapse("a\n  \t\nb"), "a\n  \t\nb");
+
+        let collapser = collapser.squeeze_blank_lines(true);
+        assert_eq!(collapser.collapse("a\n  \n\t\n \n  b   c"), "a\n\n  b c");
+    }
+
+    #[test]
+    fn trims_line_ends() {
+        let collapser = Collapser::new().trim_line_ends(true);
+        assert_eq!(collapser.collapse("a  \nb\t\n  c  "), "a\nb\n c");
+        assert_eq!(collapser.collapse("a \r\nb \rc"), "a\r\nb\rc");
+        assert_eq!(collapser.collapse("  \n  \n"), "\n\n");
+    }
+
+    #[test]
+    fn trims_line_ends_without_collapsing() {
+        let collapser = Collapser::new().collapse_spaces(false).trim_line_ends(true);
+        assert_eq!(collapser.collapse("  a  b \t\n\tc  d  "), "  a  b\n\tc  d");
+    }
+
+    #[test]
+    fn disabling_collapse_keeps_whitespace() {
+        let collapser = Collapser::new().collapse_spaces(false);
+        assert_eq!(collapser.collapse("  a \t b  "), "  a \t b  ");
+
+        let collapser = collapser.normalize_line_endings(true);
+        assert_eq!(collapser.collapse(" a  \r\n  b "), " a  \n  b ");
+    }
+
+    #[test]
+    fn trims_line_ends_with_preserved_indentation() {
+        let collapser = Collapser::new().trim_line_ends(true).preserve_indentation(true);
+        assert_eq!(collapser.collapse("a:  \n    b:   c  \n    \nd"), "a:\n    b: c\n\nd");
+    }
+
+    #[test]
+    fn single_line_flattens_line_breaks() {
+        let collapser = Collapser::single_line();
+        assert_eq!(collapser.collapse("a\nb"), "a b");
+        assert_eq!(collapser.collapse("a \r\n\t b\r\rc"), "a b c");
+        assert_eq!(collapser.collapse("\n\n  a\n\n"), "a");
+        assert_eq!(collapser.collapse("\r\n"), "");
+    }
+
+    #[test]
+    fn single_line_ignores_line_options() {
+        let collapser = Collapser::single_line().squeeze_blank_lines(true).trim_line_ends(true);
+        assert_eq!(collapser.collapse("a  \n\n\n\n  b"), "a b");
+    }
+
+    #[test]
+    fn collapses_unicode_whitespace() {
+        let collapser = Collapser::new().unicode_whitespace(true);
+        assert_eq!(collapser.collapse("a\u{a0}\u{a0}b"), "a b");
+        assert_eq!(collapser.collapse("\u{3000}a \u{2009}\tb\u{205f}"), "a b");
+        assert_eq!(collapser.collapse("a\x0b\x0cb"), "a b");
+        assert_eq!(collapser.collapse("a\u{a0}\nb"), "a \nb");
+        assert_eq!(collapser.collapse("café  naïve\u{a0}日本"), "café naïve 日本");
+    }
+
+    #[test]
+    fn unicode_whitespace_is_opt_in() {
+        assert_eq!(Collapser::new().collapse("a\u{a0}\u{a0}b"), "a\u{a0}\u{a0}b");
+        assert_eq!(Collapser::new().collapse("a\x0b\x0cb"), "a\x0b\x0cb");
+    }
+
+    #[test]
+    fn collapses_nbsp() {
+        let collapser = Collapser::new().nbsp(true);
+        assert_eq!(collapser.collapse("a\u{a0}b"), "a b");
+        assert_eq!(collapser.collapse("\u{a0} a \u{a0}\t\u{a0}b\u{a0}"), "a b");
+        assert_eq!(collapser.collapse("Â\u{a0}Â"), "Â Â");
+        assert_eq!(collapser.collapse("a\u{2003}b"), "a\u{2003}b");
+
+        let long = format!("{}\u{a0}\u{a0}{}", "x".repeat(15), "y".repeat(31));
+        assert_eq!(collapser.collapse(&long), format!("{} {}", "x".repeat(15), "y".repeat(31)));
+    }
+
+    #[test]
+    fn rewrites_nbsp_to_space() {
+        let collapser = Collapser::new().nbsp_to_space(true);
+        assert_eq!(collapser.collapse("a\u{a0}b \u{a0} c"), "a b c");
+
+        let collapser = collapser.collapse_spaces(false);
+        assert_eq!(collapser.collapse("\u{a0}a\u{a0}\u{a0}b\u{a0}"), " a  b ");
+
+        let collapser = Collapser::new().nbsp_to_space(true).preserve_indentation(true);
+        assert_eq!(collapser.collapse("\u{a0}\u{a0}a\u{a0}\u{a0}b"), "  a b");
+    }
+
+    #[test]
+    fn nbsp_is_kept_verbatim_when_not_collapsing() {
+        let collapser = Collapser::new().nbsp(true).collapse_spaces(false);
+        assert_eq!(collapser.collapse("a\u{a0}b"), "a\u{a0}b");
+    }
+
+    #[test]
+    fn collapses_ideographic_space() {
+        let collapser = Collapser::new().ideographic_space(true);
+        assert_eq!(collapser.collapse("日本\u{3000}語"), "日本 語");
+        assert_eq!(collapser.collapse("\u{3000}日本\u{3000} \u{3000}語\u{3000}"), "日本 語");
+        assert_eq!(collapser.collapse("a\u{a0}b"), "a\u{a0}b");
+
+        // Place the sequence across every chunk boundary position
+        for offset in 0..20 {
+            let input = format!("{}\u{3000}\u{3000}{}", "あ".repeat(offset), "x".repeat(20));
+            let expected = format!("{} {}", "あ".repeat(offset), "x".repeat(20));
+            assert_eq!(collapser.collapse(&input), expected.trim_start());
+        }
+    }
+
+    #[test]
+    fn collapses_typographic_spaces() {
+        let collapser = Collapser::new().typographic_spaces(true);
+        assert_eq!(collapser.collapse("a\u{2002}\u{2003}\u{200a}b"), "a b");
+        assert_eq!(collapser.collapse("\u{2009}a\u{202f}b\u{205f}"), "a b");
+        assert_eq!(collapser.collapse("a\u{200b}b “quoted”"), "a\u{200b}b “quoted”");
+        assert_eq!(collapser.collapse("a\u{a0}b\u{3000}c"), "a\u{a0}b\u{3000}c");
+    }
+
+    #[test]
+    fn strips_zero_width_characters() {
+        let collapser = Collapser::new().strip_zero_width(true);
+        assert_eq!(collapser.collapse("\u{feff}a\u{200b}b\u{2060}c\u{feff}"), "abc");
+        assert_eq!(collapser.collapse("a \u{200b} \u{feff}\tb"), "a b");
+        assert_eq!(collapser.collapse("\u{200b} a"), "a");
+        assert_eq!(collapser.collapse("a\u{200c}\u{200d}b\u{ffef}"), "a\u{200c}\u{200d}b\u{ffef}");
+
+        let long = format!("{}\u{200b}{}", "x".repeat(14), "y".repeat(30));
+        assert_eq!(collapser.collapse(&long), format!("{}{}", "x".repeat(14), "y".repeat(30)));
+    }
+
+    #[test]
+    fn strips_zero_width_with_typographic_spaces() {
+        let collapser = Collapser::new().strip_zero_width(true).typographic_spaces(true);
+        assert_eq!(collapser.collapse("a\u{2009}\u{200b}\u{2009}b"), "a b");
+    }
+
+    #[test]
+    fn unwraps_paragraphs() {
+        let collapser = Collapser::new().line_breaks(LineBreaks::Paragraphs);
+        assert_eq!(collapser.collapse(""), "");
+        assert_eq!(collapser.collapse("\n\n a \n\n"), "a");
+        assert_eq!(collapser.collapse("one\ntwo\n\nthree\nfour"), "one two\n\nthree four");
+        assert_eq!(collapser.collapse("one  \n  two \t\n \t \n three"), "one two\n\nthree");
+        assert_eq!(collapser.collapse("one\r\ntwo\r\n\r\nthree\r\rfour"), "one two\n\nthree\n\nfour");
+        assert_eq!(collapser.collapse("a\n\n\n\n\nb"), "a\n\nb");
+    }
+
+    #[test]
+    fn unwraps_paragraphs_with_other_options() {
+        let raw = Collapser::new().line_breaks(LineBreaks::Paragraphs).collapse_spaces(false);
+        assert_eq!(raw.collapse("a  \n\n  b"), "a\n\nb");
+
+        let separator = Collapser::new().line_breaks(LineBreaks::Paragraphs).separator('_');
+        assert_eq!(separator.collapse("a \n b\n\nc"), "a_b\n\nc");
+
+        let newline = Collapser::new()
+            .line_breaks(LineBreaks::Paragraphs)
+            .line_separators(LineSeparators::Newline);
+        assert_eq!(newline.collapse("a\u{2028}b\u{2029}\u{2029}c"), "a b\n\nc");
+    }
+
+    #[test]
+    fn unfolds_continuation_lines() {
+        let collapser = Collapser::new().line_breaks(LineBreaks::Unfold);
+        assert_eq!(collapser.collapse("a\n b\n\tc\nd"), "a b c\nd");
+        assert_eq!(collapser.collapse("a  \n  \t b  \n"), "a b\n");
+        assert_eq!(collapser.collapse("a\r\n b\r\nc\rd\r\te"), "a b\r\nc\rd e");
+        assert_eq!(collapser.collapse("a\n\n b"), "a\n b");
+        assert_eq!(collapser.collapse("a\r\n\r\nb\n"), "a\r\n\r\nb\n");
+        assert_eq!(collapser.collapse("\n  a"), "\n a");
+        assert_eq!(collapser.collapse("a\n"), collapse_whitespace("a\n"));
+    }
+
+    #[test]
+    fn unfolds_with_line_break_options() {
+        let normalized = Collapser::new().line_breaks(LineBreaks::Unfold).normalize_line_endings(true);
+        assert_eq!(normalized.collapse("a\r\n b\r\nc\rd"), "a b\nc\nd");
+
+        let squeezed = Collapser::new().line_breaks(LineBreaks::Unfold).squeeze_blank_lines(true);
+        assert_eq!(squeezed.collapse("a\n b\n\n\n\nc"), "a b\n\nc");
+    }
+
+    #[test]
+    fn normalizes_commas() {
+        let collapser = Collapser::new().normalize_commas(true);
+        assert_eq!(collapser.collapse("a,b , c\t,\t d"), "a, b, c, d");
+        assert_eq!(collapser.collapse(" , a ,"), ", a,");
+        assert_eq!(collapser.collapse("a,,b"), "a,, b");
+        assert_eq!(Collapser::new().normalize_commas(true).separator('_').collapse("a ,b"), "a,_b");
+    }
+
+    #[test]
+    fn keeps_quoted_strings() {
+        let collapser = Collapser::new().quoted_strings(true).normalize_commas(true);
+        assert_eq!(collapser.collapse("a  \"x  ,\t y\"  b"), "a \"x  ,\t y\" b");
+        assert_eq!(collapser.collapse(r#""a \"  b"  ,  c"#), r#""a \"  b", c"#);
+        assert_eq!(collapser.collapse(r#""\\"  x"#), r#""\\" x"#);
+        assert_eq!(collapser.collapse("\"é\\é  \"  x"), "\"é\\é  \" x");
+        assert_eq!(collapser.collapse(r#"a\  b"#), r#"a\ b"#);
+    }
+
+    #[test]
+    fn http_field_value_preset() {
+        let collapser = Collapser::http_field_value();
+        assert_eq!(collapser.collapse("  max-age=60 ,\tprivate  "), "max-age=60, private");
+        assert_eq!(collapser.collapse("text/plain;\r\n charset=utf-8"), "text/plain; charset=utf-8");
+        assert_eq!(collapser.collapse(r#"attachment; filename="a  b.txt" "#), r#"attachment; filename="a  b.txt""#);
+        assert_eq!(collapser.collapse("a,b"), collapser.collapse(" a , b "));
+    }
+
+    #[test]
+    fn html_text_preset() {
+        let collapser = Collapser::html_text();
+        assert_eq!(collapser.collapse("\r\n  Hello,\r\n\t world!  \n"), "Hello, world!");
+        assert_eq!(collapser.collapse("a\u{a0} \u{a0}b"), "a\u{a0} \u{a0}b");
+        assert_eq!(collapser.collapse("a\u{3000}\x0cb \u{200b} c"), "a\u{3000}\x0cb \u{200b} c");
+    }
+
+    #[test]
+    fn escapes_line_breaks() {
+        let collapser = Collapser::new().line_breaks(LineBreaks::Escape);
+        assert_eq!(collapser.collapse("a\nb\r\nc\rd"), r"a\nb\nc\nd");
+        assert_eq!(collapser.collapse("\n\n a  \n  b \n\n"), r"a\n b");
+        assert_eq!(collapser.collapse("a\r\n\r\n\nb"), r"a\n\n\nb");
+        assert!(!collapser.collapse(&"line \n".repeat(20)).contains('\n'));
+    }
+
+    #[test]
+    fn strips_control_chars() {
+        let collapser = Collapser::new().strip_control_chars(true);
+        assert_eq!(collapser.collapse("a\x01b\x1bc\x7fd\u{80}e\u{9f}f"), "abcdef");
+        assert_eq!(collapser.collapse("a \x00 \x08\tb\n\u{a0}"), "a b\n\u{a0}");
+        assert_eq!(collapser.collapse("a\x0bb\x0c"), "ab");
+        assert_eq!(collapser.unicode_whitespace(true).collapse("a\x0bb\x0c"), "a b");
+
+        let long = format!("{}\u{85}{}\x7f", "x".repeat(15), "y".repeat(30));
+        assert_eq!(collapser.collapse(&long), format!("{}{}", "x".repeat(15), "y".repeat(30)));
+    }
+
+    #[test]
+    fn log_line_preset() {
+        let collapser = Collapser::log_line();
+        assert_eq!(collapser.collapse("user=bob\n[INFO] admin logged in"), r"user=bob\n[INFO] admin logged in");
+        assert_eq!(collapser.collapse(" \u{1b}[2Jok \t done\r\n"), "[2Jok done");
+    }
+
+    #[test]
+    fn trailing_newline_policy() {
+        let single = Collapser::new().trailing_newline(TrailingNewline::Single);
+        let strip = Collapser::new().trailing_newline(TrailingNewline::Strip);
+        for input in ["a", "a\n", "a \n\n", "a\r\n", "a\n \t\n\r", "a\n\n\n\n"] {
+            assert_eq!(single.collapse(input), "a\n", "{input:?}");
+            assert_eq!(strip.collapse(input), "a", "{input:?}");
+        }
+        assert_eq!(single.collapse(" \n\n"), "");
+        assert_eq!(single.collapse("a\n\nb\n\n"), "a\n\nb\n");
+        assert_eq!(Collapser::new().collapse("a\n\n"), "a\n\n");
+        assert_eq!(strip.collapse_spaces(false).collapse("a  \n  "), "a");
+    }
+
+    /// Counts `\n`, `\r\n` and lone `\r` line breaks.
+    fn line_breaks(text: &str) -> usize {
+        text.replace("\r\n", "\n").matches(['\n', '\r']).count()
+    }
+
+    #[test]
+    fn preserves_line_count() {
+        let collapsers = [
+            Collapser::new(),
+            Collapser::single_line(),
+            Collapser::security(),
+            Collapser::log_line(),
+            Collapser::http_field_value(),
+            Collapser::html_text(),
+            Collapser::new().line_breaks(LineBreaks::Paragraphs),
+            Collapser::new().line_breaks(LineBreaks::Unfold).normalize_line_endings(true),
+            Collapser::new().squeeze_blank_lines(true).trim_line_ends(true),
+            Collapser::new().trailing_newline(TrailingNewline::Strip),
+            Collapser::new().trailing_newline(TrailingNewline::Single).preserve_indentation(true),
+            Collapser::new().line_separators(LineSeparators::Newline).unicode_whitespace(true),
+            Collapser::new().collapse_spaces(false).expand_indentation_tabs(4),
+        ];
+        let inputs = [
+            "",
+            "\n",
+            "a",
+            "  a  \n\n\n  b  ",
+            "\r\n\r\n x \r y\r\n",
+            "a\n \n\t\n  \n",
+            "one\n  two\u{2028}three\u{2029}\n\n",
+            "\"quoted\n  string\"\r\r\n\n",
+        ];
+        for collapser in collapsers {
+            let collapser = collapser.preserve_line_count(true);
+            for input in inputs {
+                let output = collapser.collapse(input);
+                assert_eq!(line_breaks(&output), line_breaks(input), "{collapser:?} {input:?} -> {output:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn preserve_line_count_still_collapses_lines() {
+        let collapser = Collapser::single_line().trim_line_ends(true).preserve_line_count(true);
+        assert_eq!(collapser.collapse(" a   b \n\n c \t"), "a b\n\n c");
+    }
+
+    #[test]
+    fn line_separator_policy() {
+        let input = "a \u{2028} b\u{2029}\u{2029}c";
+        assert_eq!(Collapser::new().collapse(input), input);
+        assert_eq!(Collapser::new().unicode_whitespace(true).collapse(input), "a b c");
+
+        let preserve = Collapser::new().unicode_whitespace(true).line_separators(LineSeparators::Preserve);
+        assert_eq!(preserve.collapse(input), "a \u{2028} b\u{2029}\u{2029}c");
+
+        let space = Collapser::new().line_separators(LineSeparators::Space);
+        assert_eq!(space.collapse(input), "a b c");
+
+        let newline = Collapser::new().line_separators(LineSeparators::Newline);
+        assert_eq!(newline.collapse(input), "a \n b\n\nc");
+        assert_eq!(newline.trim_line_ends(true).collapse(input), "a\n b\n\nc");
+        assert_eq!(newline.squeeze_blank_lines(true).collapse("a\u{2029}\u{2029}\u{2029}b"), "a\n\nb");
+        assert_eq!(newline.line_breaks(LineBreaks::Collapse).collapse(input), "a b c");
+        assert_eq!(newline.collapse("a\r\u{2028}b"), "a\r\nb");
+    }
+
+    #[test]
+    fn strips_bidi_controls() {
+        let collapser = Collapser::new().strip_bidi_controls(true);
+        for c in ('\u{202a}'..='\u{202e}').chain('\u{2066}'..='\u{2069}') {
+            assert_eq!(collapser.collapse(&format!("{c}a {c} b{c}")), "a b", "{c:?}");
+        }
+        assert_eq!(collapser.collapse("a\u{200e}b\u{200f}c\u{2065}"), "a\u{200e}b\u{200f}c\u{2065}");
+        assert_eq!(Collapser::new().collapse("a\u{202e}b"), "a\u{202e}b");
+
+        let long = format!("{}\u{2067}{}", "x".repeat(15), "y".repeat(30));
+        assert_eq!(collapser.collapse(&long), format!("{}{}", "x".repeat(15), "y".repeat(30)));
+    }
+
+    #[test]
+    fn collapses_confusable_spaces() {
+        let collapser = Collapser::new().confusable_spaces(true);
+        for c in ['\u{1680}', '\u{180e}', '\u{115f}', '\u{1160}', '\u{3164}', '\u{ffa0}', '\u{2800}'] {
+            assert_eq!(collapser.collapse(&format!("{c}a {c} b{c}")), "a b", "{c:?}");
+        }
+        assert_eq!(collapser.collapse("a\u{a0}b"), "a\u{a0}b");
+        assert_eq!(collapser.collapse("가\u{2801}ᅡ"), "가\u{2801}ᅡ");
+    }
+
+    #[test]
+    fn security_preset_normalizes_look_alikes() {
+        let collapser = Collapser::security();
+        let variants = [
+            "admin user",
+            "admin\u{a0}user",
+            " admin\u{3000}user ",
+            "admin\u{1680}\u{200b}user",
+            "\u{feff}admin\u{3164}user\n",
+            "admin\u{2003}\u{2800}user",
+            "\u{202e}admin user\u{202c}",
+        ];
+        for variant in variants {
+            assert_eq!(collapser.collapse(variant), "admin user", "{variant:?}");
+        }
+    }
+
+    #[test]
+    fn custom_separator() {
+        let collapser = Collapser::new().separator('_');
+        assert_eq!(collapser.collapse("  a  b\tc  "), "a_b_c");
+
+        let collapser = Collapser::single_line().separator('·');
+        assert_eq!(collapser.collapse("a\n\nb c"), "a·b·c");
+
+        let collapser = Collapser::new().separator('-').preserve_indentation(true);
+        assert_eq!(collapser.collapse("  a  b"), "  a-b");
+    }
+
+    #[test]
+    fn separator_keeps_combining_marks_on_their_base() {
+        let collapser = Collapser::new().separator('_');
+        // The mark belongs to the second space, which is kept as its base
+        assert_eq!(collapser.collapse("a  \u{301}b"), "a_ \u{301}b");
+        assert_eq!(collapser.collapse("a \u{301}b"), "a \u{301}b");
+        assert_eq!(collapser.collapse("  \u{301}b"), " \u{301}b");
+        assert_eq!(collapser.collapse("a\t \u{200d}\u{301}b"), "a_ \u{200d}\u{301}b");
+        // Marks attached to a word stay on it
+        assert_eq!(collapser.collapse("e\u{301}  b"), "e\u{301}_b");
+    }
+
+    #[test]
+    fn separator_does_not_join_preceding_cluster() {
+        // A pictographic separator would extend an emoji ZWJ sequence
+        let collapser = Collapser::new().separator('😀');
+        assert_eq!(collapser.collapse("👨\u{200d}  👩"), "👨\u{200d} 👩");
+        assert_eq!(collapser.collapse("👨  👩"), "👨😀👩");
+
+        // Any separator would attach to a `Prepend` character
+        let collapser = Collapser::new().separator('_');
+        assert_eq!(collapser.collapse("\u{600}\t 1"), "\u{600}\t1");
+    }
+
+    #[test]
+    fn separator_skips_line_breaks_as_mark_bases() {
+        let collapser = Collapser::single_line().separator('_');
+        assert_eq!(collapser.collapse("a \n b"), "a_b");
+        // Marks after a line break get a space as their base, never the separator
+        for (input, expected) in [("a \n\u{301}b", "a \u{301}b"), ("a\t\n\u{301}b", "a\t\u{301}b"), ("a\n\u{301}\u{200d}b", "a \u{301}\u{200d}b")] {
+            let collapsed = collapser.collapse(input);
+            assert!(!collapsed.contains("_\u{301}"), "{input:?}");
+            assert_eq!(collapsed, expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "separator must not join")]
+    fn rejects_joining_separator() {
+        Collapser::new().separator('\u{301}');
+    }
+
+    #[test]
+    fn grapheme_output_matches_default_for_space_separator() {
+        let input = "a  \u{301}b \u{600}\t 1 👨\u{200d}  👩";
+        assert_eq!(Collapser::new().separator(' ').collapse(input), collapse_whitespace(input));
+    }
+
+    #[test]
+    fn keeps_cr_by_default() {
+        assert_eq!(Collapser::new().collapse("a  \r\nb"), "a \r\nb");
+    }
+}
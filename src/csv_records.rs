@@ -0,0 +1,168 @@
+//! Collapsing whitespace in every field of `csv` records, once read or while reading.
+
+use std::io;
+
+use ::csv::{Reader, Result, StringRecord};
+
+use crate::kernel;
+
+/// Returns `record` with every field collapsed like [`collapse_whitespace`](crate::collapse_whitespace).
+///
+/// # Parameters
+/// - `record`: A record read with `csv`.
+///
+/// # Returns
+/// - A record of as many fields, collapsed, and at the position of `record`.
+///
+/// # Example
+/// ```
+/// use csv::StringRecord;
+/// use fast_whitespace_collapse::collapse_record;
+///
+/// let record = StringRecord::from(vec!["  Jane  Doe ", "\tEMEA", "clean"]);
+/// assert_eq!(collapse_record(&record), vec!["Jane Doe", "EMEA", "clean"]);
+/// ```
+///
+/// # Performance
+/// - Every field is collapsed with the SIMD kernel of `collapse_whitespace` into one
+///   scratch buffer, and appended to the record from there, so only the record allocates.
+pub fn collapse_record(record: &StringRecord) -> StringRecord {
+    let mut collapsed = StringRecord::with_capacity(record.as_slice().len(), record.len());
+    collapse_record_into(record, &mut collapsed, &mut Vec::new());
+    collapsed
+}
+
+/// Replaces `collapsed` with `record`, every field collapsed through `scratch`.
+fn collapse_record_into(record: &StringRecord, collapsed: &mut StringRecord, scratch: &mut Vec<u8>) {
+    collapsed.clear();
+    for field in record {
+        scratch.clear();
+        kernel::collapse_bytes_into(field.as_bytes(), scratch);
+        // Safety: The kernel only ever removes spaces/tabs or writes a space
+        collapsed.push_field(unsafe { std::str::from_utf8_unchecked(scratch) });
+    }
+    collapsed.set_position(record.position().cloned());
+}
+
+/// A `csv::Reader` adapter that collapses every field of the records it reads like
+/// [`collapse_whitespace`](crate::collapse_whitespace).
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::CollapseCsvReader;
+///
+/// let data = "name,  team \n  Jane  Doe ,\tEMEA\n";
+/// let mut reader = CollapseCsvReader::new(csv::Reader::from_reader(data.as_bytes()));
+/// assert_eq!(reader.headers().unwrap(), vec!["name", "team"]);
+/// for record in reader.records() {
+///     assert_eq!(record.unwrap(), vec!["Jane Doe", "EMEA"]);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct CollapseCsvReader<R> {
+    reader: Reader<R>,
+    /// The record last read, before collapsing.
+    raw: StringRecord,
+    /// The buffer every field is collapsed into.
+    scratch: Vec<u8>,
+}
+
+impl<R: io::Read> CollapseCsvReader<R> {
+    /// Wraps `reader`, configured as the data needs, such as with `csv::ReaderBuilder`.
+    pub fn new(reader: Reader<R>) -> Self {
+        Self { reader, raw: StringRecord::new(), scratch: Vec::new() }
+    }
+
+    /// Returns the header record, collapsed, like `csv::Reader::headers`.
+    pub fn headers(&mut self) -> Result<StringRecord> {
+        Ok(collapse_record(self.reader.headers()?))
+    }
+
+    /// Reads the next record into `record`, collapsed, like `csv::Reader::read_record`.
+    ///
+    /// Returns `false` once there are no more records to read.
+    ///
+    /// # Performance
+    /// - Reuses the buffers of `record`, and of one raw record and one scratch buffer
+    ///   kept by the reader, so reading every record into the same one allocates nothing
+    ///   once they have grown to the longest record.
+    pub fn read_record(&mut self, record: &mut StringRecord) -> Result<bool> {
+        if !self.reader.read_record(&mut self.raw)? {
+            return Ok(false);
+        }
+        collapse_record_into(&self.raw, record, &mut self.scratch);
+        Ok(true)
+    }
+
+    /// Returns an iterator over the records, collapsed, like `csv::Reader::records`.
+    pub fn records(&mut self) -> CollapsedCsvRecords<'_, R> {
+        CollapsedCsvRecords { reader: self }
+    }
+
+    /// Returns a reference to the underlying `csv::Reader`.
+    pub fn get_ref(&self) -> &Reader<R> {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the underlying `csv::Reader`.
+    pub fn get_mut(&mut self) -> &mut Reader<R> {
+        &mut self.reader
+    }
+
+    /// Returns the underlying `csv::Reader`.
+    pub fn into_inner(self) -> Reader<R> {
+        self.reader
+    }
+}
+
+/// Iterator over collapsed records, created by [`CollapseCsvReader::records`].
+#[derive(Debug)]
+pub struct CollapsedCsvRecords<'r, R> {
+    reader: &'r mut CollapseCsvReader<R>,
+}
+
+impl<R: io::Read> Iterator for CollapsedCsvRecords<'_, R> {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Result<StringRecord>> {
+        let mut record = StringRecord::new();
+        match self.reader.read_record(&mut record) {
+            Ok(true) => Some(Ok(record)),
+            Ok(false) => None,
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapses_every_field() {
+        let record = StringRecord::from(vec!["", "   ", "  Jane  Doe ", "こんにちは\t\t世界", "clean"]);
+        assert_eq!(collapse_record(&record), vec!["", "", "Jane Doe", "こんにちは 世界", "clean"]);
+    }
+
+    #[test]
+    fn reader_collapses_records_and_keeps_positions() {
+        let data = "id , name\n1,  Jane \t Doe \n2, John\n";
+        let mut reader = CollapseCsvReader::new(Reader::from_reader(data.as_bytes()));
+        assert_eq!(reader.headers().unwrap(), vec!["id", "name"]);
+
+        let mut record = StringRecord::new();
+        assert!(reader.read_record(&mut record).unwrap());
+        assert_eq!(record, vec!["1", "Jane Doe"]);
+        assert_eq!(record.position().map(|position| position.line()), Some(2));
+
+        let rest: Vec<StringRecord> = reader.records().collect::<Result<_>>().unwrap();
+        assert_eq!(rest, [StringRecord::from(vec!["2", "John"])]);
+        assert!(!reader.read_record(&mut record).unwrap());
+    }
+
+    #[test]
+    fn reader_reports_errors() {
+        let mut reader = CollapseCsvReader::new(Reader::from_reader("a,b\n1\n".as_bytes()));
+        assert!(reader.records().next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,53 @@
+//! Lazy `Display` of collapsed text.
+
+use std::fmt;
+
+use crate::split_collapsed_words;
+
+/// Displays a string as [`collapse_whitespace`](crate::collapse_whitespace) would
+/// return it, without allocating the collapsed string.
+///
+/// The words of the wrapped string are written straight to the formatter, so it can be
+/// used in `format!`, `write!` or log macros to show a collapsed view of text for free.
+/// Width, fill and alignment flags are ignored.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::Collapsed;
+/// let input = "  This   is \t  a   test.  ";
+/// assert_eq!(format!("[{}]", Collapsed(input)), "[This is a test.]");
+/// ```
+///
+/// # Performance
+/// - Uses SIMD (`u8x16`) to find the words, like [`split_collapsed_words`].
+/// - Writes one slice of the input per word, plus the separating spaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Collapsed<'a>(pub &'a str);
+
+impl fmt::Display for Collapsed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut words = split_collapsed_words(self.0);
+        if let Some(first) = words.next() {
+            f.write_str(first)?;
+            for word in words {
+                f.write_str(" ")?;
+                f.write_str(word)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Collapsed;
+    use crate::collapse_whitespace;
+
+    #[test]
+    fn displays_collapsed_text() {
+        let inputs = ["", " \t ", "x", "  Leading and trailing  ", "Line1\n   Line2 \t\nLine3", "こんにちは\t\t世界"];
+        for input in inputs {
+            assert_eq!(Collapsed(input).to_string(), collapse_whitespace(input), "{input:?}");
+        }
+    }
+}
@@ -0,0 +1,347 @@
+//! Incremental re-collapsing of edited text.
+
+use std::ops::Range;
+
+use crate::kernel;
+
+/// Raw bytes between two checkpoints of a [`CollapseIndex`].
+const CHECKPOINT_INTERVAL: usize = 4096;
+
+/// An index of offsets into the collapsed form of a text, which lets
+/// [`recollapse_edit`] find where an edit goes without reading the whole text.
+///
+/// The index keeps about one offset every 4 KiB of text, and is updated by
+/// `recollapse_edit` along with the collapsed form.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CollapseIndex {
+    /// The length of the text indexed.
+    len: usize,
+    /// Offsets of bytes of the text other than spaces and tabs, ascending and about
+    /// `CHECKPOINT_INTERVAL` apart, each with the offset of the same byte in its
+    /// collapsed form.
+    checkpoints: Vec<(usize, usize)>,
+}
+
+impl CollapseIndex {
+    /// Indexes `text`, whose collapsed form is `collapse_whitespace(text)`.
+    ///
+    /// # Performance
+    /// - Reads `text` once, without writing its collapsed form.
+    pub fn new(text: &str) -> Self {
+        Self { len: text.len(), checkpoints: checkpoints_between(text.as_bytes(), 0..text.len(), None) }
+    }
+}
+
+/// Updates `collapsed` and its `index` after an edit, recollapsing only the text around
+/// the change.
+///
+/// `collapsed` must be the output of [`collapse_whitespace`](crate::collapse_whitespace)
+/// for the text before the edit, `index` the [`CollapseIndex`] of that text, and `text`
+/// the text after the edit, in which the bytes `changed` were inserted in place of the
+/// removed ones (an empty range for a pure deletion). Only the changed bytes and the
+/// whitespace runs touching them are collapsed again; the unchanged collapsed output on
+/// both sides is kept and the new region is spliced in between, so `collapsed` ends up
+/// equal to `collapse_whitespace(text)`, and `index` is updated to match.
+///
+/// # Parameters
+/// - `collapsed`: The collapsed text before the edit, updated in place.
+/// - `index`: The index of the text before the edit, updated in place.
+/// - `text`: The full text after the edit.
+/// - `changed`: The byte range of `text` that was inserted by the edit.
+///
+/// # Panics
+/// - If `changed` is out of bounds of `text` or not on `char` boundaries, or if `text`
+///   without `changed` is longer than the text indexed.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::{collapse_whitespace, recollapse_edit, CollapseIndex};
+///
+/// let text = "Hello   world  ";
+/// let (mut collapsed, mut index) = (collapse_whitespace(text), CollapseIndex::new(text));
+/// // Insert "  brave  " after "Hello"
+/// let text = "Hello  brave     world  ";
+/// recollapse_edit(&mut collapsed, &mut index, text, 5..14);
+/// assert_eq!(collapsed, "Hello brave world");
+/// ```
+///
+/// # Performance
+/// - Only the edit, the whitespace runs touching it and at most about 4 KiB of text on
+///   either side, up to the nearest offsets of `index`, are read, whatever the size of
+///   the document.
+/// - Splicing moves the tail of `collapsed` like [`String::replace_range`], and the
+///   offsets of `index` after the edit are shifted, one for every 4 KiB of text.
+pub fn recollapse_edit(collapsed: &mut String, index: &mut CollapseIndex, text: &str, changed: Range<usize>) {
+    let bytes = text.as_bytes();
+    assert!(text.is_char_boundary(changed.start) && text.is_char_boundary(changed.end));
+    let kept_len = text.len() - changed.len();
+    assert!(kept_len <= index.len, "the edited text is longer than the text indexed");
+    let removed = index.len - kept_len;
+    let (start, end) = widen(bytes, changed.clone());
+    // Where the unchanged suffix started before the edit
+    let old_end = end - changed.len() + removed;
+
+    // The checkpoints before `start` are unchanged; those from the suffix on are shifted
+    // along with it
+    let checkpoints = &index.checkpoints;
+    let kept = checkpoints.partition_point(|&(raw, _)| raw < start);
+    let next = checkpoints.partition_point(|&(raw, _)| raw < old_end);
+    let prefix = match kept.checked_sub(1).map(|i| checkpoints[i]) {
+        Some((raw, offset)) => offset + collapsed_between(bytes, raw, start),
+        None => kernel::collapsed_len(&bytes[..start]),
+    };
+    let old_collapsed_end = if end == bytes.len() {
+        collapsed.len()
+    } else if let Some(&(raw, offset)) = checkpoints.get(next) {
+        offset - collapsed_between(bytes, end, raw - old_end + end)
+    } else {
+        collapsed.len() - kernel::collapsed_len(&bytes[end..])
+    };
+
+    let collapsed_region = collapse_region(bytes, start, end);
+    collapsed.replace_range(prefix..old_collapsed_end, &collapsed_region);
+    let collapsed_end = prefix + collapsed_region.len();
+
+    // Index the new region from the byte before it, and the first byte after it
+    let before = (start > 0).then(|| (start - 1, prefix - 1));
+    let mut region_checkpoints = checkpoints_between(bytes, start..end, before);
+    if end < bytes.len() && (next == checkpoints.len() || checkpoints[next].0 != old_end) {
+        region_checkpoints.push((end, collapsed_end));
+    }
+    region_checkpoints.extend(checkpoints[next..].iter().map(|&(raw, offset)| (raw - old_end + end, offset - old_collapsed_end + collapsed_end)));
+    index.checkpoints.splice(kept.., region_checkpoints);
+    index.len = text.len();
+}
+
+/// A text kept together with its collapsed form and their [`CollapseIndex`], updated
+/// edit by edit with [`recollapse_edit`].
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::CollapsedDocument;
+///
+/// let mut document = CollapsedDocument::new("Hello   world  ");
+/// assert_eq!(document.collapsed(), "Hello world");
+/// // Insert "  brave  " after "Hello"
+/// document.edit(5..5, "  brave  ");
+/// assert_eq!(document.text(), "Hello  brave     world  ");
+/// assert_eq!(document.collapsed(), "Hello brave world");
+/// ```
+///
+/// # Performance
+/// - [`edit`](Self::edit) reads only the text around the edit, like `recollapse_edit`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CollapsedDocument {
+    text: String,
+    collapsed: String,
+    index: CollapseIndex,
+}
+
+impl CollapsedDocument {
+    /// Collapses `text` and indexes it.
+    pub fn new(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let collapsed = crate::collapse_whitespace(&text);
+        let index = CollapseIndex::new(&text);
+        Self { text, collapsed, index }
+    }
+
+    /// Returns the text, with every edit applied.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the text collapsed, equal to `collapse_whitespace(self.text())`.
+    pub fn collapsed(&self) -> &str {
+        &self.collapsed
+    }
+
+    /// Returns the text and its collapsed form.
+    pub fn into_parts(self) -> (String, String) {
+        (self.text, self.collapsed)
+    }
+
+    /// Replaces the bytes `range` of the text with `replacement`, like
+    /// [`String::replace_range`], and updates the collapsed form to match.
+    ///
+    /// # Parameters
+    /// - `range`: The byte range of the text to replace, empty for a pure insertion.
+    /// - `replacement`: The text inserted in its place, empty for a pure deletion.
+    ///
+    /// # Panics
+    /// - If `range` is out of bounds of the text or not on `char` boundaries.
+    pub fn edit(&mut self, range: Range<usize>, replacement: &str) {
+        let start = range.start;
+        self.text.replace_range(range, replacement);
+        recollapse_edit(&mut self.collapsed, &mut self.index, &self.text, start..start + replacement.len());
+    }
+}
+
+/// Widens `changed` to the whitespace runs touching it, returning its start and end.
+///
+/// The unchanged text outside then starts and ends with other bytes, so its collapsed
+/// output is the same as before the edit.
+fn widen(bytes: &[u8], changed: Range<usize>) -> (usize, usize) {
+    let mut start = changed.start;
+    while start > 0 && is_blank(bytes[start - 1]) {
+        start -= 1;
+    }
+    let mut end = changed.end.max(start);
+    while end < bytes.len() && is_blank(bytes[end]) {
+        end += 1;
+    }
+    (start, end)
+}
+
+/// Returns the collapsed output of `bytes[start..end]`, a region widened by [`widen`],
+/// as it appears between the collapsed text around it.
+fn collapse_region(bytes: &[u8], start: usize, end: usize) -> String {
+    let region = &bytes[start..end];
+    let mut replacement = Vec::with_capacity(region.len() + 1);
+    if start > 0 && region.first().is_some_and(|&b| is_blank(b)) {
+        replacement.push(b' ');
+    }
+    kernel::collapse_bytes_into(region, &mut replacement);
+    if end < bytes.len() && region.last().is_some_and(|&b| is_blank(b)) {
+        replacement.push(b' ');
+    }
+    if region.iter().all(|&b| is_blank(b)) {
+        // A blank region between two words is one space, and nothing at an end of the text
+        replacement.truncate(usize::from(start > 0 && end < bytes.len()));
+    }
+    // Safety: The kernel only ever removes spaces/tabs or writes a space
+    unsafe { String::from_utf8_unchecked(replacement) }
+}
+
+/// Returns how far apart the outputs of `bytes[from]` and `bytes[to]` are in the
+/// collapsed text, where `bytes[from]` is neither a space nor a tab and `to` is either
+/// a byte that is neither or the end of the text before one.
+fn collapsed_between(bytes: &[u8], from: usize, to: usize) -> usize {
+    kernel::collapsed_len(&bytes[from..to]) + usize::from(to > from && is_blank(bytes[to - 1]))
+}
+
+/// Returns checkpoints for `bytes[range]`, about `CHECKPOINT_INTERVAL` apart, counting
+/// their collapsed offsets from `last`, the checkpoint of the last byte other than a
+/// space or tab before `range`, if there is one.
+fn checkpoints_between(bytes: &[u8], range: Range<usize>, mut last: Option<(usize, usize)>) -> Vec<(usize, usize)> {
+    let mut checkpoints = Vec::new();
+    for block in (range.start..range.end).step_by(CHECKPOINT_INTERVAL) {
+        let Some(raw) = bytes[block..range.end].iter().position(|&b| !is_blank(b)).map(|i| block + i) else {
+            break;
+        };
+        if last.is_some_and(|(last, _)| raw <= last) {
+            continue;
+        }
+        let offset = match last {
+            Some((last, offset)) => offset + collapsed_between(bytes, last, raw),
+            // The first byte of the text other than a space or tab starts the output
+            None => 0,
+        };
+        checkpoints.push((raw, offset));
+        last = Some((raw, offset));
+    }
+    checkpoints
+}
+
+fn is_blank(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recollapse_edit, CollapseIndex, CollapsedDocument, CHECKPOINT_INTERVAL};
+    use crate::collapse_whitespace;
+
+    /// Checks `document` against collapsing and indexing its text again.
+    fn assert_consistent(document: &CollapsedDocument) {
+        assert_eq!(document.collapsed(), collapse_whitespace(document.text()), "{:?}", document.text());
+        assert_eq!(document.index.len, document.text.len());
+        // The collapsed offset of every byte other than a space or tab
+        let mut offsets = vec![None; document.text.len()];
+        let (mut offset, mut pending) = (0, false);
+        for (i, &b) in document.text.as_bytes().iter().enumerate() {
+            if matches!(b, b' ' | b'\t') {
+                pending = true;
+            } else {
+                offset += usize::from(pending && offset > 0);
+                offsets[i] = Some(offset);
+                offset += 1;
+                pending = false;
+            }
+        }
+        for &(raw, offset) in &document.index.checkpoints {
+            assert_eq!(offsets[raw], Some(offset), "{:?}", document.text());
+        }
+        assert!(document.index.checkpoints.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    const TEXTS: [&str; 5] = ["", "  ", "a b", "  one  two\t\tthree  ", " é\t\u{3000}  ü  x "];
+    const REPLACEMENTS: [&str; 6] = ["", " ", "\t ", "x", " y ", "p  q"];
+
+    #[test]
+    fn matches_collapsing_every_edit() {
+        for text in TEXTS {
+            for start in (0..=text.len()).filter(|&i| text.is_char_boundary(i)) {
+                for end in (start..=text.len()).filter(|&i| text.is_char_boundary(i)) {
+                    for replacement in REPLACEMENTS {
+                        let edited = format!("{}{replacement}{}", &text[..start], &text[end..]);
+                        let (mut collapsed, mut index) = (collapse_whitespace(text), CollapseIndex::new(text));
+                        recollapse_edit(&mut collapsed, &mut index, &edited, start..start + replacement.len());
+                        assert_eq!(collapsed, collapse_whitespace(&edited), "{text:?} {start}..{end} {replacement:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn applies_successive_edits() {
+        let mut text = String::from("fn  main() {\n\tlet  x = 1;\n}");
+        let (mut collapsed, mut index) = (collapse_whitespace(&text), CollapseIndex::new(&text));
+        for (at, typed) in [(12, "  "), (3, "  "), (0, " \t"), (text.len() + 5, "  ")] {
+            text.insert_str(at, typed);
+            recollapse_edit(&mut collapsed, &mut index, &text, at..at + typed.len());
+            assert_eq!(collapsed, collapse_whitespace(&text));
+        }
+    }
+
+    #[test]
+    fn document_keeps_its_index_across_edits() {
+        let line = "  let  x =\t1;  // a  comment\n";
+        let mut document = CollapsedDocument::new(line.repeat(2000));
+        assert!(document.index.checkpoints.len() > 10);
+        assert_consistent(&document);
+        let mut seed = 7u64;
+        for step in 0..300 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let at = (seed >> 33) as usize % document.text().len();
+            let at = (at..).find(|&i| document.text().is_char_boundary(i)).unwrap();
+            let removed = ((seed >> 20) % 40) as usize;
+            let removed = removed.min(document.text().len() - at);
+            let edit = [" ", "\t\t", "word", "  long  insertion  ", ""][step % 5];
+            let replacement = if step % 50 == 0 { line.repeat(300) } else { edit.to_owned() };
+            document.edit(at..at + removed, &replacement);
+            assert_consistent(&document);
+        }
+    }
+
+    #[test]
+    fn reads_only_around_the_edit() {
+        let text = "some  words\tand   spaces ".repeat(100_000);
+        let middle = text.len() / 2;
+        for (range, replacement) in [(middle..middle, "  typed  "), (middle..middle + 9, ""), (middle..middle + 1000, "\t")] {
+            let edited = format!("{}{replacement}{}", &text[..range.start], &text[range.end..]);
+            let (mut collapsed, mut index) = (collapse_whitespace(&text), CollapseIndex::new(&text));
+            // Fill a double space on each side, far enough from the edit to be left unread:
+            // had it been read, the collapsed form would be one byte longer on each side
+            let mut unread = edited.clone().into_bytes();
+            for far in [middle - 3 * CHECKPOINT_INTERVAL, middle + 3 * CHECKPOINT_INTERVAL] {
+                let double = far + unread[far..].windows(2).position(|pair| pair == b"  ").unwrap();
+                unread[double] = b'x';
+            }
+            let unread = String::from_utf8(unread).unwrap();
+            recollapse_edit(&mut collapsed, &mut index, &unread, range.start..range.start + replacement.len());
+            assert_eq!(collapsed, collapse_whitespace(&edited));
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Whitespace collapsing for legacy encodings, via `encoding_rs`.
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+
+use crate::kernel::collapse_bytes;
+use crate::utf16::collapse_whitespace_utf16;
+
+/// Collapses consecutive spaces and tabs into a single space in encoded text.
+///
+/// Collapses whitespace in Latin-1/Windows-1252, Shift_JIS, GBK, EUC-KR and other
+/// legacy buffers directly, without a decode → collapse → re-encode round trip.
+///
+/// # Parameters
+/// - `bytes`: Text in `encoding`.
+/// - `encoding`: The encoding of `bytes`, such as `encoding_rs::SHIFT_JIS`.
+///
+/// # Returns
+/// - A `Vec<u8>` in the same encoding with collapsed whitespace.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_whitespace_encoded;
+/// let input = b"caf\xe9   au \t lait";
+/// let output = collapse_whitespace_encoded(input, encoding_rs::WINDOWS_1252);
+/// assert_eq!(output, b"caf\xe9 au lait");
+/// ```
+///
+/// # How Encodings Are Handled
+/// - **ASCII-compatible encodings** (all single-byte encodings, Shift_JIS, EUC-JP,
+///   GBK, gb18030, Big5, EUC-KR, UTF-8) are processed in place with the SIMD kernel.
+///   None of these use `0x20` or `0x09` inside a multi-byte character, so those
+///   bytes are always a space or a tab.
+/// - **UTF-16LE/BE** go through [`collapse_whitespace_utf16`](crate::collapse_whitespace_utf16).
+///   A trailing odd byte is kept as is.
+/// - **ISO-2022-JP** is stateful, so it is decoded, collapsed and re-encoded.
+/// - The **replacement** encoding decodes to at most one U+FFFD. It is returned
+///   unchanged.
+pub fn collapse_whitespace_encoded(bytes: &[u8], encoding: &'static Encoding) -> Vec<u8> {
+    if encoding.is_ascii_compatible() {
+        return collapse_bytes(bytes);
+    }
+
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let pairs = bytes.chunks_exact(2);
+        let odd = pairs.remainder();
+        let units: Vec<u16> = pairs
+            .map(|pair| [pair[0], pair[1]])
+            .map(|pair| if encoding == UTF_16LE { u16::from_le_bytes(pair) } else { u16::from_be_bytes(pair) })
+            .collect();
+
+        let mut result: Vec<u8> = collapse_whitespace_utf16(&units)
+            .into_iter()
+            .flat_map(|unit| if encoding == UTF_16LE { unit.to_le_bytes() } else { unit.to_be_bytes() })
+            .collect();
+        result.extend_from_slice(odd);
+        return result;
+    }
+
+    if encoding.output_encoding() != encoding {
+        // Only the replacement encoding, which cannot be encoded back
+        return bytes.to_vec();
+    }
+
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    let collapsed = crate::collapse_whitespace(&text);
+    let (result, _, _) = encoding.encode(&collapsed);
+    result.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::collapse_whitespace_encoded;
+    use encoding_rs::{Encoding, EUC_KR, GBK, ISO_2022_JP, SHIFT_JIS, UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+    fn roundtrip(input: &str, encoding: &'static Encoding) -> String {
+        let (encoded, _, _) = encoding.encode(input);
+        let collapsed = collapse_whitespace_encoded(&encoded, encoding);
+        encoding.decode_without_bom_handling(&collapsed).0.into_owned()
+    }
+
+    #[test]
+    fn single_byte_encodings() {
+        assert_eq!(collapse_whitespace_encoded(b"  \xabna\xefve\xbb \t text  ", WINDOWS_1252), b"\xabna\xefve\xbb text");
+        assert_eq!(roundtrip("  Déjà   vu \t ", WINDOWS_1252), "Déjà vu");
+    }
+
+    #[test]
+    fn multi_byte_encodings() {
+        for encoding in [SHIFT_JIS, GBK, EUC_KR] {
+            assert_eq!(roundtrip("日本  語\t\tテキスト ", encoding), "日本 語 テキスト", "{encoding:?}");
+        }
+        // `ソ` is `83 5C` in Shift_JIS; its trail byte must not be mistaken for ASCII
+        assert_eq!(roundtrip("ソ  表", SHIFT_JIS), "ソ 表");
+    }
+
+    #[test]
+    fn utf16_encodings() {
+        for encoding in [UTF_16LE, UTF_16BE] {
+            let input: Vec<u8> = "  a   b\t😀 ".encode_utf16().flat_map(|unit| {
+                if encoding == UTF_16LE { unit.to_le_bytes() } else { unit.to_be_bytes() }
+            }).collect();
+            let collapsed = collapse_whitespace_encoded(&input, encoding);
+            assert_eq!(encoding.decode_without_bom_handling(&collapsed).0, "a b 😀");
+        }
+        assert_eq!(collapse_whitespace_encoded(b"a\0 \0 \0b\0\xff", UTF_16LE), b"a\0 \0b\0\xff");
+    }
+
+    #[test]
+    fn stateful_encoding() {
+        assert_eq!(roundtrip("日本   語  ", ISO_2022_JP), "日本 語");
+    }
+}
@@ -0,0 +1,109 @@
+//! Collapsing whitespace in place in the string fields of a struct, such as a submitted
+//! form or a DTO.
+
+use crate::kernel;
+
+/// Types whose strings can be collapsed in place, as by
+/// [`collapse_whitespace`](crate::collapse_whitespace).
+///
+/// It is implemented for `String`, and for `Option`s and `Vec`s of such types. With the
+/// `derive` feature, `#[derive(CollapseWhitespace)]` implements it for a struct by
+/// collapsing every field of type `String`, `Option<String>` or `Vec<String>`, nested
+/// to any depth. A field takes `#[collapse_whitespace(skip)]` to be left as is, and
+/// `#[collapse_whitespace(nested)]` to be collapsed with its own implementation, such as
+/// a struct deriving it too.
+///
+/// # Example
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use fast_whitespace_collapse::CollapseWhitespace;
+///
+/// #[derive(CollapseWhitespace)]
+/// struct Address {
+///     street: String,
+/// }
+///
+/// #[derive(CollapseWhitespace)]
+/// struct SignUp {
+///     name: String,
+///     nickname: Option<String>,
+///     tags: Vec<String>,
+///     #[collapse_whitespace(skip)]
+///     password: String,
+///     #[collapse_whitespace(nested)]
+///     address: Address,
+///     age: u32,
+/// }
+///
+/// let mut form = SignUp {
+///     name: "  Jane \t Doe ".into(),
+///     nickname: Some("jd  ".into()),
+///     tags: vec![" new  user".into()],
+///     password: " keep  me ".into(),
+///     address: Address { street: "1  Main\tSt".into() },
+///     age: 42,
+/// };
+/// form.collapse_whitespace();
+/// assert_eq!(form.name, "Jane Doe");
+/// assert_eq!(form.nickname.as_deref(), Some("jd"));
+/// assert_eq!(form.tags, ["new user"]);
+/// assert_eq!(form.password, " keep  me ");
+/// assert_eq!(form.address.street, "1 Main St");
+/// # }
+/// ```
+pub trait CollapseWhitespace {
+    /// Collapses every string of `self` in place.
+    ///
+    /// # Performance
+    /// - A string that is already collapsed is found with the SIMD scan of
+    ///   [`collapse_whitespace_cow`](crate::collapse_whitespace_cow) and left untouched,
+    ///   so a clean struct costs no allocation.
+    fn collapse_whitespace(&mut self);
+}
+
+impl CollapseWhitespace for String {
+    fn collapse_whitespace(&mut self) {
+        if !kernel::is_collapsed(self.as_bytes()) {
+            *self = crate::collapse_whitespace(self);
+        }
+    }
+}
+
+impl<T: CollapseWhitespace> CollapseWhitespace for Option<T> {
+    fn collapse_whitespace(&mut self) {
+        if let Some(value) = self {
+            value.collapse_whitespace();
+        }
+    }
+}
+
+impl<T: CollapseWhitespace> CollapseWhitespace for Vec<T> {
+    fn collapse_whitespace(&mut self) {
+        for value in self {
+            value.collapse_whitespace();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CollapseWhitespace;
+
+    #[test]
+    fn collapses_strings_in_options_and_vecs() {
+        let mut strings = vec![Some(String::from("  a  b ")), None, Some(String::from("clean"))];
+        strings.collapse_whitespace();
+        assert_eq!(strings, [Some("a b".into()), None, Some("clean".into())]);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derives_for_tuple_and_generic_structs() {
+        #[derive(crate::CollapseWhitespace)]
+        struct Pair<T>(String, #[collapse_whitespace(nested)] T, Option<Vec<String>>, #[collapse_whitespace(skip)] String);
+
+        let mut pair = Pair(" a  b".to_string(), vec![String::from("c\t d")], Some(vec![String::from(" e ")]), String::from(" f "));
+        pair.collapse_whitespace();
+        assert_eq!((pair.0.as_str(), pair.1, pair.2, pair.3.as_str()), ("a b", vec![String::from("c d")], Some(vec![String::from("e")]), " f "));
+    }
+}
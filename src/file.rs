@@ -0,0 +1,148 @@
+//! Collapsing whole files, streamed or memory-mapped.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use crate::io::{copy_collapsed, Stats};
+#[cfg(feature = "mmap")]
+use crate::kernel;
+use crate::Collapser;
+
+/// Size of the buffers files are read and written through.
+const FILE_CHUNK: usize = 64 * 1024;
+
+/// Collapses the file at `input_path` into a new file at `output_path`.
+///
+/// The input is streamed through a [`StreamCollapser`](crate::StreamCollapser) with
+/// fixed-size buffers, so memory use does not depend on the size of the file: dumps far
+/// larger than the available RAM can be normalized. The output file is created, or
+/// truncated if it exists.
+///
+/// # Parameters
+/// - `input_path`: The file to read.
+/// - `output_path`: The file to write the collapsed contents to. It must not be the
+///   input file.
+/// - `collapser`: The options to collapse with.
+///
+/// # Returns
+/// - The number of bytes read and written, or the first I/O error.
+///
+/// # Example
+/// ```no_run
+/// use fast_whitespace_collapse::{collapse_file_to, Collapser};
+///
+/// let stats = collapse_file_to("dump.txt", "dump.collapsed.txt", Collapser::new())?;
+/// println!("{} bytes in, {} bytes out", stats.bytes_read, stats.bytes_written);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Performance
+/// - Reads and writes in 64 KiB chunks, each collapsed by the same engine as
+///   [`Collapser::collapse`].
+pub fn collapse_file_to(
+    input_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    collapser: Collapser,
+) -> io::Result<Stats> {
+    let mut input = File::open(input_path)?;
+    let mut output = File::create(output_path)?;
+    copy_collapsed(&mut input, &mut output, collapser, FILE_CHUNK)
+}
+
+/// Collapses the contents of the file at `path` like
+/// [`collapse_whitespace`](crate::collapse_whitespace), reading it through a memory map.
+///
+/// The SIMD kernel runs directly over the mapped pages, so the file is never copied
+/// into a buffer of its own; only the collapsed output is allocated.
+///
+/// The file must not be modified while it is mapped, by this or another process:
+/// the mapping would change under the kernel.
+///
+/// # Parameters
+/// - `path`: The file to collapse.
+///
+/// # Returns
+/// - The collapsed contents, an `InvalidData` error if the file is not valid UTF-8, or
+///   the I/O error from opening or mapping it.
+///
+/// # Example
+/// ```no_run
+/// use fast_whitespace_collapse::collapse_file;
+///
+/// let corpus = collapse_file("corpus.txt")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Performance
+/// - Avoids the read-into-`Vec` copy of `fs::read_to_string`, which matters for large
+///   corpora.
+/// - Validating the UTF-8 is a separate pass over the mapping.
+#[cfg(feature = "mmap")]
+pub fn collapse_file(path: impl AsRef<Path>) -> io::Result<String> {
+    let file = File::open(path)?;
+    // Safety: the caller is documented to not modify the file while it is mapped
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    let input = std::str::from_utf8(&map).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    // Safety: The kernel only ever removes spaces/tabs or writes a space
+    Ok(unsafe { String::from_utf8_unchecked(kernel::collapse_bytes(input.as_bytes())) })
+}
+
+// WASI has no temporary directory for the test files
+#[cfg(all(test, not(target_os = "wasi")))]
+mod tests {
+    #[cfg(feature = "mmap")]
+    use super::collapse_file;
+    use super::{collapse_file_to, FILE_CHUNK};
+    #[cfg(feature = "mmap")]
+    use crate::collapse_whitespace;
+    use crate::{Collapser, TrailingNewline};
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Returns a path in the temporary directory that is unique to this test run.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("fast_whitespace_collapse-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn collapses_file_into_another() {
+        let input = "  é\u{a0} and   😀 ".repeat(FILE_CHUNK / 7) + "\r\n\r\n  end \t ";
+        let (input_path, output_path) = (temp_path("input"), temp_path("output"));
+        fs::write(&input_path, &input).unwrap();
+
+        let collapser = Collapser::new().normalize_line_endings(true).trailing_newline(TrailingNewline::Single);
+        let stats = collapse_file_to(&input_path, &output_path, collapser).unwrap();
+        let output = fs::read_to_string(&output_path).unwrap();
+        fs::remove_file(input_path).unwrap();
+        fs::remove_file(output_path).unwrap();
+
+        assert_eq!(output, collapser.collapse(&input));
+        assert_eq!(stats.bytes_read, input.len() as u64);
+        assert_eq!(stats.bytes_written, output.len() as u64);
+    }
+
+    #[test]
+    fn reports_missing_input() {
+        let result = collapse_file_to(temp_path("missing"), temp_path("unused"), Collapser::new());
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn collapses_mapped_file() {
+        let input = " \t mapped   files\u{3000} collapse   too \n".repeat(FILE_CHUNK / 16);
+        let path = temp_path("mapped");
+        fs::write(&path, &input).unwrap();
+        let output = collapse_file(&path).unwrap();
+        fs::write(&path, "").unwrap();
+        let empty = collapse_file(&path).unwrap();
+        fs::write(&path, b"bad \xff  utf-8").unwrap();
+        let invalid = collapse_file(&path).unwrap_err();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(output, collapse_whitespace(&input));
+        assert_eq!(empty, "");
+        assert_eq!(invalid.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,269 @@
+//! `futures` `Stream` and `Sink` adapters that collapse text frames.
+
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use crate::{Collapser, StreamCollapser};
+
+/// A stream that collapses whitespace in the text chunks of another stream.
+///
+/// Every incoming chunk is collapsed with a [`StreamCollapser`], so whitespace runs
+/// that straddle chunks are collapsed as one and the concatenated items are identical
+/// to collapsing the whole text at once. A chunk whose output is held back entirely,
+/// such as one made only of whitespace, yields no item, and output that depends on
+/// the end of the text is yielded as a last item once the inner stream ends.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::CollapseStream;
+/// use futures::{executor::block_on, stream, StreamExt};
+///
+/// let chunks = stream::iter(["  Hello,  ", " \t ", "  world  "]);
+/// let items: Vec<String> = block_on(CollapseStream::new(chunks).collect());
+/// assert_eq!(items, ["Hello,", " world"]);
+/// ```
+#[derive(Debug)]
+pub struct CollapseStream<S> {
+    inner: S,
+    stream: StreamCollapser,
+    /// `inner` ended and the stream has been finished.
+    finished: bool,
+}
+
+impl<S> CollapseStream<S>
+where
+    S: Stream + Unpin,
+    S::Item: AsRef<str>,
+{
+    /// Creates a stream collapsing like [`collapse_whitespace`](crate::collapse_whitespace).
+    pub fn new(inner: S) -> Self {
+        Self::with_collapser(inner, Collapser::new())
+    }
+
+    /// Creates a stream collapsing with the options of `collapser`.
+    pub fn with_collapser(inner: S, collapser: Collapser) -> Self {
+        Self {
+            inner,
+            stream: collapser.stream(),
+            finished: false,
+        }
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Returns the underlying stream, discarding output that is still held back.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Stream for CollapseStream<S>
+where
+    S: Stream + Unpin,
+    S::Item: AsRef<str>,
+{
+    type Item = String;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<String>> {
+        let this = self.get_mut();
+        while !this.finished {
+            let mut output = String::new();
+            match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(chunk) => this.stream.push_str(chunk.as_ref(), &mut output),
+                None => {
+                    this.stream.finish_str(&mut output);
+                    this.finished = true;
+                }
+            }
+            if !output.is_empty() {
+                return Poll::Ready(Some(output));
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+/// A sink that collapses whitespace in the text frames sent through it.
+///
+/// Frames are collapsed with a [`StreamCollapser`] before they are forwarded to the
+/// underlying sink, with the run state carried from one frame to the next. A frame
+/// whose output is held back entirely is not forwarded, and output that depends on the
+/// end of the text is sent as a last frame when the sink is closed.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::CollapseSink;
+/// use futures::{executor::block_on, SinkExt};
+///
+/// let mut sink = CollapseSink::new(Vec::<String>::new());
+/// block_on(async {
+///     sink.send("  Hello,   ".to_string()).await?;
+///     sink.send("\t world  ".to_string()).await?;
+///     sink.close().await
+/// })?;
+/// assert_eq!(sink.get_ref(), &["Hello,", " world"]);
+/// # Ok::<(), std::convert::Infallible>(())
+/// ```
+#[derive(Debug)]
+pub struct CollapseSink<Si> {
+    inner: Si,
+    stream: StreamCollapser,
+    /// The last frame, written when the stream was finished and not yet sent.
+    last: Option<String>,
+    /// The stream has been finished by a close.
+    finished: bool,
+}
+
+impl<Si: Sink<String> + Unpin> CollapseSink<Si> {
+    /// Creates a sink collapsing like [`collapse_whitespace`](crate::collapse_whitespace).
+    pub fn new(inner: Si) -> Self {
+        Self::with_collapser(inner, Collapser::new())
+    }
+
+    /// Creates a sink collapsing with the options of `collapser`.
+    pub fn with_collapser(inner: Si, collapser: Collapser) -> Self {
+        Self {
+            inner,
+            stream: collapser.stream(),
+            last: None,
+            finished: false,
+        }
+    }
+
+    /// Returns a reference to the underlying sink.
+    pub fn get_ref(&self) -> &Si {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying sink.
+    ///
+    /// Sending to it directly interleaves with output that is still held back.
+    pub fn get_mut(&mut self) -> &mut Si {
+        &mut self.inner
+    }
+
+    /// Returns the underlying sink, discarding output that is still held back.
+    pub fn into_inner(self) -> Si {
+        self.inner
+    }
+}
+
+impl<Si: Sink<String> + Unpin> Sink<String> for CollapseSink<Si> {
+    type Error = Si::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Si::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, frame: String) -> Result<(), Si::Error> {
+        let this = self.get_mut();
+        let mut output = String::new();
+        this.stream.push_str(&frame, &mut output);
+        if output.is_empty() {
+            return Ok(());
+        }
+        Pin::new(&mut this.inner).start_send(output)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Si::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Si::Error>> {
+        let this = self.get_mut();
+        if !this.finished {
+            let mut output = String::new();
+            this.stream.finish_str(&mut output);
+            this.last = Some(output).filter(|output| !output.is_empty());
+            this.finished = true;
+        }
+        if this.last.is_some() {
+            ready!(Pin::new(&mut this.inner).poll_ready(cx))?;
+            Pin::new(&mut this.inner).start_send(this.last.take().unwrap())?;
+        }
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CollapseSink, CollapseStream};
+    use crate::{Collapser, LineBreaks, TrailingNewline};
+    use futures::executor::block_on;
+    use futures::{stream, SinkExt, StreamExt};
+
+    const INPUT: &str = "  frames \t  straddle\u{3000} chunks é\u{a0}  😀   here  \n\n\n  and  there \n ";
+
+    fn collapsers() -> [Collapser; 3] {
+        [
+            Collapser::new(),
+            Collapser::single_line(),
+            Collapser::new().line_breaks(LineBreaks::Paragraphs).trailing_newline(TrailingNewline::Single),
+        ]
+    }
+
+    /// Splits `input` into chunks of at most `size` bytes, at character boundaries.
+    fn chunks(input: &str, size: usize) -> Vec<String> {
+        let mut chunks = vec![String::new()];
+        for c in input.chars() {
+            if chunks.last().unwrap().len() + c.len_utf8() > size {
+                chunks.push(String::new());
+            }
+            chunks.last_mut().unwrap().push(c);
+        }
+        chunks
+    }
+
+    #[test]
+    fn stream_matches_one_shot() {
+        for collapser in collapsers() {
+            for size in 1..8 {
+                let chunks = stream::iter(chunks(INPUT, size));
+                let items: Vec<String> = block_on(CollapseStream::with_collapser(chunks, collapser).collect());
+                assert!(items.iter().all(|item| !item.is_empty()));
+                assert_eq!(items.concat(), collapser.collapse(INPUT), "{collapser:?} {size}");
+            }
+        }
+    }
+
+    #[test]
+    fn stream_of_blank_chunks_is_empty() {
+        let chunks = stream::iter(["  ", "\t", ""]);
+        assert_eq!(block_on(CollapseStream::new(chunks).count()), 0);
+    }
+
+    #[test]
+    fn sink_matches_one_shot() {
+        for collapser in collapsers() {
+            for size in 1..8 {
+                let mut sink = CollapseSink::with_collapser(Vec::new(), collapser);
+                let mut frames = stream::iter(chunks(INPUT, size)).map(Ok);
+                block_on(sink.send_all(&mut frames)).unwrap();
+                block_on(sink.close()).unwrap();
+                let frames = sink.into_inner();
+                assert!(frames.iter().all(|frame| !frame.is_empty()));
+                assert_eq!(frames.concat(), collapser.collapse(INPUT), "{collapser:?} {size}");
+            }
+        }
+    }
+
+    #[test]
+    fn sink_sends_trailing_newline_on_close() {
+        let collapser = Collapser::new().trailing_newline(TrailingNewline::Single);
+        let mut sink = CollapseSink::with_collapser(Vec::new(), collapser);
+        block_on(sink.send("  last  line\n\n".to_string())).unwrap();
+        assert_eq!(sink.get_ref(), &["last line"]);
+        block_on(sink.close()).unwrap();
+        assert_eq!(sink.get_ref(), &["last line", "\n"]);
+    }
+}
@@ -0,0 +1,582 @@
+//! Grapheme cluster break data used to insert separators without breaking clusters.
+//!
+//! The table is derived from the Unicode 17.0.0 `GraphemeBreakProperty.txt` and
+//! `emoji-data.txt` files. It only records the classes that can join a neighbouring
+//! character into the same extended grapheme cluster.
+
+/// How a character can join its neighbours into one grapheme cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Joining {
+    /// Never joins a neighbour on its own.
+    None,
+    /// `Extend`, `ZWJ` or `SpacingMark`: joins the preceding character.
+    Extend,
+    /// `Prepend`: joins the following character.
+    Prepend,
+    /// Regional indicators and Hangul jamo or syllables: join each other.
+    Conjoining,
+    /// `Extended_Pictographic`: joins a preceding emoji ZWJ sequence.
+    Pictographic,
+}
+
+/// Returns how `c` can join its neighbours.
+pub(crate) const fn joining(c: char) -> Joining {
+    let c = c as u32;
+    let (mut lo, mut hi) = (0, TABLE.len());
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let (start, end, joining) = TABLE[mid];
+        if c < start as u32 {
+            hi = mid;
+        } else if c > end as u32 {
+            lo = mid + 1;
+        } else {
+            return joining;
+        }
+    }
+    Joining::None
+}
+
+use Joining::{Conjoining, Extend, Pictographic, Prepend};
+
+const TABLE: &[(char, char, Joining)] = &[
+    ('\u{a9}', '\u{a9}', Pictographic),
+    ('\u{ae}', '\u{ae}', Pictographic),
+    ('\u{300}', '\u{36f}', Extend),
+    ('\u{483}', '\u{489}', Extend),
+    ('\u{591}', '\u{5bd}', Extend),
+    ('\u{5bf}', '\u{5bf}', Extend),
+    ('\u{5c1}', '\u{5c2}', Extend),
+    ('\u{5c4}', '\u{5c5}', Extend),
+    ('\u{5c7}', '\u{5c7}', Extend),
+    ('\u{600}', '\u{605}', Prepend),
+    ('\u{610}', '\u{61a}', Extend),
+    ('\u{64b}', '\u{65f}', Extend),
+    ('\u{670}', '\u{670}', Extend),
+    ('\u{6d6}', '\u{6dc}', Extend),
+    ('\u{6dd}', '\u{6dd}', Prepend),
+    ('\u{6df}', '\u{6e4}', Extend),
+    ('\u{6e7}', '\u{6e8}', Extend),
+    ('\u{6ea}', '\u{6ed}', Extend),
+    ('\u{70f}', '\u{70f}', Prepend),
+    ('\u{711}', '\u{711}', Extend),
+    ('\u{730}', '\u{74a}', Extend),
+    ('\u{7a6}', '\u{7b0}', Extend),
+    ('\u{7eb}', '\u{7f3}', Extend),
+    ('\u{7fd}', '\u{7fd}', Extend),
+    ('\u{816}', '\u{819}', Extend),
+    ('\u{81b}', '\u{823}', Extend),
+    ('\u{825}', '\u{827}', Extend),
+    ('\u{829}', '\u{82d}', Extend),
+    ('\u{859}', '\u{85b}', Extend),
+    ('\u{890}', '\u{891}', Prepend),
+    ('\u{897}', '\u{89f}', Extend),
+    ('\u{8ca}', '\u{8e1}', Extend),
+    ('\u{8e2}', '\u{8e2}', Prepend),
+    ('\u{8e3}', '\u{903}', Extend),
+    ('\u{93a}', '\u{93c}', Extend),
+    ('\u{93e}', '\u{94f}', Extend),
+    ('\u{951}', '\u{957}', Extend),
+    ('\u{962}', '\u{963}', Extend),
+    ('\u{981}', '\u{983}', Extend),
+    ('\u{9bc}', '\u{9bc}', Extend),
+    ('\u{9be}', '\u{9c4}', Extend),
+    ('\u{9c7}', '\u{9c8}', Extend),
+    ('\u{9cb}', '\u{9cd}', Extend),
+    ('\u{9d7}', '\u{9d7}', Extend),
+    ('\u{9e2}', '\u{9e3}', Extend),
+    ('\u{9fe}', '\u{9fe}', Extend),
+    ('\u{a01}', '\u{a03}', Extend),
+    ('\u{a3c}', '\u{a3c}', Extend),
+    ('\u{a3e}', '\u{a42}', Extend),
+    ('\u{a47}', '\u{a48}', Extend),
+    ('\u{a4b}', '\u{a4d}', Extend),
+    ('\u{a51}', '\u{a51}', Extend),
+    ('\u{a70}', '\u{a71}', Extend),
+    ('\u{a75}', '\u{a75}', Extend),
+    ('\u{a81}', '\u{a83}', Extend),
+    ('\u{abc}', '\u{abc}', Extend),
+    ('\u{abe}', '\u{ac5}', Extend),
+    ('\u{ac7}', '\u{ac9}', Extend),
+    ('\u{acb}', '\u{acd}', Extend),
+    ('\u{ae2}', '\u{ae3}', Extend),
+    ('\u{afa}', '\u{aff}', Extend),
+    ('\u{b01}', '\u{b03}', Extend),
+    ('\u{b3c}', '\u{b3c}', Extend),
+    ('\u{b3e}', '\u{b44}', Extend),
+    ('\u{b47}', '\u{b48}', Extend),
+    ('\u{b4b}', '\u{b4d}', Extend),
+    ('\u{b55}', '\u{b57}', Extend),
+    ('\u{b62}', '\u{b63}', Extend),
+    ('\u{b82}', '\u{b82}', Extend),
+    ('\u{bbe}', '\u{bc2}', Extend),
+    ('\u{bc6}', '\u{bc8}', Extend),
+    ('\u{bca}', '\u{bcd}', Extend),
+    ('\u{bd7}', '\u{bd7}', Extend),
+    ('\u{c00}', '\u{c04}', Extend),
+    ('\u{c3c}', '\u{c3c}', Extend),
+    ('\u{c3e}', '\u{c44}', Extend),
+    ('\u{c46}', '\u{c48}', Extend),
+    ('\u{c4a}', '\u{c4d}', Extend),
+    ('\u{c55}', '\u{c56}', Extend),
+    ('\u{c62}', '\u{c63}', Extend),
+    ('\u{c81}', '\u{c83}', Extend),
+    ('\u{cbc}', '\u{cbc}', Extend),
+    ('\u{cbe}', '\u{cc4}', Extend),
+    ('\u{cc6}', '\u{cc8}', Extend),
+    ('\u{cca}', '\u{ccd}', Extend),
+    ('\u{cd5}', '\u{cd6}', Extend),
+    ('\u{ce2}', '\u{ce3}', Extend),
+    ('\u{cf3}', '\u{cf3}', Extend),
+    ('\u{d00}', '\u{d03}', Extend),
+    ('\u{d3b}', '\u{d3c}', Extend),
+    ('\u{d3e}', '\u{d44}', Extend),
+    ('\u{d46}', '\u{d48}', Extend),
+    ('\u{d4a}', '\u{d4d}', Extend),
+    ('\u{d4e}', '\u{d4e}', Prepend),
+    ('\u{d57}', '\u{d57}', Extend),
+    ('\u{d62}', '\u{d63}', Extend),
+    ('\u{d81}', '\u{d83}', Extend),
+    ('\u{dca}', '\u{dca}', Extend),
+    ('\u{dcf}', '\u{dd4}', Extend),
+    ('\u{dd6}', '\u{dd6}', Extend),
+    ('\u{dd8}', '\u{ddf}', Extend),
+    ('\u{df2}', '\u{df3}', Extend),
+    ('\u{e31}', '\u{e31}', Extend),
+    ('\u{e33}', '\u{e3a}', Extend),
+    ('\u{e47}', '\u{e4e}', Extend),
+    ('\u{eb1}', '\u{eb1}', Extend),
+    ('\u{eb3}', '\u{ebc}', Extend),
+    ('\u{ec8}', '\u{ece}', Extend),
+    ('\u{f18}', '\u{f19}', Extend),
+    ('\u{f35}', '\u{f35}', Extend),
+    ('\u{f37}', '\u{f37}', Extend),
+    ('\u{f39}', '\u{f39}', Extend),
+    ('\u{f3e}', '\u{f3f}', Extend),
+    ('\u{f71}', '\u{f84}', Extend),
+    ('\u{f86}', '\u{f87}', Extend),
+    ('\u{f8d}', '\u{f97}', Extend),
+    ('\u{f99}', '\u{fbc}', Extend),
+    ('\u{fc6}', '\u{fc6}', Extend),
+    ('\u{102d}', '\u{1037}', Extend),
+    ('\u{1039}', '\u{103e}', Extend),
+    ('\u{1056}', '\u{1059}', Extend),
+    ('\u{105e}', '\u{1060}', Extend),
+    ('\u{1071}', '\u{1074}', Extend),
+    ('\u{1082}', '\u{1082}', Extend),
+    ('\u{1084}', '\u{1086}', Extend),
+    ('\u{108d}', '\u{108d}', Extend),
+    ('\u{109d}', '\u{109d}', Extend),
+    ('\u{1100}', '\u{11ff}', Conjoining),
+    ('\u{135d}', '\u{135f}', Extend),
+    ('\u{1712}', '\u{1715}', Extend),
+    ('\u{1732}', '\u{1734}', Extend),
+    ('\u{1752}', '\u{1753}', Extend),
+    ('\u{1772}', '\u{1773}', Extend),
+    ('\u{17b4}', '\u{17d3}', Extend),
+    ('\u{17dd}', '\u{17dd}', Extend),
+    ('\u{180b}', '\u{180d}', Extend),
+    ('\u{180f}', '\u{180f}', Extend),
+    ('\u{1885}', '\u{1886}', Extend),
+    ('\u{18a9}', '\u{18a9}', Extend),
+    ('\u{1920}', '\u{192b}', Extend),
+    ('\u{1930}', '\u{193b}', Extend),
+    ('\u{1a17}', '\u{1a1b}', Extend),
+    ('\u{1a55}', '\u{1a5e}', Extend),
+    ('\u{1a60}', '\u{1a60}', Extend),
+    ('\u{1a62}', '\u{1a62}', Extend),
+    ('\u{1a65}', '\u{1a7c}', Extend),
+    ('\u{1a7f}', '\u{1a7f}', Extend),
+    ('\u{1ab0}', '\u{1add}', Extend),
+    ('\u{1ae0}', '\u{1aeb}', Extend),
+    ('\u{1b00}', '\u{1b04}', Extend),
+    ('\u{1b34}', '\u{1b44}', Extend),
+    ('\u{1b6b}', '\u{1b73}', Extend),
+    ('\u{1b80}', '\u{1b82}', Extend),
+    ('\u{1ba1}', '\u{1bad}', Extend),
+    ('\u{1be6}', '\u{1bf3}', Extend),
+    ('\u{1c24}', '\u{1c37}', Extend),
+    ('\u{1cd0}', '\u{1cd2}', Extend),
+    ('\u{1cd4}', '\u{1ce8}', Extend),
+    ('\u{1ced}', '\u{1ced}', Extend),
+    ('\u{1cf4}', '\u{1cf4}', Extend),
+    ('\u{1cf7}', '\u{1cf9}', Extend),
+    ('\u{1dc0}', '\u{1dff}', Extend),
+    ('\u{200c}', '\u{200d}', Extend),
+    ('\u{203c}', '\u{203c}', Pictographic),
+    ('\u{2049}', '\u{2049}', Pictographic),
+    ('\u{20d0}', '\u{20f0}', Extend),
+    ('\u{2122}', '\u{2122}', Pictographic),
+    ('\u{2139}', '\u{2139}', Pictographic),
+    ('\u{2194}', '\u{2199}', Pictographic),
+    ('\u{21a9}', '\u{21aa}', Pictographic),
+    ('\u{231a}', '\u{231b}', Pictographic),
+    ('\u{2328}', '\u{2328}', Pictographic),
+    ('\u{23cf}', '\u{23cf}', Pictographic),
+    ('\u{23e9}', '\u{23f3}', Pictographic),
+    ('\u{23f8}', '\u{23fa}', Pictographic),
+    ('\u{24c2}', '\u{24c2}', Pictographic),
+    ('\u{25aa}', '\u{25ab}', Pictographic),
+    ('\u{25b6}', '\u{25b6}', Pictographic),
+    ('\u{25c0}', '\u{25c0}', Pictographic),
+    ('\u{25fb}', '\u{25fe}', Pictographic),
+    ('\u{2600}', '\u{2604}', Pictographic),
+    ('\u{260e}', '\u{260e}', Pictographic),
+    ('\u{2611}', '\u{2611}', Pictographic),
+    ('\u{2614}', '\u{2615}', Pictographic),
+    ('\u{2618}', '\u{2618}', Pictographic),
+    ('\u{261d}', '\u{261d}', Pictographic),
+    ('\u{2620}', '\u{2620}', Pictographic),
+    ('\u{2622}', '\u{2623}', Pictographic),
+    ('\u{2626}', '\u{2626}', Pictographic),
+    ('\u{262a}', '\u{262a}', Pictographic),
+    ('\u{262e}', '\u{262f}', Pictographic),
+    ('\u{2638}', '\u{263a}', Pictographic),
+    ('\u{2640}', '\u{2640}', Pictographic),
+    ('\u{2642}', '\u{2642}', Pictographic),
+    ('\u{2648}', '\u{2653}', Pictographic),
+    ('\u{265f}', '\u{2660}', Pictographic),
+    ('\u{2663}', '\u{2663}', Pictographic),
+    ('\u{2665}', '\u{2666}', Pictographic),
+    ('\u{2668}', '\u{2668}', Pictographic),
+    ('\u{267b}', '\u{267b}', Pictographic),
+    ('\u{267e}', '\u{267f}', Pictographic),
+    ('\u{2692}', '\u{2697}', Pictographic),
+    ('\u{2699}', '\u{2699}', Pictographic),
+    ('\u{269b}', '\u{269c}', Pictographic),
+    ('\u{26a0}', '\u{26a1}', Pictographic),
+    ('\u{26a7}', '\u{26a7}', Pictographic),
+    ('\u{26aa}', '\u{26ab}', Pictographic),
+    ('\u{26b0}', '\u{26b1}', Pictographic),
+    ('\u{26bd}', '\u{26be}', Pictographic),
+    ('\u{26c4}', '\u{26c5}', Pictographic),
+    ('\u{26c8}', '\u{26c8}', Pictographic),
+    ('\u{26ce}', '\u{26cf}', Pictographic),
+    ('\u{26d1}', '\u{26d1}', Pictographic),
+    ('\u{26d3}', '\u{26d4}', Pictographic),
+    ('\u{26e9}', '\u{26ea}', Pictographic),
+    ('\u{26f0}', '\u{26f5}', Pictographic),
+    ('\u{26f7}', '\u{26fa}', Pictographic),
+    ('\u{26fd}', '\u{26fd}', Pictographic),
+    ('\u{2702}', '\u{2702}', Pictographic),
+    ('\u{2705}', '\u{2705}', Pictographic),
+    ('\u{2708}', '\u{270d}', Pictographic),
+    ('\u{270f}', '\u{270f}', Pictographic),
+    ('\u{2712}', '\u{2712}', Pictographic),
+    ('\u{2714}', '\u{2714}', Pictographic),
+    ('\u{2716}', '\u{2716}', Pictographic),
+    ('\u{271d}', '\u{271d}', Pictographic),
+    ('\u{2721}', '\u{2721}', Pictographic),
+    ('\u{2728}', '\u{2728}', Pictographic),
+    ('\u{2733}', '\u{2734}', Pictographic),
+    ('\u{2744}', '\u{2744}', Pictographic),
+    ('\u{2747}', '\u{2747}', Pictographic),
+    ('\u{274c}', '\u{274c}', Pictographic),
+    ('\u{274e}', '\u{274e}', Pictographic),
+    ('\u{2753}', '\u{2755}', Pictographic),
+    ('\u{2757}', '\u{2757}', Pictographic),
+    ('\u{2763}', '\u{2764}', Pictographic),
+    ('\u{2795}', '\u{2797}', Pictographic),
+    ('\u{27a1}', '\u{27a1}', Pictographic),
+    ('\u{27b0}', '\u{27b0}', Pictographic),
+    ('\u{27bf}', '\u{27bf}', Pictographic),
+    ('\u{2934}', '\u{2935}', Pictographic),
+    ('\u{2b05}', '\u{2b07}', Pictographic),
+    ('\u{2b1b}', '\u{2b1c}', Pictographic),
+    ('\u{2b50}', '\u{2b50}', Pictographic),
+    ('\u{2b55}', '\u{2b55}', Pictographic),
+    ('\u{2cef}', '\u{2cf1}', Extend),
+    ('\u{2d7f}', '\u{2d7f}', Extend),
+    ('\u{2de0}', '\u{2dff}', Extend),
+    ('\u{302a}', '\u{302f}', Extend),
+    ('\u{3030}', '\u{3030}', Pictographic),
+    ('\u{303d}', '\u{303d}', Pictographic),
+    ('\u{3099}', '\u{309a}', Extend),
+    ('\u{3297}', '\u{3297}', Pictographic),
+    ('\u{3299}', '\u{3299}', Pictographic),
+    ('\u{a66f}', '\u{a672}', Extend),
+    ('\u{a674}', '\u{a67d}', Extend),
+    ('\u{a69e}', '\u{a69f}', Extend),
+    ('\u{a6f0}', '\u{a6f1}', Extend),
+    ('\u{a802}', '\u{a802}', Extend),
+    ('\u{a806}', '\u{a806}', Extend),
+    ('\u{a80b}', '\u{a80b}', Extend),
+    ('\u{a823}', '\u{a827}', Extend),
+    ('\u{a82c}', '\u{a82c}', Extend),
+    ('\u{a880}', '\u{a881}', Extend),
+    ('\u{a8b4}', '\u{a8c5}', Extend),
+    ('\u{a8e0}', '\u{a8f1}', Extend),
+    ('\u{a8ff}', '\u{a8ff}', Extend),
+    ('\u{a926}', '\u{a92d}', Extend),
+    ('\u{a947}', '\u{a953}', Extend),
+    ('\u{a960}', '\u{a97c}', Conjoining),
+    ('\u{a980}', '\u{a983}', Extend),
+    ('\u{a9b3}', '\u{a9c0}', Extend),
+    ('\u{a9e5}', '\u{a9e5}', Extend),
+    ('\u{aa29}', '\u{aa36}', Extend),
+    ('\u{aa43}', '\u{aa43}', Extend),
+    ('\u{aa4c}', '\u{aa4d}', Extend),
+    ('\u{aa7c}', '\u{aa7c}', Extend),
+    ('\u{aab0}', '\u{aab0}', Extend),
+    ('\u{aab2}', '\u{aab4}', Extend),
+    ('\u{aab7}', '\u{aab8}', Extend),
+    ('\u{aabe}', '\u{aabf}', Extend),
+    ('\u{aac1}', '\u{aac1}', Extend),
+    ('\u{aaeb}', '\u{aaef}', Extend),
+    ('\u{aaf5}', '\u{aaf6}', Extend),
+    ('\u{abe3}', '\u{abea}', Extend),
+    ('\u{abec}', '\u{abed}', Extend),
+    ('\u{ac00}', '\u{d7a3}', Conjoining),
+    ('\u{d7b0}', '\u{d7c6}', Conjoining),
+    ('\u{d7cb}', '\u{d7fb}', Conjoining),
+    ('\u{fb1e}', '\u{fb1e}', Extend),
+    ('\u{fe00}', '\u{fe0f}', Extend),
+    ('\u{fe20}', '\u{fe2f}', Extend),
+    ('\u{ff9e}', '\u{ff9f}', Extend),
+    ('\u{101fd}', '\u{101fd}', Extend),
+    ('\u{102e0}', '\u{102e0}', Extend),
+    ('\u{10376}', '\u{1037a}', Extend),
+    ('\u{10a01}', '\u{10a03}', Extend),
+    ('\u{10a05}', '\u{10a06}', Extend),
+    ('\u{10a0c}', '\u{10a0f}', Extend),
+    ('\u{10a38}', '\u{10a3a}', Extend),
+    ('\u{10a3f}', '\u{10a3f}', Extend),
+    ('\u{10ae5}', '\u{10ae6}', Extend),
+    ('\u{10d24}', '\u{10d27}', Extend),
+    ('\u{10d69}', '\u{10d6d}', Extend),
+    ('\u{10eab}', '\u{10eac}', Extend),
+    ('\u{10efa}', '\u{10eff}', Extend),
+    ('\u{10f46}', '\u{10f50}', Extend),
+    ('\u{10f82}', '\u{10f85}', Extend),
+    ('\u{11000}', '\u{11002}', Extend),
+    ('\u{11038}', '\u{11046}', Extend),
+    ('\u{11070}', '\u{11070}', Extend),
+    ('\u{11073}', '\u{11074}', Extend),
+    ('\u{1107f}', '\u{11082}', Extend),
+    ('\u{110b0}', '\u{110ba}', Extend),
+    ('\u{110bd}', '\u{110bd}', Prepend),
+    ('\u{110c2}', '\u{110c2}', Extend),
+    ('\u{110cd}', '\u{110cd}', Prepend),
+    ('\u{11100}', '\u{11102}', Extend),
+    ('\u{11127}', '\u{11134}', Extend),
+    ('\u{11145}', '\u{11146}', Extend),
+    ('\u{11173}', '\u{11173}', Extend),
+    ('\u{11180}', '\u{11182}', Extend),
+    ('\u{111b3}', '\u{111c0}', Extend),
+    ('\u{111c2}', '\u{111c3}', Prepend),
+    ('\u{111c9}', '\u{111cc}', Extend),
+    ('\u{111ce}', '\u{111cf}', Extend),
+    ('\u{1122c}', '\u{11237}', Extend),
+    ('\u{1123e}', '\u{1123e}', Extend),
+    ('\u{11241}', '\u{11241}', Extend),
+    ('\u{112df}', '\u{112ea}', Extend),
+    ('\u{11300}', '\u{11303}', Extend),
+    ('\u{1133b}', '\u{1133c}', Extend),
+    ('\u{1133e}', '\u{11344}', Extend),
+    ('\u{11347}', '\u{11348}', Extend),
+    ('\u{1134b}', '\u{1134d}', Extend),
+    ('\u{11357}', '\u{11357}', Extend),
+    ('\u{11362}', '\u{11363}', Extend),
+    ('\u{11366}', '\u{1136c}', Extend),
+    ('\u{11370}', '\u{11374}', Extend),
+    ('\u{113b8}', '\u{113c0}', Extend),
+    ('\u{113c2}', '\u{113c2}', Extend),
+    ('\u{113c5}', '\u{113c5}', Extend),
+    ('\u{113c7}', '\u{113ca}', Extend),
+    ('\u{113cc}', '\u{113d0}', Extend),
+    ('\u{113d1}', '\u{113d1}', Prepend),
+    ('\u{113d2}', '\u{113d2}', Extend),
+    ('\u{113e1}', '\u{113e2}', Extend),
+    ('\u{11435}', '\u{11446}', Extend),
+    ('\u{1145e}', '\u{1145e}', Extend),
+    ('\u{114b0}', '\u{114c3}', Extend),
+    ('\u{115af}', '\u{115b5}', Extend),
+    ('\u{115b8}', '\u{115c0}', Extend),
+    ('\u{115dc}', '\u{115dd}', Extend),
+    ('\u{11630}', '\u{11640}', Extend),
+    ('\u{116ab}', '\u{116b7}', Extend),
+    ('\u{1171d}', '\u{1171f}', Extend),
+    ('\u{11722}', '\u{1172b}', Extend),
+    ('\u{1182c}', '\u{1183a}', Extend),
+    ('\u{11930}', '\u{11935}', Extend),
+    ('\u{11937}', '\u{11938}', Extend),
+    ('\u{1193b}', '\u{1193e}', Extend),
+    ('\u{1193f}', '\u{1193f}', Prepend),
+    ('\u{11940}', '\u{11940}', Extend),
+    ('\u{11941}', '\u{11941}', Prepend),
+    ('\u{11942}', '\u{11943}', Extend),
+    ('\u{119d1}', '\u{119d7}', Extend),
+    ('\u{119da}', '\u{119e0}', Extend),
+    ('\u{119e4}', '\u{119e4}', Extend),
+    ('\u{11a01}', '\u{11a0a}', Extend),
+    ('\u{11a33}', '\u{11a39}', Extend),
+    ('\u{11a3b}', '\u{11a3e}', Extend),
+    ('\u{11a47}', '\u{11a47}', Extend),
+    ('\u{11a51}', '\u{11a5b}', Extend),
+    ('\u{11a84}', '\u{11a89}', Prepend),
+    ('\u{11a8a}', '\u{11a99}', Extend),
+    ('\u{11b60}', '\u{11b67}', Extend),
+    ('\u{11c2f}', '\u{11c36}', Extend),
+    ('\u{11c38}', '\u{11c3f}', Extend),
+    ('\u{11c92}', '\u{11ca7}', Extend),
+    ('\u{11ca9}', '\u{11cb6}', Extend),
+    ('\u{11d31}', '\u{11d36}', Extend),
+    ('\u{11d3a}', '\u{11d3a}', Extend),
+    ('\u{11d3c}', '\u{11d3d}', Extend),
+    ('\u{11d3f}', '\u{11d45}', Extend),
+    ('\u{11d46}', '\u{11d46}', Prepend),
+    ('\u{11d47}', '\u{11d47}', Extend),
+    ('\u{11d8a}', '\u{11d8e}', Extend),
+    ('\u{11d90}', '\u{11d91}', Extend),
+    ('\u{11d93}', '\u{11d97}', Extend),
+    ('\u{11ef3}', '\u{11ef6}', Extend),
+    ('\u{11f00}', '\u{11f01}', Extend),
+    ('\u{11f02}', '\u{11f02}', Prepend),
+    ('\u{11f03}', '\u{11f03}', Extend),
+    ('\u{11f34}', '\u{11f3a}', Extend),
+    ('\u{11f3e}', '\u{11f42}', Extend),
+    ('\u{11f5a}', '\u{11f5a}', Extend),
+    ('\u{13440}', '\u{13440}', Extend),
+    ('\u{13447}', '\u{13455}', Extend),
+    ('\u{1611e}', '\u{1612f}', Extend),
+    ('\u{16af0}', '\u{16af4}', Extend),
+    ('\u{16b30}', '\u{16b36}', Extend),
+    ('\u{16d63}', '\u{16d63}', Conjoining),
+    ('\u{16d67}', '\u{16d6a}', Conjoining),
+    ('\u{16f4f}', '\u{16f4f}', Extend),
+    ('\u{16f51}', '\u{16f87}', Extend),
+    ('\u{16f8f}', '\u{16f92}', Extend),
+    ('\u{16fe4}', '\u{16fe4}', Extend),
+    ('\u{16ff0}', '\u{16ff1}', Extend),
+    ('\u{1bc9d}', '\u{1bc9e}', Extend),
+    ('\u{1cf00}', '\u{1cf2d}', Extend),
+    ('\u{1cf30}', '\u{1cf46}', Extend),
+    ('\u{1d165}', '\u{1d169}', Extend),
+    ('\u{1d16d}', '\u{1d172}', Extend),
+    ('\u{1d17b}', '\u{1d182}', Extend),
+    ('\u{1d185}', '\u{1d18b}', Extend),
+    ('\u{1d1aa}', '\u{1d1ad}', Extend),
+    ('\u{1d242}', '\u{1d244}', Extend),
+    ('\u{1da00}', '\u{1da36}', Extend),
+    ('\u{1da3b}', '\u{1da6c}', Extend),
+    ('\u{1da75}', '\u{1da75}', Extend),
+    ('\u{1da84}', '\u{1da84}', Extend),
+    ('\u{1da9b}', '\u{1da9f}', Extend),
+    ('\u{1daa1}', '\u{1daaf}', Extend),
+    ('\u{1e000}', '\u{1e006}', Extend),
+    ('\u{1e008}', '\u{1e018}', Extend),
+    ('\u{1e01b}', '\u{1e021}', Extend),
+    ('\u{1e023}', '\u{1e024}', Extend),
+    ('\u{1e026}', '\u{1e02a}', Extend),
+    ('\u{1e08f}', '\u{1e08f}', Extend),
+    ('\u{1e130}', '\u{1e136}', Extend),
+    ('\u{1e2ae}', '\u{1e2ae}', Extend),
+    ('\u{1e2ec}', '\u{1e2ef}', Extend),
+    ('\u{1e4ec}', '\u{1e4ef}', Extend),
+    ('\u{1e5ee}', '\u{1e5ef}', Extend),
+    ('\u{1e6e3}', '\u{1e6e3}', Extend),
+    ('\u{1e6e6}', '\u{1e6e6}', Extend),
+    ('\u{1e6ee}', '\u{1e6ef}', Extend),
+    ('\u{1e6f5}', '\u{1e6f5}', Extend),
+    ('\u{1e8d0}', '\u{1e8d6}', Extend),
+    ('\u{1e944}', '\u{1e94a}', Extend),
+    ('\u{1f004}', '\u{1f004}', Pictographic),
+    ('\u{1f02c}', '\u{1f02f}', Pictographic),
+    ('\u{1f094}', '\u{1f09f}', Pictographic),
+    ('\u{1f0af}', '\u{1f0b0}', Pictographic),
+    ('\u{1f0c0}', '\u{1f0c0}', Pictographic),
+    ('\u{1f0cf}', '\u{1f0d0}', Pictographic),
+    ('\u{1f0f6}', '\u{1f0ff}', Pictographic),
+    ('\u{1f170}', '\u{1f171}', Pictographic),
+    ('\u{1f17e}', '\u{1f17f}', Pictographic),
+    ('\u{1f18e}', '\u{1f18e}', Pictographic),
+    ('\u{1f191}', '\u{1f19a}', Pictographic),
+    ('\u{1f1ae}', '\u{1f1e5}', Pictographic),
+    ('\u{1f1e6}', '\u{1f1ff}', Conjoining),
+    ('\u{1f201}', '\u{1f20f}', Pictographic),
+    ('\u{1f21a}', '\u{1f21a}', Pictographic),
+    ('\u{1f22f}', '\u{1f22f}', Pictographic),
+    ('\u{1f232}', '\u{1f23a}', Pictographic),
+    ('\u{1f23c}', '\u{1f23f}', Pictographic),
+    ('\u{1f249}', '\u{1f25f}', Pictographic),
+    ('\u{1f266}', '\u{1f321}', Pictographic),
+    ('\u{1f324}', '\u{1f393}', Pictographic),
+    ('\u{1f396}', '\u{1f397}', Pictographic),
+    ('\u{1f399}', '\u{1f39b}', Pictographic),
+    ('\u{1f39e}', '\u{1f3f0}', Pictographic),
+    ('\u{1f3f3}', '\u{1f3f5}', Pictographic),
+    ('\u{1f3f7}', '\u{1f3fa}', Pictographic),
+    ('\u{1f3fb}', '\u{1f3ff}', Extend),
+    ('\u{1f400}', '\u{1f4fd}', Pictographic),
+    ('\u{1f4ff}', '\u{1f53d}', Pictographic),
+    ('\u{1f549}', '\u{1f54e}', Pictographic),
+    ('\u{1f550}', '\u{1f567}', Pictographic),
+    ('\u{1f56f}', '\u{1f570}', Pictographic),
+    ('\u{1f573}', '\u{1f57a}', Pictographic),
+    ('\u{1f587}', '\u{1f587}', Pictographic),
+    ('\u{1f58a}', '\u{1f58d}', Pictographic),
+    ('\u{1f590}', '\u{1f590}', Pictographic),
+    ('\u{1f595}', '\u{1f596}', Pictographic),
+    ('\u{1f5a4}', '\u{1f5a5}', Pictographic),
+    ('\u{1f5a8}', '\u{1f5a8}', Pictographic),
+    ('\u{1f5b1}', '\u{1f5b2}', Pictographic),
+    ('\u{1f5bc}', '\u{1f5bc}', Pictographic),
+    ('\u{1f5c2}', '\u{1f5c4}', Pictographic),
+    ('\u{1f5d1}', '\u{1f5d3}', Pictographic),
+    ('\u{1f5dc}', '\u{1f5de}', Pictographic),
+    ('\u{1f5e1}', '\u{1f5e1}', Pictographic),
+    ('\u{1f5e3}', '\u{1f5e3}', Pictographic),
+    ('\u{1f5e8}', '\u{1f5e8}', Pictographic),
+    ('\u{1f5ef}', '\u{1f5ef}', Pictographic),
+    ('\u{1f5f3}', '\u{1f5f3}', Pictographic),
+    ('\u{1f5fa}', '\u{1f64f}', Pictographic),
+    ('\u{1f680}', '\u{1f6c5}', Pictographic),
+    ('\u{1f6cb}', '\u{1f6d2}', Pictographic),
+    ('\u{1f6d5}', '\u{1f6e5}', Pictographic),
+    ('\u{1f6e9}', '\u{1f6e9}', Pictographic),
+    ('\u{1f6eb}', '\u{1f6f0}', Pictographic),
+    ('\u{1f6f3}', '\u{1f6ff}', Pictographic),
+    ('\u{1f7da}', '\u{1f7ff}', Pictographic),
+    ('\u{1f80c}', '\u{1f80f}', Pictographic),
+    ('\u{1f848}', '\u{1f84f}', Pictographic),
+    ('\u{1f85a}', '\u{1f85f}', Pictographic),
+    ('\u{1f888}', '\u{1f88f}', Pictographic),
+    ('\u{1f8ae}', '\u{1f8af}', Pictographic),
+    ('\u{1f8bc}', '\u{1f8bf}', Pictographic),
+    ('\u{1f8c2}', '\u{1f8cf}', Pictographic),
+    ('\u{1f8d9}', '\u{1f8ff}', Pictographic),
+    ('\u{1f90c}', '\u{1f93a}', Pictographic),
+    ('\u{1f93c}', '\u{1f945}', Pictographic),
+    ('\u{1f947}', '\u{1f9ff}', Pictographic),
+    ('\u{1fa58}', '\u{1fa5f}', Pictographic),
+    ('\u{1fa6e}', '\u{1faff}', Pictographic),
+    ('\u{1fc00}', '\u{1fffd}', Pictographic),
+    ('\u{e0020}', '\u{e007f}', Extend),
+    ('\u{e0100}', '\u{e01ef}', Extend),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{joining, Joining, TABLE};
+
+    #[test]
+    fn table_is_sorted() {
+        assert!(TABLE.iter().all(|&(start, end, _)| start <= end));
+        assert!(TABLE.windows(2).all(|pair| pair[0].1 < pair[1].0));
+    }
+
+    #[test]
+    fn classifies_characters() {
+        assert_eq!(joining('a'), Joining::None);
+        assert_eq!(joining(' '), Joining::None);
+        assert_eq!(joining('\u{301}'), Joining::Extend);
+        assert_eq!(joining('\u{200d}'), Joining::Extend);
+        assert_eq!(joining('\u{903}'), Joining::Extend);
+        assert_eq!(joining('\u{1f3fb}'), Joining::Extend);
+        assert_eq!(joining('\u{600}'), Joining::Prepend);
+        assert_eq!(joining('\u{1f1e6}'), Joining::Conjoining);
+        assert_eq!(joining('한'), Joining::Conjoining);
+        assert_eq!(joining('😀'), Joining::Pictographic);
+        assert_eq!(joining('©'), Joining::Pictographic);
+    }
+}
@@ -0,0 +1,66 @@
+//! Collapsing whitespace into fixed-capacity `heapless` strings, without allocating.
+
+use heapless::{CapacityError, String};
+
+use crate::split_collapsed_words;
+
+/// Collapses consecutive spaces and tabs in `input`, like `collapse_whitespace`, into a
+/// `heapless::String` of capacity `N`.
+///
+/// The string lives on the stack or in a static, so command strings can be normalized
+/// in firmware without a heap. Like [`split_collapsed_words`], this only needs `core`:
+/// it is available on `no_std` targets, without the default `std` feature.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+///
+/// # Returns
+/// - `Ok` with the collapsed string, stored inline.
+/// - `Err(CapacityError)` if the collapsed string is longer than `N` bytes.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_whitespace_heapless;
+///
+/// let command = collapse_whitespace_heapless::<16>("  set \t led   on ").unwrap();
+/// assert_eq!(command, "set led on");
+/// assert!(collapse_whitespace_heapless::<4>("  set \t led   on ").is_err());
+/// ```
+///
+/// # Performance
+/// - Never allocates: the words of `input` are found with the SIMD scanner of
+///   [`split_collapsed_words`] and copied straight into the string.
+/// - Stops at the first word that does not fit.
+pub fn collapse_whitespace_heapless<const N: usize>(input: &str) -> Result<String<N>, CapacityError> {
+    let mut collapsed = String::new();
+    let mut words = split_collapsed_words(input);
+    if let Some(first) = words.next() {
+        collapsed.push_str(first)?;
+        for word in words {
+            collapsed.push(' ')?;
+            collapsed.push_str(word)?;
+        }
+    }
+    Ok(collapsed)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::collapse_whitespace_heapless;
+    use crate::collapse_whitespace;
+
+    #[test]
+    fn matches_collapse_whitespace() {
+        for text in ["", "   ", "clean text", "  Hello  \t world ", "こんにちは\t\t世界 ", "line one  \n\t line two"] {
+            assert_eq!(collapse_whitespace_heapless::<64>(text).unwrap(), collapse_whitespace(text).as_str(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_output_that_does_not_fit() {
+        assert_eq!(collapse_whitespace_heapless::<7>("  a  b \t c  d ").unwrap(), "a b c d");
+        assert!(collapse_whitespace_heapless::<6>("  a  b \t c  d ").is_err());
+        assert!(collapse_whitespace_heapless::<0>("a").is_err());
+        assert!(collapse_whitespace_heapless::<0>(" \t ").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,510 @@
+//! `std::io` adapters that collapse whitespace on the fly.
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::{kernel, Collapser, StreamCollapser};
+
+/// A writer that collapses whitespace in everything written through it.
+///
+/// Bytes are collapsed as they arrive using a [`StreamCollapser`], so it can be dropped
+/// into `io::copy` or serializer pipelines without materializing the input. Call
+/// [`finish`](Self::finish) once everything is written: output that depends on the end
+/// of the input, such as an incomplete UTF-8 sequence or a trailing newline policy, is
+/// only written then.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::CollapseWriter;
+/// use std::io::Write;
+///
+/// let mut writer = CollapseWriter::new(Vec::new());
+/// writer.write_all(b"  Hello, ")?;
+/// writer.write_all(b"   world  ")?;
+/// let output = writer.finish()?;
+/// assert_eq!(output, b"Hello, world");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct CollapseWriter<W: Write> {
+    inner: W,
+    stream: StreamCollapser,
+    /// Collapsed output not yet accepted by `inner`.
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> CollapseWriter<W> {
+    /// Creates a writer collapsing like [`collapse_whitespace`](crate::collapse_whitespace).
+    pub fn new(inner: W) -> Self {
+        Self::with_collapser(inner, Collapser::new())
+    }
+
+    /// Creates a writer collapsing with the options of `collapser`.
+    pub fn with_collapser(inner: W, collapser: Collapser) -> Self {
+        Self {
+            inner,
+            stream: collapser.stream(),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    ///
+    /// Writing to it directly interleaves with collapsed output that is still buffered.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Finishes the collapsed output, flushes it and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.stream.finish(&mut self.buffer);
+        self.write_buffer()?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    /// Writes the buffered output to `inner`, keeping whatever it did not accept.
+    fn write_buffer(&mut self) -> io::Result<()> {
+        let mut written = 0;
+        let result = loop {
+            if written == self.buffer.len() {
+                break Ok(());
+            }
+            match self.inner.write(&self.buffer[written..]) {
+                Ok(0) => break Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write collapsed output")),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.buffer.drain(..written);
+        result
+    }
+}
+
+impl<W: Write> Write for CollapseWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Output left over from a failed write goes first
+        self.write_buffer()?;
+        self.stream.push_bytes(buf, &mut self.buffer);
+
+        // `buf` is consumed either way; an error resurfaces on the next call
+        if let Err(e) = self.write_buffer() {
+            if e.kind() == io::ErrorKind::WriteZero {
+                return Err(e);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_buffer()?;
+        self.inner.flush()
+    }
+}
+
+/// Size of the chunks a [`CollapseReader`] reads from the underlying reader.
+pub(crate) const READ_CHUNK: usize = 8 * 1024;
+
+/// A reader that yields the collapsed contents of another reader.
+///
+/// The underlying reader is read in chunks and collapsed with a [`StreamCollapser`], so
+/// runs split across chunk refills are handled and downstream parsers see normalized
+/// text without an extra pass. It also implements [`BufRead`], so collapsed lines can
+/// be read with [`BufRead::lines`].
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::CollapseReader;
+/// use std::io::Read;
+///
+/// let mut reader = CollapseReader::new("  Hello,   \t world  ".as_bytes());
+/// let mut output = String::new();
+/// reader.read_to_string(&mut output)?;
+/// assert_eq!(output, "Hello, world");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct CollapseReader<R: Read> {
+    inner: R,
+    stream: StreamCollapser,
+    /// Bytes read from `inner`.
+    input: Box<[u8]>,
+    /// Collapsed output, of which `output[position..]` has not been read yet.
+    output: Vec<u8>,
+    position: usize,
+    /// `inner` reached its end and the stream has been finished.
+    finished: bool,
+}
+
+impl<R: Read> CollapseReader<R> {
+    /// Creates a reader collapsing like [`collapse_whitespace`](crate::collapse_whitespace).
+    pub fn new(inner: R) -> Self {
+        Self::with_collapser(inner, Collapser::new())
+    }
+
+    /// Creates a reader collapsing with the options of `collapser`.
+    pub fn with_collapser(inner: R, collapser: Collapser) -> Self {
+        Self {
+            inner,
+            stream: collapser.stream(),
+            input: vec![0; READ_CHUNK].into_boxed_slice(),
+            output: Vec::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly skips input that has not been collapsed yet.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the underlying reader, discarding collapsed output not read yet.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CollapseReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for CollapseReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        // A chunk may collapse to nothing, so keep reading until there is output
+        while self.position == self.output.len() && !self.finished {
+            self.output.clear();
+            self.position = 0;
+            match self.inner.read(&mut self.input)? {
+                0 => {
+                    self.stream.finish(&mut self.output);
+                    self.finished = true;
+                }
+                n => self.stream.push_bytes(&self.input[..n], &mut self.output),
+            }
+        }
+        Ok(&self.output[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.output.len());
+    }
+}
+
+/// Byte counts of a collapsing copy, returned by [`collapse_file_to`](crate::collapse_file_to).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// Number of bytes read from the input.
+    pub bytes_read: u64,
+    /// Number of collapsed bytes written to the output.
+    pub bytes_written: u64,
+}
+
+/// Copies `reader` to `writer`, collapsing whitespace like
+/// [`collapse_whitespace`](crate::collapse_whitespace).
+///
+/// The input is read in chunks of `buf_size` bytes and collapsed with a
+/// [`StreamCollapser`], which carries runs and multi-byte characters across chunk
+/// boundaries, so a filter program is a single call. `writer` is flushed at the end.
+///
+/// # Parameters
+/// - `reader`: The input to collapse, such as `io::stdin().lock()`.
+/// - `writer`: The output to write to, such as `io::stdout().lock()`.
+/// - `buf_size`: The size of the read buffer in bytes. Memory use stays proportional to
+///   it, whatever the size of the input.
+///
+/// # Returns
+/// - The number of bytes written, or the first I/O error. `Interrupted` reads are retried.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_stream;
+///
+/// let mut output = Vec::new();
+/// let written = collapse_stream("  Hello,   \t world  ".as_bytes(), &mut output, 4)?;
+/// assert_eq!((output.as_slice(), written), (&b"Hello, world"[..], 12));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn collapse_stream<R: Read, W: Write>(mut reader: R, mut writer: W, buf_size: usize) -> io::Result<u64> {
+    copy_collapsed(&mut reader, &mut writer, Collapser::new(), buf_size).map(|stats| stats.bytes_written)
+}
+
+/// Copies `reader` to `writer`, collapsing with `collapser` through buffers of
+/// `buffer_size` bytes.
+pub(crate) fn copy_collapsed<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    collapser: Collapser,
+    buffer_size: usize,
+) -> io::Result<Stats> {
+    let mut stream = collapser.stream();
+    let mut input = vec![0; buffer_size.max(1)];
+    let mut output = Vec::with_capacity(buffer_size);
+    let mut stats = Stats::default();
+    loop {
+        let n = match reader.read(&mut input) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        stream.push_bytes(&input[..n], &mut output);
+        writer.write_all(&output)?;
+        stats.bytes_read += n as u64;
+        stats.bytes_written += output.len() as u64;
+        output.clear();
+    }
+    stream.finish(&mut output);
+    writer.write_all(&output)?;
+    writer.flush()?;
+    stats.bytes_written += output.len() as u64;
+    Ok(stats)
+}
+
+/// Returns an iterator over the collapsed and trimmed lines of `reader`.
+///
+/// Lines are read with [`BufRead::read_line`] and split like [`BufRead::lines`]: the
+/// items carry no `\n` or `\r\n` line break. Every line is collapsed and trimmed as by
+/// [`collapse_lines`](crate::collapse_lines). Call
+/// [`skip_empty`](CollapsedLines::skip_empty) to drop lines that end up empty, which is
+/// what log ingestion usually wants.
+///
+/// # Parameters
+/// - `reader`: The buffered reader to read lines from.
+///
+/// # Returns
+/// - A [`CollapsedLines`] iterator yielding `io::Result<String>`. Errors from `reader`,
+///   including invalid UTF-8, are passed through.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapsed_lines;
+///
+/// let log = "  GET   /index  200 \r\n \t \n POST\t/login   302\n";
+/// let lines: Vec<String> = collapsed_lines(log.as_bytes()).skip_empty(true).collect::<Result<_, _>>()?;
+/// assert_eq!(lines, ["GET /index 200", "POST /login 302"]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// # Performance
+/// - Each line is collapsed with the SIMD kernel of `collapse_whitespace`.
+/// - The buffer lines are read into is reused across lines.
+pub fn collapsed_lines<R: BufRead>(reader: R) -> CollapsedLines<R> {
+    CollapsedLines {
+        reader,
+        line: String::new(),
+        skip_empty: false,
+    }
+}
+
+/// Iterator over the collapsed lines of a reader, created by [`collapsed_lines`].
+#[derive(Debug)]
+pub struct CollapsedLines<R> {
+    reader: R,
+    /// The line being read, including its line break.
+    line: String,
+    skip_empty: bool,
+}
+
+impl<R> CollapsedLines<R> {
+    /// Sets whether lines that are empty after collapsing are skipped.
+    ///
+    /// Default: `false`
+    pub fn skip_empty(mut self, skip: bool) -> Self {
+        self.skip_empty = skip;
+        self
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for CollapsedLines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e)),
+            }
+
+            let line = self.line.strip_suffix('\n').unwrap_or(&self.line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let mut result = Vec::with_capacity(line.len());
+            kernel::collapse_bytes_into(line.as_bytes(), &mut result);
+            if self.skip_empty && result.is_empty() {
+                continue;
+            }
+
+            // Safety: The kernel only ever removes spaces/tabs or writes a space
+            return Some(Ok(unsafe { String::from_utf8_unchecked(result) }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collapse_stream, collapsed_lines, CollapseReader, CollapseWriter, READ_CHUNK};
+    use crate::{collapse_whitespace, Collapser, TrailingNewline};
+    use std::io::{self, BufRead, Read, Write};
+
+    /// A writer accepting at most one byte per call, and failing two calls out of three.
+    struct Flaky {
+        written: Vec<u8>,
+        calls: usize,
+    }
+
+    impl Write for Flaky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            match self.calls % 3 {
+                0 => Err(io::ErrorKind::Interrupted.into()),
+                1 => Err(io::ErrorKind::WouldBlock.into()),
+                _ => {
+                    self.written.extend_from_slice(&buf[..buf.len().min(1)]);
+                    Ok(buf.len().min(1))
+                }
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn copies_through_collapsed() {
+        let input = "  Some \t text\n  split   across  \u{3000} many   writes  ";
+        let mut writer = CollapseWriter::new(Vec::new());
+        for chunk in input.as_bytes().chunks(3) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), collapse_whitespace(input).as_bytes());
+    }
+
+    #[test]
+    fn works_with_io_copy() {
+        let input = "a   b \r\n\r\n\r\n  c  \n";
+        let collapser = Collapser::new().squeeze_blank_lines(true).trailing_newline(TrailingNewline::Strip);
+        let mut writer = CollapseWriter::with_collapser(Vec::new(), collapser);
+        io::copy(&mut input.as_bytes(), &mut writer).unwrap();
+        assert_eq!(writer.finish().unwrap(), collapser.collapse(input).as_bytes());
+    }
+
+    #[test]
+    fn keeps_output_across_failed_writes() {
+        let input = "  retry   until \t everything  is   written  ";
+        let mut writer = CollapseWriter::new(Flaky { written: Vec::new(), calls: 0 });
+        let mut rest = input.as_bytes();
+        while !rest.is_empty() {
+            match writer.write(&rest[..rest.len().min(4)]) {
+                Ok(n) => rest = &rest[n..],
+                Err(e) => assert_eq!(e.kind(), io::ErrorKind::WouldBlock),
+            }
+        }
+        while let Err(e) = writer.flush() {
+            assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+        }
+        assert_eq!(writer.get_ref().written, collapse_whitespace(input).as_bytes());
+    }
+
+    /// A reader returning at most `step` bytes per call.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.data.len().min(self.step).min(buf.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn reads_collapsed_across_refills() {
+        let input = "  runs \t  straddle\u{3000} refills é\u{a0}  😀   here  \n  and  there ";
+        for step in 1..8 {
+            let mut reader = CollapseReader::new(Trickle { data: input.as_bytes(), step });
+            let mut output = String::new();
+            reader.read_to_string(&mut output).unwrap();
+            assert_eq!(output, collapse_whitespace(input), "step {step}");
+        }
+    }
+
+    #[test]
+    fn reads_inputs_larger_than_a_chunk() {
+        let input = "word   \t ".repeat(READ_CHUNK / 3);
+        let collapser = Collapser::new().trailing_newline(TrailingNewline::Single);
+        let mut reader = CollapseReader::with_collapser(input.as_bytes(), collapser);
+        let mut output = Vec::new();
+        let mut buf = [0; 100];
+        loop {
+            match reader.read(&mut buf).unwrap() {
+                0 => break,
+                n => output.extend_from_slice(&buf[..n]),
+            }
+        }
+        assert_eq!(output, collapser.collapse(&input).as_bytes());
+    }
+
+    #[test]
+    fn reads_collapsed_lines() {
+        let reader = CollapseReader::new("  one   line \n   \t two  ".as_bytes());
+        let lines: Vec<String> = reader.lines().collect::<io::Result<_>>().unwrap();
+        assert_eq!(lines, ["one line ", " two"]);
+    }
+
+    #[test]
+    fn collapses_lines_of_a_reader() {
+        let input = "  one   line \r\n\n \t \n\t two\t\tlines  \nlast  ";
+        let lines: Vec<String> = collapsed_lines(input.as_bytes()).collect::<io::Result<_>>().unwrap();
+        assert_eq!(lines, ["one line", "", "", "two lines", "last"]);
+        let lines: Vec<String> = collapsed_lines(input.as_bytes()).skip_empty(true).collect::<io::Result<_>>().unwrap();
+        assert_eq!(lines, ["one line", "two lines", "last"]);
+    }
+
+    #[test]
+    fn passes_through_read_errors() {
+        let mut lines = collapsed_lines(&b"ok  line\n\xff\xfe\n"[..]);
+        assert_eq!(lines.next().unwrap().unwrap(), "ok line");
+        assert_eq!(lines.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn streams_reader_to_writer() {
+        let input = "  a  filter\u{3000}  program \t\n  in   one  line  ".repeat(100);
+        for buf_size in [0, 1, 2, 3, 7, 64, 4096] {
+            let mut output = Vec::new();
+            let written = collapse_stream(input.as_bytes(), &mut output, buf_size).unwrap();
+            assert_eq!(output, collapse_whitespace(&input).as_bytes(), "{buf_size}");
+            assert_eq!(written, output.len() as u64);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Collapsing whitespace in every string of a `serde_json::Value` tree.
+
+use serde_json::Value;
+
+use crate::batch;
+
+/// Collapses consecutive spaces and tabs in every string of `value`, like
+/// [`collapse_whitespace`](crate::collapse_whitespace), at any depth.
+///
+/// The strings of arrays and object members are collapsed; object keys are kept as
+/// they are. Use a [`JsonCollapser`] to leave the values of some keys untouched.
+///
+/// # Parameters
+/// - `value`: The JSON value to collapse, updated in place.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_json_strings;
+/// use serde_json::json;
+///
+/// let mut payload = json!({"name": "  Jane  Doe ", "tags": ["\trust", "clean"], "age": 42});
+/// collapse_json_strings(&mut payload);
+/// assert_eq!(payload, json!({"name": "Jane Doe", "tags": ["rust", "clean"], "age": 42}));
+/// ```
+///
+/// # Performance
+/// - Strings that are already collapsed are left untouched; the others are collapsed
+///   with the SIMD kernel of `collapse_whitespace` through one scratch buffer, reusing
+///   their allocation.
+/// - Walks the tree with an explicit stack, so deeply nested values cannot overflow the
+///   call stack.
+pub fn collapse_json_strings(value: &mut Value) {
+    JsonCollapser::new().collapse(value);
+}
+
+/// Collapses whitespace in the strings of `serde_json::Value` trees, like
+/// [`collapse_json_strings`], except under the keys it is told to skip.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::JsonCollapser;
+/// use serde_json::json;
+///
+/// let collapser = JsonCollapser::new().skip_key("password").skip_key("body");
+/// let mut payload = json!({"user": {"name": " Jane  Doe", "password": "  two  spaces "}, "body": "keep\t this"});
+/// collapser.collapse(&mut payload);
+/// assert_eq!(payload, json!({"user": {"name": "Jane Doe", "password": "  two  spaces "}, "body": "keep\t this"}));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct JsonCollapser {
+    skipped_keys: Vec<String>,
+}
+
+impl JsonCollapser {
+    /// Creates a collapser for every string of a value.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves the value of every object member named `key` untouched, at any depth,
+    /// including all the strings nested in it.
+    pub fn skip_key(mut self, key: impl Into<String>) -> Self {
+        self.skipped_keys.push(key.into());
+        self
+    }
+
+    /// Collapses every string of `value` that is not under a skipped key, in place.
+    ///
+    /// # Parameters
+    /// - `value`: The JSON value to collapse, updated in place.
+    pub fn collapse(&self, value: &mut Value) {
+        let mut scratch = Vec::new();
+        let mut pending = vec![value];
+        while let Some(value) = pending.pop() {
+            match value {
+                Value::String(string) => batch::collapse_in_place_with_scratch(string, &mut scratch),
+                Value::Array(values) => pending.extend(values.iter_mut()),
+                Value::Object(members) => {
+                    pending.extend(members.iter_mut().filter(|(key, _)| !self.is_skipped(key)).map(|(_, value)| value));
+                }
+                Value::Null | Value::Bool(_) | Value::Number(_) => {}
+            }
+        }
+    }
+
+    fn is_skipped(&self, key: &str) -> bool {
+        self.skipped_keys.iter().any(|skipped| skipped == key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{collapse_json_strings, JsonCollapser};
+
+    #[test]
+    fn collapses_strings_at_any_depth() {
+        let mut value = json!({
+            "  key  ": "  a \t b ",
+            "list": [" x  y", ["\tnested\t\tarray "], {"deep": {"deeper": "こんにちは\t\t世界"}}],
+            "other": [null, true, 1.5, ""],
+        });
+        collapse_json_strings(&mut value);
+        assert_eq!(value, json!({
+            "  key  ": "a b",
+            "list": ["x y", ["nested array"], {"deep": {"deeper": "こんにちは 世界"}}],
+            "other": [null, true, 1.5, ""],
+        }));
+
+        let mut string = Value::from("  lone  string ");
+        collapse_json_strings(&mut string);
+        assert_eq!(string, "lone string");
+    }
+
+    #[test]
+    fn skips_the_given_keys() {
+        let collapser = JsonCollapser::new().skip_key("raw");
+        let mut value = json!({"raw": {"a": "  kept  "}, "items": [{"raw": " kept ", "text": " cut "}]});
+        collapser.collapse(&mut value);
+        assert_eq!(value, json!({"raw": {"a": "  kept  "}, "items": [{"raw": " kept ", "text": "cut"}]}));
+    }
+
+    #[test]
+    fn handles_deep_nesting() {
+        let mut value = Value::from("  deep  ");
+        for _ in 0..100_000 {
+            value = Value::Array(vec![value]);
+        }
+        collapse_json_strings(&mut value);
+        let mut inner = &value;
+        while let Value::Array(values) = inner {
+            inner = &values[0];
+        }
+        assert_eq!(inner, "deep");
+        // Dropping a value this deep recursively would overflow the stack itself
+        while let Value::Array(mut values) = value {
+            value = values.pop().unwrap();
+        }
+    }
+}
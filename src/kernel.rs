@@ -0,0 +1,665 @@
+//! The core space/tab collapsing kernels behind [`collapse_whitespace`](crate::collapse_whitespace).
+
+use crate::{scan, Backend};
+
+mod chunks;
+#[cfg(all(feature = "simd-optimized", target_arch = "aarch64", target_feature = "neon"))]
+mod neon;
+#[cfg(feature = "portable-simd")]
+mod portable;
+#[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+mod x86;
+#[cfg(all(feature = "avx512", target_arch = "x86_64", target_feature = "sse2"))]
+mod avx512;
+#[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+mod huge;
+#[cfg(feature = "memchr")]
+mod sparse;
+#[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+mod ssse3;
+mod swar;
+
+/// Collapses consecutive spaces and tabs into a single space, trimming both ends.
+///
+/// Works on any ASCII-compatible bytes: only `b' '` and `b'\t'` are inspected and every
+/// other byte is copied through unchanged, so valid UTF-8 input gives valid UTF-8 output.
+pub(crate) fn collapse_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(estimated_len(bytes));
+    collapse::<false>(bytes, &mut result);
+    result
+}
+
+/// Like `collapse_bytes`, but appends the collapsed bytes to `out`.
+pub(crate) fn collapse_bytes_into(bytes: &[u8], out: &mut Vec<u8>) {
+    out.reserve(estimated_len(bytes));
+    collapse::<false>(bytes, out);
+}
+
+/// Like `collapse_bytes`, but also lowercases ASCII letters in the same pass.
+pub(crate) fn collapse_bytes_ascii_lowercase(bytes: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(estimated_len(bytes));
+    collapse::<true>(bytes, &mut result);
+    result
+}
+
+#[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+pub(crate) use huge::collapse_bytes_huge;
+#[cfg(all(feature = "kernels", target_arch = "x86_64", target_feature = "sse2"))]
+pub(crate) use x86::Kernel;
+
+/// Like `collapse_bytes`, but with `kernel` rather than the widest one the CPU supports.
+///
+/// # Safety
+/// - The CPU must support `kernel`.
+#[cfg(all(feature = "kernels", target_arch = "x86_64", target_feature = "sse2"))]
+#[inline(always)]
+pub(crate) unsafe fn collapse_bytes_with(kernel: Kernel, bytes: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(estimated_len(bytes));
+    // Safety: The caller guarantees the CPU supports `kernel`
+    unsafe { x86::collapse_with::<false>(kernel, bytes, &mut result) };
+    result
+}
+
+/// Returns `true` if this build has `backend` and the CPU can run it.
+pub(crate) fn supports(backend: Backend) -> bool {
+    match backend {
+        Backend::Auto | Backend::Scalar | Backend::Swar => true,
+        #[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+        Backend::Sse2 => true,
+        #[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+        Backend::Ssse3 => x86::Kernel::Ssse3.is_supported(),
+        #[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+        Backend::Avx2 => x86::Kernel::Avx2.is_supported(),
+        #[cfg(all(feature = "avx512", target_arch = "x86_64", target_feature = "sse2"))]
+        Backend::Avx512 => x86::Kernel::Avx512.is_supported(),
+        #[cfg(all(feature = "simd-optimized", target_arch = "aarch64", target_feature = "neon"))]
+        Backend::Neon => true,
+        #[cfg(all(feature = "simd-optimized", target_arch = "wasm32", target_feature = "simd128"))]
+        Backend::Simd128 => true,
+        #[cfg(feature = "memchr")]
+        Backend::Memchr => true,
+        _ => false,
+    }
+}
+
+/// Like `collapse_bytes`, but with `backend` alone, or `None` if it is not supported.
+///
+/// Unlike `Backend::Auto`, the other backends never hand the input to the scalar loop
+/// for being short or to `sparse::collapse` for having hardly any whitespace.
+pub(crate) fn collapse_bytes_with_backend(bytes: &[u8], backend: Backend) -> Option<Vec<u8>> {
+    if !supports(backend) {
+        return None;
+    }
+    let mut result = Vec::with_capacity(estimated_len(bytes));
+    #[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+    let x86 = |kernel, result: &mut Vec<u8>| {
+        // Safety: `supports` checked the CPU can run the kernel
+        unsafe { x86::collapse_with::<false>(kernel, bytes, result) }
+    };
+    match backend {
+        Backend::Scalar => chunks::collapse_rest::<false>(bytes, true, 0, &mut result),
+        Backend::Swar => collapse_swar::<false>(bytes, &mut result),
+        #[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+        Backend::Sse2 => x86(x86::Kernel::Sse2, &mut result),
+        #[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+        Backend::Ssse3 => x86(x86::Kernel::Ssse3, &mut result),
+        #[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+        Backend::Avx2 => x86(x86::Kernel::Avx2, &mut result),
+        #[cfg(all(feature = "avx512", target_arch = "x86_64", target_feature = "sse2"))]
+        Backend::Avx512 => x86(x86::Kernel::Avx512, &mut result),
+        #[cfg(all(feature = "simd-optimized", target_arch = "aarch64", target_feature = "neon"))]
+        Backend::Neon => collapse_neon::<false>(bytes, &mut result),
+        #[cfg(all(feature = "simd-optimized", target_arch = "wasm32", target_feature = "simd128"))]
+        Backend::Simd128 => collapse_simd128::<false>(bytes, &mut result),
+        #[cfg(feature = "memchr")]
+        Backend::Memchr => sparse::collapse::<false>(bytes, &mut result),
+        Backend::Auto => collapse::<false>(bytes, &mut result),
+        _ => unreachable!("`supports` rejects the backends this build does not have"),
+    }
+    Some(result)
+}
+
+/// Like `collapse_bytes`, for inputs far larger than the caches; only x86_64 has the
+/// non-temporal stores of `huge::collapse_bytes_huge`.
+#[cfg(not(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2")))]
+pub(crate) fn collapse_bytes_huge(bytes: &[u8]) -> Vec<u8> {
+    collapse_bytes(bytes)
+}
+
+/// The number of leading bytes `estimated_len` counts the repeated blanks of. A 4 KiB
+/// sample measured about 10% slower on long prose, for no better estimate of padded
+/// records.
+const ESTIMATE_SAMPLE_LEN: usize = 1024;
+
+/// Returns the capacity to reserve for collapsing `bytes`: its length for short input,
+/// and otherwise the share of its first `ESTIMATE_SAMPLE_LEN` bytes that collapsing
+/// keeps, scaled to the whole input with an eighth more as slack.
+///
+/// Reserving the input length would over-allocate up to tenfold for padded fixed-width
+/// exports, where most bytes are spaces. Input that turns out denser than its start
+/// just grows the output as it is written.
+fn estimated_len(bytes: &[u8]) -> usize {
+    if bytes.len() <= ESTIMATE_SAMPLE_LEN {
+        return bytes.len();
+    }
+    let kept = ESTIMATE_SAMPLE_LEN - repeated_blanks(&bytes[..ESTIMATE_SAMPLE_LEN]);
+    let estimate = (bytes.len() as u64 * kept as u64 / ESTIMATE_SAMPLE_LEN as u64) as usize;
+    (estimate + estimate / 8).min(bytes.len())
+}
+
+/// Returns the length `collapse_bytes(bytes)` would have, without writing any output.
+pub(crate) fn collapsed_len(bytes: &[u8]) -> usize {
+    let start = scan::find_blank(bytes, false);
+    let end = scan::rfind_blank(bytes, false).max(start);
+    let trimmed = &bytes[start..end];
+    trimmed.len() - repeated_blanks(trimmed)
+}
+
+/// Returns `true` if `collapse_bytes(bytes)` would return `bytes` unchanged.
+///
+/// The scan stops at the first leading or trailing blank, tab or pair of adjacent
+/// blanks, so input that needs collapsing is usually rejected within its first bytes.
+pub(crate) fn is_collapsed(bytes: &[u8]) -> bool {
+    let is_blank = |b: &u8| *b == b' ' || *b == b'\t';
+    !bytes.first().is_some_and(is_blank) && !bytes.last().is_some_and(is_blank) && !has_collapsible_blanks(bytes)
+}
+
+/// Inputs shorter than this go straight to the scalar loop of `collapse`: they fill no
+/// chunk, so the kernel dispatch would only add to the cost of short fields such as
+/// usernames. Longer thresholds measured slower, as the 16-byte chunks already pay off
+/// from one chunk on.
+#[cfg(any(
+    feature = "portable-simd",
+    all(
+        feature = "simd-optimized",
+        any(
+            all(target_arch = "x86_64", target_feature = "sse2"),
+            all(target_arch = "x86_64", target_feature = "avx2"),
+            all(target_arch = "aarch64", target_feature = "neon"),
+            all(target_arch = "wasm32", target_feature = "simd128")
+        )
+    )
+))]
+const SMALL_INPUT_LEN: usize = 16;
+
+/// Collapses `bytes` into `result`, optionally lowercasing ASCII letters along the way.
+///
+/// # Performance
+/// - Inputs shorter than `SMALL_INPUT_LEN` bytes skip the SIMD setup and the kernel
+///   dispatch, and are collapsed one byte at a time, branchlessly (see
+///   `chunks::collapse_scalar`).
+/// - On aarch64, longer inputs are collapsed one byte at a time up to a 16-byte
+///   boundary first, so no NEON load crosses a cache line (see `neon::ALIGNED_MIN_LEN`).
+/// - On x86_64, runs the widest kernel the CPU supports, detected at runtime (see
+///   `x86::collapse`), so binaries built for the baseline target still use AVX2, or
+///   AVX-512 with the `avx512` feature, where available.
+/// - Uses SIMD (NEON registers on aarch64, `u8x16` otherwise) to process superblocks
+///   of 64 bytes, then 16 bytes at a time, turning each chunk into a whitespace mask
+///   whose runs are handled with bit manipulation.
+/// - With the `portable-simd` feature, targets without such a kernel use `std::simd`
+///   vectors of 64 bytes instead.
+/// - Copies chunks without any whitespace in one go, and so are chunks that are
+///   already collapsed, with no two adjacent blanks.
+/// - Skips the chunks of a whitespace run after its first one without writing (see
+///   `chunks::skip_blank_chunks`), so long runs cost little more than their loads.
+/// - With SSSE3 or AVX2, writes the kept bytes of the other chunks with a `PSHUFB`
+///   shuffle picked from their keep-mask (see `ssse3::left_pack`), so they take no
+///   branch on the layout of their whitespace.
+/// - With the `memchr` feature, input whose first bytes have hardly any whitespace is
+///   collapsed by `sparse::collapse` instead, which jumps from blank to blank.
+/// - Collapses the incomplete chunk that ends the input as one more chunk, padded on
+///   the stack (or loaded with a mask with AVX-512), rather than one byte at a time.
+/// - Writes into the capacity the callers reserve from `estimated_len`, which the
+///   kernels storing whole vectors only grow when less than a vector is left.
+#[cfg(any(
+    feature = "portable-simd",
+    all(
+        feature = "simd-optimized",
+        any(
+            all(target_arch = "x86_64", target_feature = "sse2"),
+            all(target_arch = "x86_64", target_feature = "avx2"),
+            all(target_arch = "aarch64", target_feature = "neon"),
+            all(target_arch = "wasm32", target_feature = "simd128")
+        )
+    )
+))]
+fn collapse<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    if bytes.len() < SMALL_INPUT_LEN {
+        return chunks::collapse_rest::<ASCII_LOWERCASE>(bytes, true, result.len(), result);
+    }
+
+    #[cfg(all(
+        feature = "memchr",
+        not(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))
+    ))]
+    if sparse::is_sparse(bytes) {
+        return sparse::collapse::<ASCII_LOWERCASE>(bytes, result);
+    }
+
+    #[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+    x86::collapse::<ASCII_LOWERCASE>(bytes, result);
+
+    #[cfg(all(feature = "simd-optimized", target_arch = "aarch64", target_feature = "neon"))]
+    collapse_neon::<ASCII_LOWERCASE>(bytes, result);
+
+    #[cfg(all(feature = "simd-optimized", target_arch = "wasm32", target_feature = "simd128"))]
+    collapse_simd128::<ASCII_LOWERCASE>(bytes, result);
+
+    #[cfg(not(all(
+        feature = "simd-optimized",
+        any(
+            all(target_arch = "x86_64", target_feature = "sse2"),
+            all(target_arch = "x86_64", target_feature = "avx2"),
+            all(target_arch = "aarch64", target_feature = "neon"),
+            all(target_arch = "wasm32", target_feature = "simd128")
+        )
+    )))]
+    collapse_portable::<ASCII_LOWERCASE>(bytes, result);
+}
+
+/// Collapses `bytes` into `result` with the NEON chunks, from a 16-byte boundary on for
+/// longer inputs.
+#[cfg(all(feature = "simd-optimized", target_arch = "aarch64", target_feature = "neon"))]
+#[inline(always)]
+fn collapse_neon<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    use chunks::{collapse_chunks, collapse_rest, collapse_tail};
+    let start = result.len();
+    let mut i = 0;
+    let mut last_was_space = true;
+    if bytes.len() >= neon::ALIGNED_MIN_LEN {
+        chunks::collapse_prologue::<ASCII_LOWERCASE>(bytes, 16, &mut i, &mut last_was_space, result);
+    }
+    collapse_chunks::<neon::Block, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_chunks::<neon::Vector, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_tail::<neon::Vector, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_rest::<ASCII_LOWERCASE>(&bytes[i..], last_was_space, start, result);
+}
+
+/// Collapses `bytes` into `result` with superblocks of four SIMD128 vectors, then
+/// single ones.
+#[cfg(all(feature = "simd-optimized", target_arch = "wasm32", target_feature = "simd128"))]
+#[inline(always)]
+fn collapse_simd128<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    use chunks::{collapse_chunks, collapse_rest, collapse_tail, Superblock};
+    let start = result.len();
+    let mut i = 0;
+    let mut last_was_space = true;
+    collapse_chunks::<Superblock<wide::u8x16, 4>, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_chunks::<wide::u8x16, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_tail::<wide::u8x16, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_rest::<ASCII_LOWERCASE>(&bytes[i..], last_was_space, start, result);
+}
+
+/// Collapses `bytes` into `result` with `std::simd` vectors of 64, then 16 bytes.
+#[cfg(all(
+    feature = "portable-simd",
+    not(all(
+        feature = "simd-optimized",
+        any(
+            all(target_arch = "x86_64", target_feature = "sse2"),
+            all(target_arch = "x86_64", target_feature = "avx2"),
+            all(target_arch = "aarch64", target_feature = "neon"),
+            all(target_arch = "wasm32", target_feature = "simd128")
+        )
+    ))
+))]
+#[inline(always)]
+fn collapse_portable<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    use chunks::{collapse_chunks, collapse_rest, collapse_tail};
+    use std::simd::Simd;
+    let start = result.len();
+    let mut i = 0;
+    let mut last_was_space = true;
+    collapse_chunks::<Simd<u8, 64>, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_chunks::<Simd<u8, 16>, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_tail::<Simd<u8, 16>, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_rest::<ASCII_LOWERCASE>(&bytes[i..], last_was_space, start, result);
+}
+
+/// Scalar (non-SIMD) version of `collapse`.
+///
+/// # When is This Used?
+/// - If compiling for a **non-x86** or **non-aarch64** target.
+/// - If **SIMD is not available** on the target CPU.
+/// - If the Rust compiler **cannot enable** the required SIMD features.
+/// - If the `simd-optimized` feature is disabled, and `portable-simd` is not enabled.
+///
+/// # Performance
+/// - Runs the chunked kernel on `u64` words (see `swar::Word`), classifying 8 bytes at
+///   a time with integer bit tricks, so targets without vector registers, such as
+///   MIPS or RISC-V without the V extension, still copy whole words of text at once.
+#[cfg(not(any(
+    feature = "portable-simd",
+    all(
+        feature = "simd-optimized",
+        any(
+            all(target_arch = "x86_64", target_feature = "sse2"),
+            all(target_arch = "x86_64", target_feature = "avx2"),
+            all(target_arch = "aarch64", target_feature = "neon"),
+            all(target_arch = "wasm32", target_feature = "simd128")
+        )
+    )
+)))]
+fn collapse<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    #[cfg(feature = "memchr")]
+    if sparse::is_sparse(bytes) {
+        return sparse::collapse::<ASCII_LOWERCASE>(bytes, result);
+    }
+    collapse_swar::<ASCII_LOWERCASE>(bytes, result);
+}
+
+/// Collapses `bytes` into `result` with the SWAR chunks on `u64` words.
+#[inline(always)]
+fn collapse_swar<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    let start = result.len();
+    let mut i = 0;
+    let mut last_was_space = true;
+    chunks::collapse_chunks::<swar::Word, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    chunks::collapse_tail::<swar::Word, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    chunks::collapse_rest::<ASCII_LOWERCASE>(&bytes[i..], last_was_space, start, result);
+}
+
+/// Returns the number of spaces and tabs in `bytes` that directly follow another one,
+/// which are exactly the bytes collapsing removes from a trimmed input.
+///
+/// # Performance
+/// - Uses SIMD (`u8x16`) to count 16 bytes at a time, carrying the last lane of each
+///   chunk into the next.
+/// - Falls back to scalar processing for remaining bytes.
+#[cfg(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+))]
+fn repeated_blanks(bytes: &[u8]) -> usize {
+    use wide::u8x16;
+    let space = u8x16::splat(b' ');
+    let tab = u8x16::splat(b'\t');
+
+    let mut count = 0;
+    let mut after_blank = false;
+    let mut i = 0;
+    while i + 16 <= bytes.len() {
+        let arr: [u8; 16] = bytes[i..i + 16].try_into().unwrap();
+        let chunk = u8x16::from(arr);
+
+        let blanks = (chunk.cmp_eq(space) | chunk.cmp_eq(tab)).move_mask() as u32;
+        let preceded = (blanks << 1) | u32::from(after_blank);
+        count += (blanks & preceded).count_ones() as usize;
+        after_blank = blanks & 0x8000 != 0;
+
+        i += 16;
+    }
+
+    count + scalar_repeated_blanks(&bytes[i..], after_blank)
+}
+
+/// Returns `true` if `bytes` has a tab or two adjacent spaces or tabs.
+///
+/// # Performance
+/// - Uses SIMD (`u8x16`, or AVX2 where the CPU supports it) to test 64 bytes at a
+///   time, and returns at the first chunk that has either.
+/// - Falls back to scalar processing for remaining bytes.
+#[cfg(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+))]
+fn has_collapsible_blanks(bytes: &[u8]) -> bool {
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+    if x86::Kernel::best() >= x86::Kernel::Avx2 {
+        // Safety: The CPU supports AVX2, checked just above
+        return unsafe { x86::has_collapsible_blanks_avx2(bytes) };
+    }
+
+    use wide::u8x16;
+    let space = u8x16::splat(b' ');
+    let tab = u8x16::splat(b'\t');
+    let load = |at: usize| u8x16::from(<[u8; 16]>::try_from(&bytes[at..at + 16]).unwrap());
+
+    let mut i = 0;
+    // Each chunk is compared with the one starting a byte later, so a space followed
+    // by a space is found without moving any mask bits; a tab is always collapsible
+    while i + 65 <= bytes.len() {
+        let mut found = u8x16::splat(0);
+        for part in (i..i + 64).step_by(16) {
+            let (chunk, next) = (load(part), load(part + 1));
+            found |= (chunk.cmp_eq(space) & next.cmp_eq(space)) | chunk.cmp_eq(tab);
+        }
+        if found.any() {
+            return true;
+        }
+        i += 64;
+    }
+
+    scalar_has_collapsible_blanks(&bytes[i..], false)
+}
+
+/// Scalar version of `has_collapsible_blanks`, used when SIMD is unavailable.
+#[cfg(not(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+)))]
+fn has_collapsible_blanks(bytes: &[u8]) -> bool {
+    scalar_has_collapsible_blanks(bytes, false)
+}
+
+fn scalar_has_collapsible_blanks(bytes: &[u8], mut after_blank: bool) -> bool {
+    for &b in bytes {
+        let blank = b == b' ' || b == b'\t';
+        if b == b'\t' || (blank && after_blank) {
+            return true;
+        }
+        after_blank = blank;
+    }
+    false
+}
+
+/// Scalar version of `repeated_blanks`, used when SIMD is unavailable.
+#[cfg(not(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+)))]
+fn repeated_blanks(bytes: &[u8]) -> usize {
+    scalar_repeated_blanks(bytes, false)
+}
+
+fn scalar_repeated_blanks(bytes: &[u8], mut after_blank: bool) -> usize {
+    let mut count = 0;
+    for &b in bytes {
+        let blank = b == b' ' || b == b'\t';
+        count += usize::from(blank && after_blank);
+        after_blank = blank;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        collapse_bytes, collapse_bytes_ascii_lowercase, collapse_bytes_huge, collapsed_len, is_collapsed,
+        ESTIMATE_SAMPLE_LEN,
+    };
+
+    /// Byte-at-a-time reference for the kernels.
+    fn reference(bytes: &[u8], lowercase: bool) -> Vec<u8> {
+        let mut result = Vec::new();
+        let mut last_was_space = true;
+        for &b in bytes {
+            if b == b' ' || b == b'\t' {
+                if !last_was_space {
+                    result.push(b' ');
+                }
+                last_was_space = true;
+            } else {
+                result.push(if lowercase { b.to_ascii_lowercase() } else { b });
+                last_was_space = false;
+            }
+        }
+        if result.last() == Some(&b' ') {
+            result.pop();
+        }
+        result
+    }
+
+    /// Deterministic inputs mixing runs of blanks and other bytes at every alignment.
+    fn inputs() -> impl Iterator<Item = Vec<u8>> {
+        const ALPHABET: &[u8] = b"  \t\t aZ.\n\xc3\xa9";
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        (0..2000).map(move |n| {
+            (0..n % 150)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    ALPHABET[(state % ALPHABET.len() as u64) as usize]
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn matches_reference_and_counts() {
+        for input in inputs() {
+            let collapsed = collapse_bytes(&input);
+            assert_eq!(collapsed, reference(&input, false), "{input:?}");
+            assert_eq!(collapse_bytes_ascii_lowercase(&input), reference(&input, true), "{input:?}");
+            assert_eq!(collapsed_len(&input), collapsed.len(), "{input:?}");
+            assert_eq!(is_collapsed(&input), collapsed == input, "{input:?}");
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+    fn every_supported_x86_kernel_matches_reference() {
+        use super::x86::{collapse_with, Kernel};
+        let kernels = [Kernel::Sse2, Kernel::Ssse3, Kernel::Avx2, #[cfg(feature = "avx512")] Kernel::Avx512];
+        for kernel in kernels.into_iter().filter(|kernel| kernel.is_supported()) {
+            for input in inputs() {
+                let (mut collapsed, mut lowercased) = (Vec::new(), Vec::new());
+                // Safety: Only the supported kernels are run
+                unsafe {
+                    collapse_with::<false>(kernel, &input, &mut collapsed);
+                    collapse_with::<true>(kernel, &input, &mut lowercased);
+                }
+                assert_eq!(collapsed, reference(&input, false), "{kernel:?} {input:?}");
+                assert_eq!(lowercased, reference(&input, true), "{kernel:?} {input:?}");
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "memchr")]
+    fn sparse_kernel_matches_reference() {
+        for input in inputs() {
+            let (mut collapsed, mut lowercased) = (Vec::new(), Vec::new());
+            super::sparse::collapse::<false>(&input, &mut collapsed);
+            super::sparse::collapse::<true>(&input, &mut lowercased);
+            assert_eq!(collapsed, reference(&input, false), "{input:?}");
+            assert_eq!(lowercased, reference(&input, true), "{input:?}");
+        }
+
+        let long = [&b"\t"[..], &b"Xy".repeat(200), b" \t ", &b"z".repeat(300), b"  "].concat().repeat(8);
+        assert!(super::sparse::is_sparse(&long));
+        assert_eq!(collapse_bytes(&long), reference(&long, false));
+        assert_eq!(collapse_bytes_ascii_lowercase(&long), reference(&long, true));
+    }
+
+    #[test]
+    fn skips_runs_spanning_many_chunks() {
+        let mut inputs = Vec::new();
+        for run in [63, 64, 65, 128, 200, 1000] {
+            for lead in [0, 1, 15, 16, 63, 64] {
+                let blanks: Vec<u8> = (0..run).map(|n| if n % 7 == 3 { b'\t' } else { b' ' }).collect();
+                inputs.push([&b"Ab".repeat(lead)[..], &blanks, b"cD", &blanks, &b"e".repeat(lead + 3), &blanks].concat());
+            }
+        }
+        for input in &inputs {
+            assert_eq!(collapse_bytes(input), reference(input, false), "{input:?}");
+            assert_eq!(collapse_bytes_ascii_lowercase(input), reference(input, true), "{input:?}");
+        }
+
+        #[cfg(all(feature = "simd-optimized", target_arch = "x86_64", target_feature = "sse2"))]
+        {
+            use super::x86::{collapse_with, Kernel};
+            let kernels = [Kernel::Sse2, Kernel::Ssse3, Kernel::Avx2, #[cfg(feature = "avx512")] Kernel::Avx512];
+            for kernel in kernels.into_iter().filter(|kernel| kernel.is_supported()) {
+                for input in &inputs {
+                    let mut collapsed = Vec::new();
+                    // Safety: Only the supported kernels are run
+                    unsafe { collapse_with::<false>(kernel, input, &mut collapsed) };
+                    assert_eq!(collapsed, reference(input, false), "{kernel:?} {input:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reserves_for_mostly_blank_input() {
+        let padded = [b"a".as_slice(), &[b' '; 99]].concat().repeat(1000);
+        let collapsed = collapse_bytes(&padded);
+        assert_eq!(collapsed, reference(&padded, false));
+        assert!(collapsed.capacity() < padded.len() / 10, "{}", collapsed.capacity());
+
+        // Input that turns out denser than its sampled start still collapses whole
+        let denser = [&padded[..2 * ESTIMATE_SAMPLE_LEN], &b"no padding here ".repeat(1000)].concat();
+        assert_eq!(collapse_bytes(&denser), reference(&denser, false));
+        assert_eq!(collapse_bytes_ascii_lowercase(&denser), reference(&denser, true));
+    }
+
+    #[test]
+    fn huge_mode_matches_reference_across_blocks() {
+        let text = b"some  words\t and   runs ".repeat(3000);
+        let blanks = [b' '; 40_000];
+        let inputs = [
+            text.clone(),
+            [&blanks[..20_000], &text].concat(),
+            [&text, &blanks[..], &text, &blanks[..5]].concat(),
+            [b"x".as_slice(), &blanks, b"y"].concat(),
+            blanks.to_vec(),
+        ];
+        for input in inputs {
+            for offset in [0, 1, 5, 100] {
+                assert_eq!(collapse_bytes_huge(&input[offset..]), reference(&input[offset..], false), "{offset}");
+            }
+        }
+    }
+
+    #[test]
+    fn handles_long_inputs_at_every_offset() {
+        let long: Vec<u8> = inputs().skip(140).take(10).flatten().collect();
+        for offset in 0..64 {
+            let input = &long[offset..];
+            assert_eq!(collapse_bytes(input), reference(input, false), "{offset}");
+            assert_eq!(collapse_bytes_ascii_lowercase(input), reference(input, true), "{offset}");
+        }
+    }
+
+    #[test]
+    fn handles_every_chunk_mask() {
+        for mask in 0..=u16::MAX {
+            let chunk: Vec<u8> = (0..16).map(|lane| if mask >> lane & 1 == 1 { b' ' } else { b'x' }).collect();
+            for input in [chunk.clone(), [&b"y"[..], &chunk, &chunk, &chunk, &chunk, &chunk].concat()] {
+                assert_eq!(collapse_bytes(&input), reference(&input, false), "{mask:#x}");
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! The AVX-512 kernel, which writes the kept bytes of every 64-byte chunk with a single
+//! compress and store, without branching on the input.
+
+use std::arch::x86_64::*;
+
+use super::ssse3::grow;
+
+/// Packs the kept lanes of `chunk` to its front, given whether the byte before it was
+/// a space, and returns them with the keep-mask and the mask of the blank lanes.
+///
+/// The kept lanes are every byte but a space or tab that follows another one, and tabs
+/// become spaces.
+#[inline]
+#[target_feature(enable = "avx512f,avx512bw,avx512vbmi2")]
+fn pack<const ASCII_LOWERCASE: bool>(mut chunk: __m512i, last_was_space: bool) -> (__m512i, u64, u64) {
+    let space = _mm512_set1_epi8(b' ' as i8);
+    if ASCII_LOWERCASE {
+        let upper = _mm512_cmple_epu8_mask(_mm512_sub_epi8(chunk, _mm512_set1_epi8(b'A' as i8)), _mm512_set1_epi8(25));
+        chunk = _mm512_mask_blend_epi8(upper, chunk, _mm512_or_si512(chunk, _mm512_set1_epi8(0x20)));
+    }
+
+    let tabs = _mm512_cmpeq_epi8_mask(chunk, _mm512_set1_epi8(b'\t' as i8));
+    let blanks = _mm512_cmpeq_epi8_mask(chunk, space) | tabs;
+    let keep = !(blanks & ((blanks << 1) | u64::from(last_was_space)));
+    (_mm512_maskz_compress_epi8(keep, _mm512_mask_blend_epi8(tabs, chunk, space)), keep, blanks)
+}
+
+/// Returns the mask of the spaces and tabs among the 64 bytes at `bytes`.
+///
+/// # Safety
+/// - `bytes` must be valid for reads of 64 bytes.
+#[inline]
+#[target_feature(enable = "avx512f,avx512bw")]
+unsafe fn blank_mask(bytes: *const u8) -> u64 {
+    // Safety: The caller guarantees 64 readable bytes
+    let chunk = unsafe { _mm512_loadu_si512(bytes.cast()) };
+    _mm512_cmpeq_epi8_mask(chunk, _mm512_set1_epi8(b' ' as i8)) | _mm512_cmpeq_epi8_mask(chunk, _mm512_set1_epi8(b'\t' as i8))
+}
+
+/// Collapses the bytes of `bytes` from `*i` on into `result`, 64 at a time, and leaves
+/// `*i` at the end of the input.
+///
+/// # Safety
+/// - The CPU must support `POPCNT` and AVX-512 F, BW and VBMI2.
+///
+/// # Performance
+/// - Builds a 64-bit keep-mask per chunk (every byte but a space or tab that follows
+///   another one) and packs the kept bytes with `VPCOMPRESSB`.
+/// - Stores whole vectors into the spare capacity of `result`, so the output grows by
+///   the number of kept bytes without any per-byte push, and only grows `result` when
+///   less than a vector is left, so it keeps the capacity the caller estimated.
+/// - Skips the chunks of a whitespace run after its first one, only comparing them
+///   against spaces and tabs, so long runs take no compress or store.
+/// - Loads and stores the incomplete chunk that ends the input with masks, so it takes
+///   no byte-at-a-time loop either.
+#[target_feature(enable = "popcnt,avx512f,avx512bw,avx512vbmi2")]
+pub(super) unsafe fn collapse_chunks<const ASCII_LOWERCASE: bool>(
+    bytes: &[u8],
+    i: &mut usize,
+    last_was_space: &mut bool,
+    result: &mut Vec<u8>,
+) {
+    // The byte stores may alias anything, so the loop state is kept in locals rather
+    // than behind the references
+    let (mut pos, mut last) = (*i, *last_was_space);
+    let mut len = result.len();
+    let (mut out, mut capacity) = (result.as_mut_ptr(), result.capacity());
+
+    while pos + 64 <= bytes.len() {
+        if capacity - len < 64 {
+            // Safety: The first `len` bytes are the previous output and the kept bytes
+            (out, capacity) = unsafe { grow(result, len, 64) };
+        }
+        // Safety: `pos + 64 <= bytes.len()`, and unaligned loads are allowed
+        let chunk = unsafe { _mm512_loadu_si512(bytes.as_ptr().add(pos).cast()) };
+        let (packed, keep, blanks) = pack::<ASCII_LOWERCASE>(chunk, last);
+        if blanks == u64::MAX {
+            // A whitespace run: it becomes at most one space, and the chunks after this
+            // one that are all blanks too are skipped without storing anything
+            if !last {
+                // Safety: The capacity leaves room for 64 bytes at `len`
+                unsafe { out.add(len).write(b' ') };
+                len += 1;
+                last = true;
+            }
+            pos += 64;
+            // Safety: The loop only loads whole chunks of the input
+            while pos + 64 <= bytes.len() && unsafe { blank_mask(bytes.as_ptr().add(pos)) } == u64::MAX {
+                pos += 64;
+            }
+            continue;
+        }
+
+        // Safety: The capacity leaves room for the 64 bytes stored at `len`, of which the
+        // first `keep.count_ones()` are the kept bytes
+        unsafe { _mm512_storeu_si512(out.add(len).cast(), packed) };
+        len += keep.count_ones() as usize;
+        last = blanks >> 63 != 0;
+
+        pos += 64;
+    }
+
+    let rest = bytes.len() - pos;
+    if rest > 0 {
+        // The masked store only writes the kept bytes, so it needs no more room than the
+        // rest of the input
+        if capacity - len < rest {
+            // Safety: The first `len` bytes are the previous output and the kept bytes
+            (out, _) = unsafe { grow(result, len, rest) };
+        }
+        let lanes = (1 << rest) - 1;
+        // Safety: The mask only loads the `rest` bytes left from `pos` on, and masked
+        // loads do not fault on the lanes they skip
+        let chunk = unsafe { _mm512_maskz_loadu_epi8(lanes, bytes.as_ptr().add(pos).cast()) };
+        let (packed, keep, blanks) = pack::<ASCII_LOWERCASE>(chunk, last);
+        // The zeroed lanes past the input are kept, but never stored
+        let kept = (keep & lanes).count_ones();
+
+        // Safety: As above, and the mask only stores the kept bytes, which fit in the
+        // capacity
+        unsafe { _mm512_mask_storeu_epi8(out.add(len).cast(), (1 << kept) - 1, packed) };
+        len += kept as usize;
+        last = blanks >> (rest - 1) & 1 != 0;
+        pos = bytes.len();
+    }
+    // Safety: The first `len` bytes are the previous output and the kept bytes
+    unsafe { result.set_len(len) };
+    (*i, *last_was_space) = (pos, last);
+}
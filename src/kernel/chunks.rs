@@ -0,0 +1,439 @@
+//! The chunked kernel shared by the SIMD backends and the SWAR fallback, generic over
+//! their vector type.
+//!
+//! Each backend only implements [`Chunk`] for its vectors or words; classifying a chunk
+//! and writing its collapsed form from the whitespace mask is the same for all of them.
+
+/// The output of the chunk loops, written straight into the spare capacity of a `Vec`.
+///
+/// Collapsing never writes more bytes than it reads, so room for a whole chunk is made
+/// once per chunk, and the bytes it writes take no capacity check of their own. The
+/// length of the `Vec` is set once, when the output is dropped.
+struct Output<'a> {
+    result: &'a mut Vec<u8>,
+    out: *mut u8,
+    capacity: usize,
+    /// The length of `result` counting the bytes written so far.
+    len: usize,
+}
+
+impl<'a> Output<'a> {
+    #[inline(always)]
+    fn new(result: &'a mut Vec<u8>) -> Self {
+        let (out, capacity, len) = (result.as_mut_ptr(), result.capacity(), result.len());
+        Self { result, out, capacity, len }
+    }
+
+    /// Makes room for `n` more bytes.
+    #[inline(always)]
+    fn reserve(&mut self, n: usize) {
+        if self.capacity - self.len < n {
+            self.grow(n);
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn grow(&mut self, n: usize) {
+        self.commit();
+        self.result.reserve(n);
+        (self.out, self.capacity) = (self.result.as_mut_ptr(), self.result.capacity());
+    }
+
+    /// Appends `bytes`.
+    ///
+    /// # Safety
+    /// - `reserve` must have made room for `bytes` and every byte written after it.
+    #[inline(always)]
+    unsafe fn extend(&mut self, bytes: &[u8]) {
+        debug_assert!(self.capacity - self.len >= bytes.len());
+        // Safety: The caller guarantees the room, and `bytes` cannot overlap the spare
+        // capacity of `result`
+        unsafe { self.out.add(self.len).copy_from_nonoverlapping(bytes.as_ptr(), bytes.len()) };
+        self.len += bytes.len();
+    }
+
+    /// Appends `byte`.
+    ///
+    /// # Safety
+    /// - As for `extend`.
+    #[inline(always)]
+    unsafe fn push(&mut self, byte: u8) {
+        // Safety: The caller's guarantee is the same
+        unsafe { self.push_if(byte, true) };
+    }
+
+    /// Stores `byte` past the bytes written so far, and appends it if `keep` is set, so
+    /// that whether a byte is kept takes no branch.
+    ///
+    /// # Safety
+    /// - As for `extend`, counting `byte` even if it is not kept.
+    #[inline(always)]
+    unsafe fn push_if(&mut self, byte: u8, keep: bool) {
+        debug_assert!(self.capacity > self.len);
+        // Safety: The caller guarantees the room
+        unsafe { self.out.add(self.len).write(byte) };
+        self.len += usize::from(keep);
+    }
+
+    #[inline(always)]
+    fn commit(&mut self) {
+        // Safety: `extend` and `push` wrote every byte up to `len`, within the capacity
+        unsafe { self.result.set_len(self.len) };
+    }
+}
+
+impl Drop for Output<'_> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        self.commit();
+    }
+}
+
+/// For every byte, the byte written in its place: tabs become spaces, and in the second
+/// table ASCII uppercase letters become lowercase.
+///
+/// Only spaces and tabs map to a space, so the table classifies the bytes too.
+static SPACED: [[u8; 256]; 2] = {
+    let mut tables = [[0; 256]; 2];
+    let mut b = 0;
+    while b < 256 {
+        let byte = if b as u8 == b'\t' { b' ' } else { b as u8 };
+        tables[0][b] = byte;
+        tables[1][b] = byte.to_ascii_lowercase();
+        b += 1;
+    }
+    tables
+};
+
+/// Appends the collapsed form of `bytes` to `out` one byte at a time, given whether the
+/// byte before them was a space or tab, and returns whether the last of them is.
+///
+/// Every byte is looked up in [`SPACED`] and stored, and only the length of the output
+/// depends on whether it is kept, so mixed input takes no unpredictable branch.
+///
+/// # Safety
+/// - `out` must have room for `bytes.len()` bytes.
+#[inline(always)]
+unsafe fn collapse_scalar<const ASCII_LOWERCASE: bool>(bytes: &[u8], mut last_was_space: bool, out: &mut Output) -> bool {
+    let table = &SPACED[usize::from(ASCII_LOWERCASE)];
+    for &b in bytes {
+        let byte = table[usize::from(b)];
+        let blank = byte == b' ';
+        // Safety: The caller guarantees room for every byte, and each one is stored at
+        // most once
+        unsafe { out.push_if(byte, !(blank && last_was_space)) };
+        last_was_space = blank;
+    }
+    last_was_space
+}
+
+/// Collapses the bytes that the chunk loops left at the end of the input one at a time,
+/// then trims the trailing space of the output written from `start` on.
+///
+/// The kernels collapse that tail with `collapse_tail` first, so it is only left with
+/// input too short for the chunks, and the trimming.
+#[inline(always)]
+pub(super) fn collapse_rest<const ASCII_LOWERCASE: bool>(
+    bytes: &[u8],
+    last_was_space: bool,
+    start: usize,
+    result: &mut Vec<u8>,
+) {
+    let mut out = Output::new(result);
+    out.reserve(bytes.len());
+    // Safety: Room was just made for every byte
+    unsafe { collapse_scalar::<ASCII_LOWERCASE>(bytes, last_was_space, &mut out) };
+    drop(out);
+
+    // Remove trailing space if any
+    if result.len() > start && result.last() == Some(&b' ') {
+        result.pop();
+    }
+}
+
+/// Collapses the bytes of `bytes` from `*i` on one at a time, up to the first one whose
+/// address is a multiple of `align`, so the chunk loops that follow load aligned
+/// vectors.
+#[cfg(all(feature = "simd-optimized", target_arch = "aarch64", target_feature = "neon"))]
+#[inline(always)]
+pub(super) fn collapse_prologue<const ASCII_LOWERCASE: bool>(
+    bytes: &[u8],
+    align: usize,
+    i: &mut usize,
+    last_was_space: &mut bool,
+    result: &mut Vec<u8>,
+) {
+    let end = (*i + bytes[*i..].as_ptr().align_offset(align)).min(bytes.len());
+    let mut out = Output::new(result);
+    out.reserve(end - *i);
+    // Safety: Room was just made for every byte up to `end`
+    *last_was_space = unsafe { collapse_scalar::<ASCII_LOWERCASE>(&bytes[*i..end], *last_was_space, &mut out) };
+    *i = end;
+}
+
+/// A SIMD vector of bytes, or a word of them, that `collapse_chunks` runs on.
+pub(super) trait Chunk: Copy {
+    /// The number of bytes in a chunk.
+    const LANES: usize;
+    /// The number of mask bits per lane in `blank_mask`.
+    const LANE_BITS: u32 = 1;
+    /// The lanes as an array.
+    type Array: AsRef<[u8]>;
+
+    /// Loads the first `LANES` bytes of `bytes`.
+    fn load(bytes: &[u8]) -> Self;
+    /// Returns the lanes as an array.
+    fn to_array(self) -> Self::Array;
+    /// Lowercases the lanes that are ASCII uppercase letters.
+    fn to_ascii_lowercase(self) -> Self;
+    /// Returns the mask of the space and tab lanes, with the `LANE_BITS` bits from
+    /// `n * LANE_BITS` on set for lane `n`.
+    fn blank_mask(self) -> u64;
+    /// Replaces the tab lanes with spaces.
+    fn tabs_to_spaces(self) -> Self;
+}
+
+#[cfg(feature = "simd-optimized")]
+impl Chunk for wide::u8x16 {
+    const LANES: usize = 16;
+    type Array = [u8; 16];
+
+    #[inline(always)]
+    fn load(bytes: &[u8]) -> Self {
+        let array: [u8; 16] = bytes[..16].try_into().unwrap();
+        Self::from(array)
+    }
+
+    #[inline(always)]
+    fn to_array(self) -> [u8; 16] {
+        wide::u8x16::to_array(self)
+    }
+
+    #[inline(always)]
+    fn to_ascii_lowercase(self) -> Self {
+        // A lane is an uppercase letter exactly when clamping it to `A..=Z` is a no-op
+        let upper = self.cmp_eq(self.max(Self::splat(b'A')).min(Self::splat(b'Z')));
+        self | (upper & Self::splat(0x20))
+    }
+
+    #[inline(always)]
+    fn blank_mask(self) -> u64 {
+        let blanks = self.cmp_eq(Self::splat(b' ')) | self.cmp_eq(Self::splat(b'\t'));
+        blanks.move_mask() as u32 as u64
+    }
+
+    #[inline(always)]
+    fn tabs_to_spaces(self) -> Self {
+        self.cmp_eq(Self::splat(b'\t')).blend(Self::splat(b' '), self)
+    }
+}
+
+/// `N` chunks of `C` making up 64 bytes, processed as one chunk.
+///
+/// The masks of the chunks are combined into a single `u64`, so a superblock takes one
+/// pass of the checks in `collapse_chunks`, and one 64-byte copy when it has no
+/// whitespace.
+#[cfg(all(
+    feature = "simd-optimized",
+    any(all(target_arch = "x86_64", target_feature = "sse2"), all(target_arch = "wasm32", target_feature = "simd128"))
+))]
+#[derive(Clone, Copy)]
+pub(super) struct Superblock<C, const N: usize>([C; N]);
+
+#[cfg(all(
+    feature = "simd-optimized",
+    any(all(target_arch = "x86_64", target_feature = "sse2"), all(target_arch = "wasm32", target_feature = "simd128"))
+))]
+impl<C: Chunk, const N: usize> Chunk for Superblock<C, N> {
+    const LANES: usize = {
+        assert!(N * C::LANES == 64 && C::LANE_BITS == 1, "a superblock is 64 lanes of one mask bit");
+        64
+    };
+    type Array = [u8; 64];
+
+    #[inline(always)]
+    fn load(bytes: &[u8]) -> Self {
+        Self(std::array::from_fn(|n| C::load(&bytes[n * C::LANES..])))
+    }
+
+    #[inline(always)]
+    fn to_array(self) -> [u8; 64] {
+        let mut array = [0; 64];
+        for (part, chunk) in array.chunks_exact_mut(C::LANES).zip(self.0) {
+            part.copy_from_slice(chunk.to_array().as_ref());
+        }
+        array
+    }
+
+    #[inline(always)]
+    fn to_ascii_lowercase(self) -> Self {
+        Self(self.0.map(C::to_ascii_lowercase))
+    }
+
+    #[inline(always)]
+    fn blank_mask(self) -> u64 {
+        self.0.iter().rev().fold(0, |mask, chunk| mask << C::LANES | chunk.blank_mask())
+    }
+
+    #[inline(always)]
+    fn tabs_to_spaces(self) -> Self {
+        Self(self.0.map(C::tabs_to_spaces))
+    }
+}
+
+/// Collapses the whole chunks of `C::LANES` bytes of `bytes` from `*i` on into `result`,
+/// leaving `*i` at the incomplete chunk that ends the input.
+#[inline(always)]
+pub(super) fn collapse_chunks<C: Chunk, const ASCII_LOWERCASE: bool>(
+    bytes: &[u8],
+    i: &mut usize,
+    last_was_space: &mut bool,
+    result: &mut Vec<u8>,
+) {
+    let bits = C::LANE_BITS;
+    let last_lane = 1 << (C::LANES as u32 * bits - 1);
+    let all_blank = u64::MAX >> (64 - C::LANES as u32 * bits);
+    let mut out = Output::new(result);
+    while *i + C::LANES <= bytes.len() {
+        out.reserve(C::LANES);
+        let source = &bytes[*i..*i + C::LANES];
+        let mut chunk = C::load(source);
+        if ASCII_LOWERCASE {
+            chunk = chunk.to_ascii_lowercase();
+        }
+
+        let blanks = chunk.blank_mask();
+        if blanks == all_blank {
+            // A whitespace run: it becomes at most one space, and the chunks after this
+            // one that are all blanks too are skipped without writing anything
+            if !*last_was_space {
+                // Safety: Room was made for the whole chunk
+                unsafe { out.push(b' ') };
+                *last_was_space = true;
+            }
+            *i = skip_blank_chunks::<C>(bytes, *i + C::LANES);
+            continue;
+        } else if blanks == 0 {
+            // No whitespace at all: the chunk is copied as is
+            // Safety: Room was made for the whole chunk, and each branch below writes at
+            // most one byte per lane of it
+            if ASCII_LOWERCASE {
+                unsafe { out.extend(chunk.to_array().as_ref()) };
+            } else {
+                unsafe { out.extend(source) };
+            }
+            *last_was_space = false;
+        } else if blanks & ((blanks << bits) | u64::from(*last_was_space)) == 0 {
+            // Every blank is a single one after another byte, so the chunk is already
+            // collapsed once its tabs are spaces
+            // Safety: As above
+            unsafe { out.extend(chunk.tabs_to_spaces().to_array().as_ref()) };
+            *last_was_space = blanks & last_lane != 0;
+        } else {
+            let lowercased = chunk.to_array();
+            let lanes = if ASCII_LOWERCASE { lowercased.as_ref() } else { source };
+            if C::LANES <= 16 {
+                // Safety: As above
+                unsafe { collapse_lanes(lanes, blanks, bits, last_was_space, &mut out) };
+            } else {
+                // A wide chunk fails the check above far more often than its halves do,
+                // so each 16 lanes get the verbatim copy on their own if they can
+                let spaced = chunk.tabs_to_spaces().to_array();
+                let part_mask = u64::MAX >> (64 - 16 * bits);
+                for part in (0..C::LANES).step_by(16) {
+                    let part_blanks = blanks >> (part as u32 * bits) & part_mask;
+                    if part_blanks & ((part_blanks << bits) | u64::from(*last_was_space)) == 0 {
+                        // Safety: As above, the parts are disjoint lanes of the chunk
+                        unsafe { out.extend(&spaced.as_ref()[part..part + 16]) };
+                        *last_was_space = part_blanks >> (16 * bits - 1) != 0;
+                    } else {
+                        // Safety: As above
+                        unsafe { collapse_lanes(&lanes[part..part + 16], part_blanks, bits, last_was_space, &mut out) };
+                    }
+                }
+            }
+        }
+
+        *i += C::LANES;
+    }
+}
+
+/// Returns the index of the first chunk of `C::LANES` bytes from `pos` on that is not
+/// all spaces and tabs, or of the incomplete chunk that ends the input.
+///
+/// The chunks of a whitespace run are only classified, so a long run, such as the
+/// padding of a fixed-width export, is skipped at the speed of the loads.
+#[inline(always)]
+pub(super) fn skip_blank_chunks<C: Chunk>(bytes: &[u8], mut pos: usize) -> usize {
+    let all_blank = u64::MAX >> (64 - C::LANES as u32 * C::LANE_BITS);
+    while pos + C::LANES <= bytes.len() && C::load(&bytes[pos..]).blank_mask() == all_blank {
+        pos += C::LANES;
+    }
+    pos
+}
+
+/// Collapses the bytes of `bytes` from `*i` on, fewer than `C::LANES`, as one chunk
+/// padded with zeros on a stack buffer, and leaves `*i` at the end of the input.
+///
+/// The padding is no whitespace, and only the lanes of the input are written, so the
+/// tail takes the chunk classification rather than a byte-at-a-time loop.
+#[inline(always)]
+pub(super) fn collapse_tail<C: Chunk, const ASCII_LOWERCASE: bool>(
+    bytes: &[u8],
+    i: &mut usize,
+    last_was_space: &mut bool,
+    result: &mut Vec<u8>,
+) {
+    let tail = &bytes[*i..];
+    if tail.is_empty() {
+        return;
+    }
+    let mut padded = [0; 64];
+    padded[..tail.len()].copy_from_slice(tail);
+    let mut chunk = C::load(&padded);
+    if ASCII_LOWERCASE {
+        chunk = chunk.to_ascii_lowercase();
+    }
+
+    let (lanes, blanks) = (chunk.to_array(), chunk.blank_mask());
+    let mut out = Output::new(result);
+    out.reserve(tail.len());
+    // Safety: Room was just made for the lanes of the tail
+    unsafe { collapse_lanes(&lanes.as_ref()[..tail.len()], blanks, C::LANE_BITS, last_was_space, &mut out) };
+    *i = bytes.len();
+}
+
+/// Appends the collapsed form of `lanes` to `out`, given the mask of its space and
+/// tab lanes (the `bits` bits from `n * bits` on set for lane `n`).
+///
+/// Runs are found with bit tricks on the mask: every run of other bytes is copied with
+/// one copy, and a whitespace run becomes at most one space.
+///
+/// # Safety
+/// - `out` must have room for `lanes.len()` bytes.
+#[inline(always)]
+unsafe fn collapse_lanes(lanes: &[u8], blanks: u64, bits: u32, last_was_space: &mut bool, out: &mut Output) {
+    let len = lanes.len() as u32;
+    let mut pos = 0;
+    while pos < len {
+        let rest = blanks >> (pos * bits);
+        if rest & 1 == 0 {
+            // Bits past the last lane are clear, so only a mask without blanks left
+            // ends the run at the end of the chunk
+            let run = if rest == 0 { len - pos } else { rest.trailing_zeros() / bits };
+            // Safety: The caller guarantees room for every lane, and each one is written at
+            // most once
+            unsafe { out.extend(&lanes[pos as usize..(pos + run) as usize]) };
+            *last_was_space = false;
+            pos += run;
+        } else {
+            if !*last_was_space {
+                // Safety: As above, the space stands for at least one lane
+                unsafe { out.push(b' ') };
+                *last_was_space = true;
+            }
+            pos += (!rest).trailing_zeros() / bits;
+        }
+    }
+}
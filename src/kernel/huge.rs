@@ -0,0 +1,79 @@
+//! Collapsing inputs far larger than the caches without evicting the rest of the
+//! working set, through a staging buffer copied out with non-temporal stores.
+
+use std::arch::x86_64::*;
+
+use super::{collapse, estimated_len};
+use crate::scan;
+
+/// The number of input bytes collapsed into the staging buffer at a time, small enough
+/// for the block, the staging buffer and the prefetched next block to stay in L2.
+const BLOCK_LEN: usize = 16 * 1024;
+
+/// Like `collapse_bytes`, but writes the output with non-temporal stores and prefetches
+/// the input with a non-temporal hint, so neither displaces other data from the caches.
+///
+/// Each block is collapsed into a staging buffer that stays in the caches, which is
+/// then appended to the output with `MOVNTDQ`: the kernels' left-packed stores are not
+/// aligned, and non-temporal stores only pay off for whole aligned lines.
+pub(crate) fn collapse_bytes_huge(bytes: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(estimated_len(bytes));
+    let mut staging = Vec::with_capacity(BLOCK_LEN);
+    let mut start = 0;
+    while start < bytes.len() {
+        // Every block after the first starts at a byte that is no blank, so collapsing it
+        // on its own only trims the blanks that end the previous block; one space is put
+        // back for them unless nothing came before
+        let end = if bytes.len() - start <= BLOCK_LEN {
+            bytes.len()
+        } else {
+            start + BLOCK_LEN + scan::find_blank(&bytes[start + BLOCK_LEN..], false)
+        };
+        prefetch(&bytes[end..(end + BLOCK_LEN).min(bytes.len())]);
+
+        staging.clear();
+        if start > 0 && matches!(bytes[start - 1], b' ' | b'\t') && !result.is_empty() {
+            staging.push(b' ');
+        }
+        collapse::<false>(&bytes[start..end], &mut staging);
+        append_streaming(&staging, &mut result);
+        start = end;
+    }
+    // Non-temporal stores are weakly ordered, so they are fenced before the output is
+    // handed to code that may pass it to another thread
+    // Safety: SSE is part of the x86_64 baseline
+    unsafe { _mm_sfence() };
+    result
+}
+
+/// Prefetches every cache line of `bytes` with the non-temporal hint.
+#[inline]
+fn prefetch(bytes: &[u8]) {
+    for line in bytes.chunks(64) {
+        // Safety: Prefetching never faults, and the line is part of `bytes` anyway
+        unsafe { _mm_prefetch::<_MM_HINT_NTA>(line.as_ptr().cast()) };
+    }
+}
+
+/// Appends `bytes` to `result`, with non-temporal stores from the first 16-byte
+/// boundary of the output on.
+fn append_streaming(bytes: &[u8], result: &mut Vec<u8>) {
+    result.reserve(bytes.len());
+    let len = result.len();
+    // Safety: The capacity reserved above leaves room for `bytes` from `len` on
+    let out = unsafe { result.as_mut_ptr().add(len) };
+    let head = out.align_offset(16).min(bytes.len());
+
+    // Safety: Every store below is within the `bytes.len()` bytes reserved at `out`,
+    // and `out.add(i)` is 16-byte aligned in the loop
+    unsafe {
+        out.copy_from_nonoverlapping(bytes.as_ptr(), head);
+        let mut i = head;
+        while i + 16 <= bytes.len() {
+            _mm_stream_si128(out.add(i).cast(), _mm_loadu_si128(bytes.as_ptr().add(i).cast()));
+            i += 16;
+        }
+        out.add(i).copy_from_nonoverlapping(bytes.as_ptr().add(i), bytes.len() - i);
+        result.set_len(len + bytes.len());
+    }
+}
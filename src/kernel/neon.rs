@@ -0,0 +1,128 @@
+//! The NEON chunks for aarch64, with whitespace masks extracted by a narrowing shift for
+//! single registers and by pairwise additions for 64-byte blocks.
+
+use std::arch::aarch64::*;
+
+use super::chunks::Chunk;
+
+/// The shortest input that is collapsed one byte at a time up to a 16-byte boundary
+/// before the NEON chunks.
+///
+/// Many Arm cores split a load that crosses a cache line, and an unaligned 64-byte
+/// [`Block`] crosses one on every iteration; from a 16-byte boundary on, none of the
+/// loads do. The prologue costs up to 15 scalar bytes, which only longer inputs make up
+/// for. On x86_64, and on WebAssembly run there, aligning the loads measured no faster,
+/// so the other kernels skip it.
+pub(super) const ALIGNED_MIN_LEN: usize = 256;
+
+// Safety: Every intrinsic below only needs NEON, which the target enables
+
+/// Returns the lanes of `chunk` that are spaces or tabs, all ones, and the others zero.
+#[inline(always)]
+fn blanks(chunk: uint8x16_t) -> uint8x16_t {
+    unsafe { vorrq_u8(vceqq_u8(chunk, vdupq_n_u8(b' ')), vceqq_u8(chunk, vdupq_n_u8(b'\t'))) }
+}
+
+#[inline(always)]
+fn to_ascii_lowercase(chunk: uint8x16_t) -> uint8x16_t {
+    unsafe {
+        let upper = vcleq_u8(vsubq_u8(chunk, vdupq_n_u8(b'A')), vdupq_n_u8(25));
+        vorrq_u8(chunk, vandq_u8(upper, vdupq_n_u8(0x20)))
+    }
+}
+
+#[inline(always)]
+fn tabs_to_spaces(chunk: uint8x16_t) -> uint8x16_t {
+    unsafe { vbslq_u8(vceqq_u8(chunk, vdupq_n_u8(b'\t')), vdupq_n_u8(b' '), chunk) }
+}
+
+/// 16 bytes in a NEON register.
+#[derive(Clone, Copy)]
+pub(super) struct Vector(uint8x16_t);
+
+impl Chunk for Vector {
+    const LANES: usize = 16;
+    // NEON has no movemask instruction, and narrowing by 4 bits is its cheapest substitute
+    const LANE_BITS: u32 = 4;
+    type Array = [u8; 16];
+
+    #[inline(always)]
+    fn load(bytes: &[u8]) -> Self {
+        let bytes = &bytes[..16];
+        Self(unsafe { vld1q_u8(bytes.as_ptr()) })
+    }
+
+    #[inline(always)]
+    fn to_array(self) -> [u8; 16] {
+        let mut array = [0; 16];
+        unsafe { vst1q_u8(array.as_mut_ptr(), self.0) };
+        array
+    }
+
+    #[inline(always)]
+    fn to_ascii_lowercase(self) -> Self {
+        Self(to_ascii_lowercase(self.0))
+    }
+
+    #[inline(always)]
+    fn blank_mask(self) -> u64 {
+        unsafe {
+            // Shifting every 16-bit pair of lanes right by 4 and narrowing it to 8 bits
+            // keeps the high half of the first lane and the low half of the second
+            let nibbles = vshrn_n_u16::<4>(vreinterpretq_u16_u8(blanks(self.0)));
+            vget_lane_u64::<0>(vreinterpret_u64_u8(nibbles))
+        }
+    }
+
+    #[inline(always)]
+    fn tabs_to_spaces(self) -> Self {
+        Self(tabs_to_spaces(self.0))
+    }
+}
+
+/// 64 bytes in four NEON registers, processed as one superblock.
+#[derive(Clone, Copy)]
+pub(super) struct Block([uint8x16_t; 4]);
+
+impl Chunk for Block {
+    const LANES: usize = 64;
+    type Array = [u8; 64];
+
+    #[inline(always)]
+    fn load(bytes: &[u8]) -> Self {
+        let bytes = &bytes[..64];
+        Self(std::array::from_fn(|n| unsafe { vld1q_u8(bytes.as_ptr().add(16 * n)) }))
+    }
+
+    #[inline(always)]
+    fn to_array(self) -> [u8; 64] {
+        let mut array = [0; 64];
+        for (n, chunk) in self.0.into_iter().enumerate() {
+            unsafe { vst1q_u8(array.as_mut_ptr().add(16 * n), chunk) };
+        }
+        array
+    }
+
+    #[inline(always)]
+    fn to_ascii_lowercase(self) -> Self {
+        Self(self.0.map(to_ascii_lowercase))
+    }
+
+    #[inline(always)]
+    fn blank_mask(self) -> u64 {
+        const WEIGHTS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+        unsafe {
+            // Each blank lane keeps its bit of its group of 8, and three rounds of
+            // pairwise additions sum every group into one byte of the mask
+            let weights = vld1q_u8(WEIGHTS.as_ptr());
+            let [a, b, c, d] = self.0.map(|chunk| vandq_u8(blanks(chunk), weights));
+            let sums = vpaddq_u8(vpaddq_u8(a, b), vpaddq_u8(c, d));
+            vgetq_lane_u64::<0>(vreinterpretq_u64_u8(vpaddq_u8(sums, sums)))
+        }
+    }
+
+    #[inline(always)]
+    fn tabs_to_spaces(self) -> Self {
+        Self(self.0.map(tabs_to_spaces))
+    }
+}
@@ -0,0 +1,38 @@
+//! The `std::simd` chunks, for targets without a hand-written kernel.
+
+use std::simd::cmp::{SimdPartialEq, SimdPartialOrd};
+use std::simd::{Select, Simd};
+
+use super::chunks::Chunk;
+
+impl<const N: usize> Chunk for Simd<u8, N> {
+    const LANES: usize = N;
+    type Array = [u8; N];
+
+    #[inline(always)]
+    fn load(bytes: &[u8]) -> Self {
+        Simd::from_slice(bytes)
+    }
+
+    #[inline(always)]
+    fn to_array(self) -> [u8; N] {
+        Simd::to_array(self)
+    }
+
+    #[inline(always)]
+    fn to_ascii_lowercase(self) -> Self {
+        // Lanes below `A` wrap around, so a single comparison finds the letters
+        let upper = (self - Simd::splat(b'A')).simd_le(Simd::splat(b'Z' - b'A'));
+        upper.select(self | Simd::splat(0x20), self)
+    }
+
+    #[inline(always)]
+    fn blank_mask(self) -> u64 {
+        (self.simd_eq(Simd::splat(b' ')) | self.simd_eq(Simd::splat(b'\t'))).to_bitmask()
+    }
+
+    #[inline(always)]
+    fn tabs_to_spaces(self) -> Self {
+        self.simd_eq(Simd::splat(b'\t')).select(Simd::splat(b' '), self)
+    }
+}
@@ -0,0 +1,88 @@
+//! The kernel for input with sparse whitespace, which jumps from blank to blank with
+//! `memchr` and copies everything in between in bulk.
+
+/// Inputs shorter than this always take the other kernels, as sampling them costs
+/// more than it can save.
+const MIN_LEN: usize = 1024;
+/// The number of leading bytes the density of whitespace is estimated from.
+const SAMPLE_LEN: usize = 256;
+/// The most blanks in the sample for [`collapse`] to beat the SIMD kernels (but the
+/// AVX-512 one, which it never beats), that is about one blank per 256 bytes.
+#[cfg(any(
+    feature = "portable-simd",
+    all(
+        feature = "simd-optimized",
+        any(
+            all(target_arch = "x86_64", target_feature = "sse2"),
+            all(target_arch = "x86_64", target_feature = "avx2"),
+            all(target_arch = "aarch64", target_feature = "neon"),
+            all(target_arch = "wasm32", target_feature = "simd128")
+        )
+    )
+))]
+const MAX_SAMPLE_BLANKS: usize = 1;
+/// The most blanks in the sample for [`collapse`] to beat the scalar kernel, that is
+/// about one blank per 16 bytes.
+#[cfg(not(any(
+    feature = "portable-simd",
+    all(
+        feature = "simd-optimized",
+        any(
+            all(target_arch = "x86_64", target_feature = "sse2"),
+            all(target_arch = "x86_64", target_feature = "avx2"),
+            all(target_arch = "aarch64", target_feature = "neon"),
+            all(target_arch = "wasm32", target_feature = "simd128")
+        )
+    )
+)))]
+const MAX_SAMPLE_BLANKS: usize = 16;
+
+/// Returns `true` if `bytes` looks sparse enough for [`collapse`] to beat the kernel
+/// it would otherwise take, judging from the spaces and tabs in its first bytes.
+#[inline]
+pub(super) fn is_sparse(bytes: &[u8]) -> bool {
+    bytes.len() >= MIN_LEN && memchr::memchr2_iter(b' ', b'\t', &bytes[..SAMPLE_LEN]).nth(MAX_SAMPLE_BLANKS).is_none()
+}
+
+/// Collapses `bytes` into `result`, optionally lowercasing ASCII letters along the way.
+///
+/// # Performance
+/// - Finds every space and tab with `memchr2`, which skips the bytes in between far
+///   faster than classifying them chunk by chunk when blanks are rare, such as in
+///   minified data or identifiers.
+/// - Copies each run of other bytes with one `extend_from_slice`.
+/// - Visits every blank of a run, so it is slower than the chunked kernels on input
+///   with a lot of whitespace.
+pub(super) fn collapse<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    let start = result.len();
+    let mut last_was_space = true;
+    let mut pos = 0;
+
+    for blank in memchr::memchr2_iter(b' ', b'\t', bytes) {
+        if blank > pos {
+            push_plain::<ASCII_LOWERCASE>(&bytes[pos..blank], result);
+            last_was_space = false;
+        }
+        if !last_was_space {
+            result.push(b' ');
+            last_was_space = true;
+        }
+        pos = blank + 1;
+    }
+    push_plain::<ASCII_LOWERCASE>(&bytes[pos..], result);
+
+    // Trim the trailing space, which only a blank can have written
+    if result.len() > start && result.last() == Some(&b' ') {
+        result.pop();
+    }
+}
+
+/// Appends `plain`, which has no spaces or tabs, to `result`.
+#[inline(always)]
+fn push_plain<const ASCII_LOWERCASE: bool>(plain: &[u8], result: &mut Vec<u8>) {
+    let len = result.len();
+    result.extend_from_slice(plain);
+    if ASCII_LOWERCASE {
+        result[len..].make_ascii_lowercase();
+    }
+}
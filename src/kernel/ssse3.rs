@@ -0,0 +1,211 @@
+//! The SSSE3 left-pack kernel, which writes the kept bytes of every 16-byte chunk with
+//! one `PSHUFB` and two stores, without branching on the input.
+
+use std::arch::x86_64::*;
+
+use super::chunks::{skip_blank_chunks, Chunk};
+
+/// For every 8-bit keep-mask, the indices of its set bits in ascending order, padded
+/// with `0x80` (which `PSHUFB` turns into zero bytes).
+static PACK: [[u8; 8]; 256] = {
+    let mut table = [[0x80; 8]; 256];
+    let mut mask = 0;
+    while mask < 256 {
+        let mut kept = 0;
+        let mut lane = 0;
+        while lane < 8 {
+            if mask & (1 << lane) != 0 {
+                table[mask][kept] = lane as u8;
+                kept += 1;
+            }
+            lane += 1;
+        }
+        mask += 1;
+    }
+    table
+};
+
+/// Stores the lanes of `spaced` whose bit is set in the 16-bit `keep` mask to `out`,
+/// packed to its front, and returns how many there are.
+///
+/// # Safety
+/// - `out` must be valid for writes of 16 bytes, whatever the number of kept lanes.
+#[inline]
+#[target_feature(enable = "ssse3")]
+pub(super) unsafe fn left_pack(spaced: __m128i, keep: u32, out: *mut u8) -> usize {
+    let (low, high) = ((keep & 0xFF) as usize, (keep >> 8 & 0xFF) as usize);
+    // Safety: Both masks are below 256, and every row of the table is 8 bytes
+    let shuffle = unsafe {
+        _mm_unpacklo_epi64(_mm_loadl_epi64(PACK[low].as_ptr().cast()), _mm_loadl_epi64(PACK[high].as_ptr().cast()))
+    };
+    // The high half of the shuffle picks from the high 8 lanes; adding 8 leaves the
+    // `0x80` padding with its top bit set
+    let shuffle = _mm_add_epi8(shuffle, _mm_set_epi64x(0x0808_0808_0808_0808, 0));
+    let packed = _mm_shuffle_epi8(spaced, shuffle);
+
+    let kept_low = low.count_ones() as usize;
+    // Safety: The caller guarantees 16 writable bytes, and `kept_low + 8 <= 16`
+    unsafe {
+        _mm_storel_epi64(out.cast(), packed);
+        _mm_storel_epi64(out.add(kept_low).cast(), _mm_unpackhi_epi64(packed, packed));
+    }
+    kept_low + high.count_ones() as usize
+}
+
+/// Collapses the whole chunks of `C::LANES` bytes of `bytes` from `*i` on into `result`,
+/// leaving `*i` at the incomplete chunk that ends the input.
+///
+/// `C` must have one mask bit per lane and a multiple of 16 lanes.
+///
+/// # Safety
+/// - The CPU must support SSSE3, and whatever `C` needs.
+///
+/// # Performance
+/// - Builds a keep-mask per chunk (every byte but a space or tab that follows another
+///   one) and packs the kept bytes of every 16 lanes with [`left_pack`].
+/// - Chunks that keep every byte are stored whole, and the chunks of a whitespace run
+///   are skipped (see [`skip_blank_chunks`]); everything else takes the same branchless
+///   path however its whitespace is laid out.
+/// - Stores whole vectors into the spare capacity of `result`, and only grows it when
+///   less than a chunk is left, so it keeps the capacity the caller estimated.
+#[inline(always)]
+pub(super) unsafe fn collapse_chunks<C: Chunk, const ASCII_LOWERCASE: bool>(
+    bytes: &[u8],
+    i: &mut usize,
+    last_was_space: &mut bool,
+    result: &mut Vec<u8>,
+) {
+    const { assert!(C::LANE_BITS == 1 && C::LANES % 16 == 0 && C::LANES <= 64) };
+    let all_kept = u64::MAX >> (64 - C::LANES);
+    if *i + C::LANES > bytes.len() {
+        return;
+    }
+    // The byte stores may alias anything, so the loop state is kept in locals rather
+    // than behind the references
+    let (mut pos, mut last) = (*i, *last_was_space);
+    let mut len = result.len();
+    let (mut out, mut capacity) = (result.as_mut_ptr(), result.capacity());
+
+    while pos + C::LANES <= bytes.len() {
+        if capacity - len < C::LANES {
+            // Safety: The first `len` bytes are the previous output and the kept bytes
+            (out, capacity) = unsafe { grow(result, len, C::LANES) };
+        }
+
+        let mut chunk = C::load(&bytes[pos..]);
+        if ASCII_LOWERCASE {
+            chunk = chunk.to_ascii_lowercase();
+        }
+
+        let blanks = chunk.blank_mask();
+        if blanks == all_kept {
+            // A whitespace run: it becomes at most one space, and the chunks after this
+            // one that are all blanks too are skipped without storing anything
+            if !last {
+                // Safety: The capacity leaves `C::LANES` writable bytes at `len`
+                unsafe { out.add(len).write(b' ') };
+                len += 1;
+                last = true;
+            }
+            pos = skip_blank_chunks::<C>(bytes, pos + C::LANES);
+            continue;
+        }
+        let keep = !(blanks & ((blanks << 1) | u64::from(last))) & all_kept;
+        let spaced = chunk.tabs_to_spaces().to_array();
+        if keep == all_kept {
+            // Safety: The capacity leaves `C::LANES` writable bytes at `len`
+            unsafe { out.add(len).copy_from_nonoverlapping(spaced.as_ref().as_ptr(), C::LANES) };
+            len += C::LANES;
+        } else {
+            for (part, lanes) in spaced.as_ref().chunks_exact(16).enumerate() {
+                // Safety: As above, and the parts before this one wrote at most their
+                // own number of lanes
+                len += unsafe {
+                    left_pack(_mm_loadu_si128(lanes.as_ptr().cast()), (keep >> (16 * part)) as u32, out.add(len))
+                };
+            }
+        }
+        last = blanks >> (C::LANES - 1) != 0;
+
+        pos += C::LANES;
+    }
+    // Safety: The first `len` bytes are the previous output and the kept bytes
+    unsafe { result.set_len(len) };
+    (*i, *last_was_space) = (pos, last);
+}
+
+/// Sets the length of `result` to the `len` bytes a kernel has written to its spare
+/// capacity, and makes room for `room` more, returning its new pointer and capacity.
+///
+/// # Safety
+/// - The first `len` bytes of `result` must be initialized.
+#[cold]
+#[inline(never)]
+pub(super) unsafe fn grow(result: &mut Vec<u8>, len: usize, room: usize) -> (*mut u8, usize) {
+    // Safety: The caller guarantees the first `len` bytes are initialized
+    unsafe { result.set_len(len) };
+    result.reserve(room);
+    (result.as_mut_ptr(), result.capacity())
+}
+
+/// Collapses the bytes of `bytes` from `*i` on, fewer than 16, as one chunk padded with
+/// zeros on a stack buffer, and leaves `*i` at the end of the input.
+///
+/// # Safety
+/// - The CPU must support SSSE3.
+///
+/// # Performance
+/// - Left-packs the tail with the same shuffle as the whole chunks, keeping only its
+///   own lanes, so it takes no byte-at-a-time loop either.
+#[inline(always)]
+pub(super) unsafe fn collapse_tail<const ASCII_LOWERCASE: bool>(
+    bytes: &[u8],
+    i: &mut usize,
+    last_was_space: &mut bool,
+    result: &mut Vec<u8>,
+) {
+    let tail = &bytes[*i..];
+    if tail.is_empty() {
+        return;
+    }
+    let mut padded = [0; 16];
+    padded[..tail.len()].copy_from_slice(tail);
+    let mut chunk = wide::u8x16::from(padded);
+    if ASCII_LOWERCASE {
+        chunk = chunk.to_ascii_lowercase();
+    }
+
+    let blanks = chunk.blank_mask();
+    let keep = !(blanks & ((blanks << 1) | u64::from(*last_was_space))) & ((1 << tail.len()) - 1);
+    let spaced = chunk.tabs_to_spaces().to_array();
+    // The output has no room for a whole vector past the input, so the tail is packed
+    // on the stack
+    let mut packed = [0; 16];
+    // Safety: `packed` has room for 16 bytes
+    let kept = unsafe { left_pack(_mm_loadu_si128(spaced.as_ptr().cast()), keep as u32, packed.as_mut_ptr()) };
+    result.extend_from_slice(&packed[..kept]);
+    *last_was_space = blanks >> (tail.len() - 1) & 1 != 0;
+    *i = bytes.len();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::arch::x86_64::*;
+
+    use super::left_pack;
+
+    #[test]
+    fn packs_every_keep_mask() {
+        if !is_x86_feature_detected!("ssse3") {
+            return;
+        }
+        let lanes: [u8; 16] = std::array::from_fn(|lane| b'a' + lane as u8);
+        for keep in 0..=u16::MAX as u32 {
+            let mut out = [0; 16];
+            // Safety: SSSE3 is supported, and `out` has room for 16 bytes
+            let kept = unsafe { left_pack(_mm_loadu_si128(lanes.as_ptr().cast()), keep, out.as_mut_ptr()) };
+            let expected: Vec<u8> = (0..16).filter(|lane| keep >> lane & 1 == 1).map(|lane| lanes[lane]).collect();
+            assert_eq!(&out[..kept], expected, "{keep:#x}");
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! The SWAR chunk for targets without SIMD, which classifies the 8 bytes of a `u64`
+//! at once with carry-free integer arithmetic.
+
+use super::chunks::Chunk;
+
+/// `byte` in every lane of a word.
+const fn splat(byte: u8) -> u64 {
+    u64::from_ne_bytes([byte; 8])
+}
+
+/// Returns the lanes of `word` equal to `byte`, with the high bit set and the others
+/// clear.
+///
+/// Adding `0x7F` to the low 7 bits of a lane sets its high bit unless they are all
+/// zero, and cannot carry into the next lane, so unlike the classic `haszero` trick
+/// no lane is flagged because of the one below it.
+#[inline(always)]
+fn lanes_equal(word: u64, byte: u8) -> u64 {
+    let x = word ^ splat(byte);
+    !(((x & splat(0x7F)) + splat(0x7F)) | x) & splat(0x80)
+}
+
+/// Widens the high bit of every lane of `high_bits` to the whole lane.
+#[inline(always)]
+fn widen(high_bits: u64) -> u64 {
+    (high_bits >> 7).wrapping_mul(0xFF)
+}
+
+/// 8 bytes in a `u64`, lane `n` in its `n`-th byte from the least significant one.
+#[derive(Clone, Copy)]
+pub(super) struct Word(u64);
+
+impl Chunk for Word {
+    const LANES: usize = 8;
+    // Widening the high bits to whole lanes takes one multiplication, while packing
+    // them into 8 bits would take a multiplication and a shift
+    const LANE_BITS: u32 = 8;
+    type Array = [u8; 8];
+
+    #[inline(always)]
+    fn load(bytes: &[u8]) -> Self {
+        Self(u64::from_le_bytes(bytes[..8].try_into().unwrap()))
+    }
+
+    #[inline(always)]
+    fn to_array(self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+
+    #[inline(always)]
+    fn to_ascii_lowercase(self) -> Self {
+        // The low 7 bits of a lane reach the high bit past `Z` and from `A` on, without
+        // carrying into the next lane; only ASCII lanes between the two are uppercase
+        let low = self.0 & splat(0x7F);
+        let past_z = low + splat(0x7F - b'Z');
+        let from_a = low + splat(0x80 - b'A');
+        let upper = !self.0 & (from_a ^ past_z) & splat(0x80);
+        Self(self.0 | upper >> 2)
+    }
+
+    #[inline(always)]
+    fn blank_mask(self) -> u64 {
+        widen(lanes_equal(self.0, b' ') | lanes_equal(self.0, b'\t'))
+    }
+
+    #[inline(always)]
+    fn tabs_to_spaces(self) -> Self {
+        Self(self.0 ^ (widen(lanes_equal(self.0, b'\t')) & splat(b'\t' ^ b' ')))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::chunks::Chunk;
+    use super::Word;
+
+    #[test]
+    fn classifies_every_byte_in_every_lane() {
+        for byte in 0..=u8::MAX {
+            for lane in 0..8 {
+                // The other lanes are blanks, so a flag spilling from them would show
+                let mut lanes = *b" \t \t \t \t";
+                lanes[lane] = byte;
+                let word = Word::load(&lanes);
+
+                let blank = byte == b' ' || byte == b'\t';
+                assert_eq!(word.blank_mask() >> (8 * lane) & 0xFF, if blank { 0xFF } else { 0 }, "{byte:#x}");
+                assert_eq!(word.to_ascii_lowercase().to_array()[lane], byte.to_ascii_lowercase(), "{byte:#x}");
+                let spaced = if byte == b'\t' { b' ' } else { byte };
+                assert_eq!(word.tabs_to_spaces().to_array()[lane], spaced, "{byte:#x}");
+            }
+        }
+    }
+}
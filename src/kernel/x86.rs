@@ -0,0 +1,233 @@
+//! Runtime selection of the x86_64 kernels, and the AVX2 chunk they are built from.
+//!
+//! SSE2 is part of the x86_64 baseline, so the SSE2 kernel always works; the AVX2 and,
+//! with the `avx512` feature, AVX-512 ones are compiled with `#[target_feature]` and
+//! only called once the CPU is known to support them. Binaries built for the baseline
+//! target thus still run the widest kernel available, without `-C target-cpu=native`.
+
+use std::arch::x86_64::*;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[cfg(feature = "avx512")]
+use super::avx512;
+#[cfg(feature = "memchr")]
+use super::sparse;
+use super::ssse3;
+use super::chunks::{collapse_chunks, collapse_rest, collapse_tail, Chunk, Superblock};
+
+/// The x86_64 kernels, from the narrowest to the widest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub(crate) enum Kernel {
+    /// 64 bytes, then 16 bytes at a time with SSE2.
+    Sse2 = 1,
+    /// 64 bytes, then 16 bytes at a time, left-packed with the `PSHUFB` of SSSE3 and
+    /// counted with `POPCNT`.
+    Ssse3 = 2,
+    /// 64 bytes, then 32 bytes at a time with AVX2, left-packed like SSSE3.
+    Avx2 = 3,
+    /// 64 bytes at a time with the `VPCOMPRESSB` of AVX-512 VBMI2, counted with `POPCNT`.
+    #[cfg(feature = "avx512")]
+    Avx512 = 4,
+}
+
+impl Kernel {
+    /// Returns the widest kernel the CPU supports, detecting it on the first call.
+    pub(super) fn best() -> Kernel {
+        // 0 until detected; the detection is idempotent, so racing threads are fine
+        static BEST: AtomicU8 = AtomicU8::new(0);
+        match BEST.load(Ordering::Relaxed) {
+            1 => Kernel::Sse2,
+            2 => Kernel::Ssse3,
+            3 => Kernel::Avx2,
+            #[cfg(feature = "avx512")]
+            4 => Kernel::Avx512,
+            _ => {
+                let best = Kernel::detect();
+                BEST.store(best as u8, Ordering::Relaxed);
+                best
+            }
+        }
+    }
+
+    fn detect() -> Kernel {
+        #[cfg(feature = "avx512")]
+        if is_x86_feature_detected!("avx512bw")
+            && is_x86_feature_detected!("avx512vbmi2")
+            && is_x86_feature_detected!("popcnt")
+        {
+            return Kernel::Avx512;
+        }
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("popcnt") {
+            Kernel::Avx2
+        } else if is_x86_feature_detected!("ssse3") && is_x86_feature_detected!("popcnt") {
+            Kernel::Ssse3
+        } else {
+            Kernel::Sse2
+        }
+    }
+
+    /// Returns `true` if the CPU can run this kernel.
+    pub(super) fn is_supported(self) -> bool {
+        self <= Kernel::best()
+    }
+}
+
+/// Collapses `bytes` into `result` with the widest kernel the CPU supports, or with
+/// `sparse::collapse` for input with hardly any whitespace unless that is AVX-512.
+#[inline(always)]
+pub(super) fn collapse<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    let kernel = Kernel::best();
+    #[cfg(feature = "memchr")]
+    if kernel <= Kernel::Avx2 && sparse::is_sparse(bytes) {
+        return sparse::collapse::<ASCII_LOWERCASE>(bytes, result);
+    }
+    // Safety: `Kernel::best` only returns kernels the CPU supports
+    unsafe { collapse_with::<ASCII_LOWERCASE>(kernel, bytes, result) }
+}
+
+/// Collapses `bytes` into `result` with `kernel`.
+///
+/// # Safety
+/// - The CPU must support `kernel`.
+#[inline(always)]
+pub(super) unsafe fn collapse_with<const ASCII_LOWERCASE: bool>(kernel: Kernel, bytes: &[u8], result: &mut Vec<u8>) {
+    match kernel {
+        Kernel::Sse2 => collapse_sse2::<ASCII_LOWERCASE>(bytes, result),
+        Kernel::Ssse3 => collapse_ssse3::<ASCII_LOWERCASE>(bytes, result),
+        Kernel::Avx2 => collapse_avx2::<ASCII_LOWERCASE>(bytes, result),
+        #[cfg(feature = "avx512")]
+        Kernel::Avx512 => collapse_avx512::<ASCII_LOWERCASE>(bytes, result),
+    }
+}
+
+fn collapse_sse2<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    let start = result.len();
+    let mut i = 0;
+    let mut last_was_space = true;
+    collapse_chunks::<Superblock<wide::u8x16, 4>, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_chunks::<wide::u8x16, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_tail::<wide::u8x16, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_rest::<ASCII_LOWERCASE>(&bytes[i..], last_was_space, start, result);
+}
+
+#[target_feature(enable = "ssse3,popcnt")]
+unsafe fn collapse_ssse3<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    let start = result.len();
+    let mut i = 0;
+    let mut last_was_space = true;
+    // A superblock of four 16-byte chunks takes one keep-mask and one check for being
+    // kept whole, which measured faster than looping over the chunks one by one
+    ssse3::collapse_chunks::<Superblock<wide::u8x16, 4>, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    ssse3::collapse_chunks::<wide::u8x16, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    ssse3::collapse_tail::<ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_rest::<ASCII_LOWERCASE>(&bytes[i..], last_was_space, start, result);
+}
+
+#[target_feature(enable = "avx2,popcnt")]
+unsafe fn collapse_avx2<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    let start = result.len();
+    let mut i = 0;
+    let mut last_was_space = true;
+    // AVX2 registers hold 32 bytes, and a superblock of two of them takes 64 bytes per
+    // iteration with one keep-mask; single registers and 16-byte chunks then handle
+    // what is left of the input
+    ssse3::collapse_chunks::<Superblock<Avx2, 2>, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    ssse3::collapse_chunks::<Avx2, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    ssse3::collapse_chunks::<wide::u8x16, ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    ssse3::collapse_tail::<ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_rest::<ASCII_LOWERCASE>(&bytes[i..], last_was_space, start, result);
+}
+
+#[cfg(feature = "avx512")]
+#[target_feature(enable = "avx2,popcnt,avx512f,avx512bw,avx512vbmi2")]
+unsafe fn collapse_avx512<const ASCII_LOWERCASE: bool>(bytes: &[u8], result: &mut Vec<u8>) {
+    let start = result.len();
+    let mut i = 0;
+    let mut last_was_space = true;
+    // 64-byte chunks are compressed branchlessly, down to the masked one that ends the
+    // input
+    avx512::collapse_chunks::<ASCII_LOWERCASE>(bytes, &mut i, &mut last_was_space, result);
+    collapse_rest::<ASCII_LOWERCASE>(&bytes[i..], last_was_space, start, result);
+}
+
+/// Returns `true` if `bytes` has a tab or two adjacent spaces or tabs, like
+/// `kernel::has_collapsible_blanks`, testing 64 bytes at a time with AVX2.
+///
+/// # Safety
+/// - The CPU must support AVX2.
+#[target_feature(enable = "avx2")]
+pub(super) unsafe fn has_collapsible_blanks_avx2(bytes: &[u8]) -> bool {
+    let space = _mm256_set1_epi8(b' ' as i8);
+    let tab = _mm256_set1_epi8(b'\t' as i8);
+    // Safety: The loop below only loads from `at + 32 <= bytes.len()`
+    let load = |at: usize| unsafe { _mm256_loadu_si256(bytes.as_ptr().add(at).cast()) };
+
+    let mut i = 0;
+    // As in the generic scan, each chunk is compared with the one starting a byte later
+    while i + 65 <= bytes.len() {
+        let mut found = _mm256_setzero_si256();
+        for part in [i, i + 32] {
+            let (chunk, next) = (load(part), load(part + 1));
+            let spaces = _mm256_and_si256(_mm256_cmpeq_epi8(chunk, space), _mm256_cmpeq_epi8(next, space));
+            found = _mm256_or_si256(found, _mm256_or_si256(spaces, _mm256_cmpeq_epi8(chunk, tab)));
+        }
+        if _mm256_testz_si256(found, found) == 0 {
+            return true;
+        }
+        i += 64;
+    }
+
+    super::scalar_has_collapsible_blanks(&bytes[i..], false)
+}
+
+/// 32 bytes in an AVX2 register.
+#[derive(Clone, Copy)]
+struct Avx2(__m256i);
+
+// Safety: The intrinsics below need AVX2, and the chunk is only used by the kernels
+// that enable it, once `Kernel::best` has found the CPU supports it
+impl Chunk for Avx2 {
+    const LANES: usize = 32;
+    type Array = [u8; 32];
+
+    #[inline(always)]
+    fn load(bytes: &[u8]) -> Self {
+        let bytes = &bytes[..32];
+        Self(unsafe { _mm256_loadu_si256(bytes.as_ptr().cast()) })
+    }
+
+    #[inline(always)]
+    fn to_array(self) -> [u8; 32] {
+        let mut array = [0; 32];
+        unsafe { _mm256_storeu_si256(array.as_mut_ptr().cast(), self.0) };
+        array
+    }
+
+    #[inline(always)]
+    fn to_ascii_lowercase(self) -> Self {
+        unsafe {
+            // A lane is an uppercase letter exactly when clamping it to `A..=Z` is a no-op
+            let clamped = _mm256_min_epu8(_mm256_max_epu8(self.0, _mm256_set1_epi8(b'A' as i8)), _mm256_set1_epi8(b'Z' as i8));
+            let upper = _mm256_cmpeq_epi8(self.0, clamped);
+            Self(_mm256_or_si256(self.0, _mm256_and_si256(upper, _mm256_set1_epi8(0x20))))
+        }
+    }
+
+    #[inline(always)]
+    fn blank_mask(self) -> u64 {
+        unsafe {
+            let spaces = _mm256_cmpeq_epi8(self.0, _mm256_set1_epi8(b' ' as i8));
+            let tabs = _mm256_cmpeq_epi8(self.0, _mm256_set1_epi8(b'\t' as i8));
+            _mm256_movemask_epi8(_mm256_or_si256(spaces, tabs)) as u32 as u64
+        }
+    }
+
+    #[inline(always)]
+    fn tabs_to_spaces(self) -> Self {
+        unsafe {
+            let tabs = _mm256_cmpeq_epi8(self.0, _mm256_set1_epi8(b'\t' as i8));
+            Self(_mm256_blendv_epi8(self.0, _mm256_set1_epi8(b' ' as i8), tabs))
+        }
+    }
+}
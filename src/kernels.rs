@@ -0,0 +1,150 @@
+//! The per-ISA kernels behind [`collapse_whitespace`](crate::collapse_whitespace), for
+//! callers that do their own CPU dispatch.
+//!
+//! Each function returns exactly what `collapse_whitespace` returns, with one kernel and
+//! no detection. The ones whose instructions are not part of the target's baseline are
+//! `unsafe`, and compiled with `#[target_feature]` so they inline into callers that
+//! enable the same features.
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+use crate::kernel::{self, Kernel};
+
+/// Collapses consecutive spaces and tabs like [`collapse_whitespace`](crate::collapse_whitespace),
+/// with the SSE2 kernel.
+///
+/// SSE2 is part of the x86_64 baseline, so unlike the wider kernels this is safe to call
+/// on any x86_64 CPU.
+///
+/// # Example
+/// ```
+/// # #[cfg(target_arch = "x86_64")] {
+/// use fast_whitespace_collapse::collapse_whitespace_sse2;
+/// assert_eq!(collapse_whitespace_sse2("  some \t text  "), "some text");
+/// # }
+/// ```
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+pub fn collapse_whitespace_sse2(input: &str) -> String {
+    // Safety: SSE2 is part of the x86_64 baseline, and the kernel only ever removes
+    // spaces/tabs or writes a space
+    unsafe { String::from_utf8_unchecked(kernel::collapse_bytes_with(Kernel::Sse2, input.as_bytes())) }
+}
+
+/// Collapses consecutive spaces and tabs like [`collapse_whitespace`](crate::collapse_whitespace),
+/// with the SSSE3 kernel, which left-packs the kept bytes with `PSHUFB`.
+///
+/// # Safety
+/// - The CPU must support SSSE3 and `POPCNT`.
+///
+/// # Example
+/// ```
+/// # #[cfg(target_arch = "x86_64")] {
+/// use fast_whitespace_collapse::collapse_whitespace_ssse3;
+/// if is_x86_feature_detected!("ssse3") && is_x86_feature_detected!("popcnt") {
+///     // Safety: The features were just detected
+///     assert_eq!(unsafe { collapse_whitespace_ssse3("  some \t text  ") }, "some text");
+/// }
+/// # }
+/// ```
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+#[target_feature(enable = "ssse3,popcnt")]
+pub unsafe fn collapse_whitespace_ssse3(input: &str) -> String {
+    // Safety: The caller guarantees the CPU supports the kernel, and it only ever removes
+    // spaces/tabs or writes a space
+    unsafe { String::from_utf8_unchecked(kernel::collapse_bytes_with(Kernel::Ssse3, input.as_bytes())) }
+}
+
+/// Collapses consecutive spaces and tabs like [`collapse_whitespace`](crate::collapse_whitespace),
+/// with the AVX2 kernel.
+///
+/// # Safety
+/// - The CPU must support AVX2 and `POPCNT`.
+///
+/// # Example
+/// ```
+/// # #[cfg(target_arch = "x86_64")] {
+/// use fast_whitespace_collapse::collapse_whitespace_avx2;
+/// if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("popcnt") {
+///     // Safety: The features were just detected
+///     assert_eq!(unsafe { collapse_whitespace_avx2("  some \t text  ") }, "some text");
+/// }
+/// # }
+/// ```
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+#[target_feature(enable = "avx2,popcnt")]
+pub unsafe fn collapse_whitespace_avx2(input: &str) -> String {
+    // Safety: As in `collapse_whitespace_ssse3`
+    unsafe { String::from_utf8_unchecked(kernel::collapse_bytes_with(Kernel::Avx2, input.as_bytes())) }
+}
+
+/// Collapses consecutive spaces and tabs like [`collapse_whitespace`](crate::collapse_whitespace),
+/// with the AVX-512 kernel, which packs the kept bytes with `VPCOMPRESSB`.
+///
+/// # Safety
+/// - The CPU must support AVX-512 F, BW and VBMI2, AVX2 and `POPCNT`.
+///
+/// # Example
+/// ```
+/// # #[cfg(target_arch = "x86_64")] {
+/// use fast_whitespace_collapse::collapse_whitespace_avx512;
+/// if is_x86_feature_detected!("avx512bw")
+///     && is_x86_feature_detected!("avx512vbmi2")
+///     && is_x86_feature_detected!("popcnt")
+/// {
+///     // Safety: The features were just detected, and AVX-512 BW implies F and AVX2
+///     assert_eq!(unsafe { collapse_whitespace_avx512("  some \t text  ") }, "some text");
+/// }
+/// # }
+/// ```
+#[cfg(all(feature = "avx512", target_arch = "x86_64", target_feature = "sse2"))]
+#[target_feature(enable = "avx2,popcnt,avx512f,avx512bw,avx512vbmi2")]
+pub unsafe fn collapse_whitespace_avx512(input: &str) -> String {
+    // Safety: As in `collapse_whitespace_ssse3`
+    unsafe { String::from_utf8_unchecked(kernel::collapse_bytes_with(Kernel::Avx512, input.as_bytes())) }
+}
+
+/// Collapses consecutive spaces and tabs like [`collapse_whitespace`](crate::collapse_whitespace),
+/// with the NEON kernel.
+///
+/// The kernel is only compiled for targets with NEON, which covers every usual aarch64
+/// target, so this is safe to call wherever it exists. `collapse_whitespace` detects
+/// nothing there, and this is the same function under the name of its kernel.
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+pub fn collapse_whitespace_neon(input: &str) -> String {
+    crate::collapse_whitespace(input)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+    #[test]
+    fn every_supported_kernel_matches_collapse_whitespace() {
+        use super::*;
+        let input = "  The   quick\t\tbrown fox  ".repeat(20);
+        let expected = crate::collapse_whitespace(&input);
+
+        assert_eq!(collapse_whitespace_sse2(&input), expected);
+        if is_x86_feature_detected!("ssse3") && is_x86_feature_detected!("popcnt") {
+            // Safety: The features were just detected
+            assert_eq!(unsafe { collapse_whitespace_ssse3(&input) }, expected);
+        }
+        if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("popcnt") {
+            // Safety: As above
+            assert_eq!(unsafe { collapse_whitespace_avx2(&input) }, expected);
+        }
+        #[cfg(feature = "avx512")]
+        if is_x86_feature_detected!("avx512bw")
+            && is_x86_feature_detected!("avx512vbmi2")
+            && is_x86_feature_detected!("popcnt")
+        {
+            // Safety: As above
+            assert_eq!(unsafe { collapse_whitespace_avx512(&input) }, expected);
+        }
+    }
+
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+    #[test]
+    fn neon_kernel_matches_collapse_whitespace() {
+        let input = "  The   quick\t\tbrown fox  ".repeat(20);
+        assert_eq!(super::collapse_whitespace_neon(&input), crate::collapse_whitespace(&input));
+    }
+}
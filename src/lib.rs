@@ -1,3 +1,160 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "portable-simd", feature(portable_simd))]
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+// Lets the tests name the crate like the code `#[derive(CollapseWhitespace)]` expands to
+#[cfg(all(test, feature = "derive"))]
+extern crate self as fast_whitespace_collapse;
+
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "std")]
+mod backend;
+#[cfg(feature = "std")]
+mod batch;
+#[cfg(feature = "bstr")]
+mod byte_str;
+#[cfg(feature = "std")]
+mod chars;
+#[cfg(feature = "clap")]
+mod clap_args;
+#[cfg(feature = "std")]
+mod collapsed_string;
+#[cfg(feature = "std")]
+mod collapser;
+#[cfg(feature = "csv")]
+mod csv_records;
+#[cfg(feature = "std")]
+mod display;
+#[cfg(feature = "std")]
+mod edit;
+#[cfg(feature = "encoding")]
+mod encoding;
+#[cfg(feature = "std")]
+mod fields;
+#[cfg(feature = "std")]
+mod file;
+#[cfg(feature = "futures")]
+mod frames;
+#[cfg(feature = "std")]
+mod grapheme;
+#[cfg(feature = "heapless")]
+mod heapless_string;
+#[cfg(feature = "std")]
+mod io;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "std")]
+mod kernel;
+#[cfg(feature = "kernels")]
+mod kernels;
+#[cfg(feature = "std")]
+mod lines;
+#[cfg(feature = "log")]
+mod log_records;
+#[cfg(feature = "unicode-normalization")]
+mod normalization;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "std")]
+mod pieces;
+#[cfg(feature = "ropey")]
+mod rope;
+mod scan;
+#[cfg(feature = "unicode-segmentation")]
+mod segmentation;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "tendril")]
+mod str_tendril;
+#[cfg(feature = "std")]
+mod threads;
+#[cfg(feature = "tracing")]
+mod tracing_fields;
+#[cfg(feature = "std")]
+mod unicode;
+#[cfg(feature = "std")]
+mod utf16;
+mod words;
+
+#[cfg(feature = "arrow")]
+pub use arrow::collapse_whitespace_array;
+#[cfg(feature = "tokio")]
+pub use async_io::{AsyncCollapseReader, AsyncCollapseWriter};
+#[cfg(feature = "std")]
+pub use backend::{collapse_whitespace_with_backend, Backend};
+#[cfg(feature = "std")]
+pub use batch::{collapse_all_in_place, collapse_many, collapse_many_contiguous, collapse_many_cow, CollapsedBatch};
+#[cfg(feature = "bstr")]
+pub use byte_str::ByteSliceCollapseWhitespaceExt;
+#[cfg(feature = "std")]
+pub use chars::{CollapseChars, CollapseWhitespaceExt};
+#[cfg(feature = "clap")]
+pub use clap_args::{CollapseValueParser, CollapsedStringValueParser};
+#[cfg(feature = "std")]
+pub use collapsed_string::CollapsedString;
+#[cfg(feature = "std")]
+pub use collapser::{Collapser, LineBreaks, LineSeparators, TrailingNewline};
+#[cfg(feature = "csv")]
+pub use csv_records::{collapse_record, CollapseCsvReader, CollapsedCsvRecords};
+#[cfg(feature = "std")]
+pub use display::Collapsed;
+#[cfg(feature = "std")]
+pub use edit::{recollapse_edit, CollapseIndex, CollapsedDocument};
+#[cfg(feature = "encoding")]
+pub use encoding::collapse_whitespace_encoded;
+#[cfg(feature = "derive")]
+pub use fast_whitespace_collapse_derive::CollapseWhitespace;
+#[cfg(feature = "std")]
+pub use fields::CollapseWhitespace;
+#[cfg(feature = "mmap")]
+pub use file::collapse_file;
+#[cfg(feature = "std")]
+pub use file::collapse_file_to;
+#[cfg(feature = "futures")]
+pub use frames::{CollapseSink, CollapseStream};
+#[cfg(feature = "heapless")]
+pub use heapless_string::collapse_whitespace_heapless;
+#[cfg(all(feature = "kernels", target_arch = "x86_64", target_feature = "sse2"))]
+pub use kernels::{collapse_whitespace_avx2, collapse_whitespace_sse2, collapse_whitespace_ssse3};
+#[cfg(all(feature = "kernels", feature = "avx512", target_arch = "x86_64", target_feature = "sse2"))]
+pub use kernels::collapse_whitespace_avx512;
+#[cfg(all(feature = "kernels", target_arch = "aarch64", target_feature = "neon"))]
+pub use kernels::collapse_whitespace_neon;
+#[cfg(feature = "std")]
+pub use lines::{collapse_and_wrap, collapse_lines, collapse_lines_iter, CollapseLines, LinesCollapsed, LinesCollapsedExt};
+#[cfg(feature = "log")]
+pub use log_records::CollapseLogger;
+#[cfg(feature = "std")]
+pub use io::{collapse_stream, collapsed_lines, CollapseReader, CollapseWriter, CollapsedLines, Stats};
+#[cfg(feature = "json")]
+pub use json::{collapse_json_strings, JsonCollapser};
+#[cfg(feature = "unicode-normalization")]
+pub use normalization::collapse_whitespace_nfc;
+#[cfg(feature = "rayon")]
+pub use parallel::{collapse_all_in_place_parallel, collapse_many_parallel, collapse_whitespace_parallel, ParallelCollapseWhitespaceExt, ParallelCollapsed};
+#[cfg(feature = "ropey")]
+pub use rope::{collapse_whitespace_rope, collapse_whitespace_rope_in_place};
+#[cfg(feature = "unicode-segmentation")]
+pub use segmentation::{unicode_words_collapsed, UnicodeWordsCollapsed};
+#[cfg(feature = "std")]
+pub use stream::StreamCollapser;
+#[cfg(feature = "tendril")]
+pub use str_tendril::{collapse_whitespace_tendril, collapse_whitespace_tendril_in_place};
+#[cfg(feature = "std")]
+pub use threads::{collapse_all_in_place_threaded, collapse_many_threaded, collapse_whitespace_threaded};
+#[cfg(feature = "tracing")]
+pub use tracing_fields::CollapseFields;
+#[cfg(feature = "std")]
+pub use utf16::collapse_whitespace_utf16;
+pub use words::{kept_ranges, split_collapsed_words, split_segments, KeptRanges, Segment, Segments, SplitCollapsedWords};
+
 /// Collapses consecutive spaces and tabs into a single space in the input string.
 ///
 /// This function efficiently processes input using SIMD (`u8x16`) for performance.
@@ -22,147 +179,243 @@
 /// - Uses SIMD (`u8x16`) to process 16 bytes at a time.
 /// - Falls back to scalar processing for remaining bytes.
 /// - Ensures valid UTF-8 output by keeping only original characters.
-#[cfg(any(
-    all(target_arch = "x86_64", target_feature = "sse2"),   // SSE2 on x86
-    all(target_arch = "x86_64", target_feature = "avx2"),   // AVX2 on x86
-    all(target_arch = "aarch64", target_feature = "neon")   // NEON on ARM (For example Apple M1/M2)
-))]
-#[cfg(feature = "simd-optimized")]
+/// - On targets without AVX2, SSE2 or NEON, or with the `simd-optimized` feature
+///   disabled, a scalar implementation processes the input byte by byte instead.
+#[cfg(feature = "std")]
 pub fn collapse_whitespace(input: &str) -> String {
-    use wide::u8x16;
-    let bytes = input.as_bytes();
-    let len = bytes.len();
-    let mut result = Vec::with_capacity(len);
-
-    let space = u8x16::splat(b' ');
-    let tab   = u8x16::splat(b'\t');
-
-    let mut i = 0;
-    let mut last_was_space = true;
-
-    while i + 16 <= len {
-        // Load a 16-byte chunk
-        let arr: [u8; 16] = bytes[i..i+16].try_into().unwrap();
-        let chunk = u8x16::from(arr);
-
-        // Compare each lane to space or tab
-        let cmp_space = chunk.cmp_eq(space);
-        let cmp_tab   = chunk.cmp_eq(tab);
-        let cmp_any   = cmp_space | cmp_tab;
-
-        // Convert to arrays
-        let chunk_arr = chunk.to_array();
-        let mask_arr  = cmp_any.to_array();
-
-        // mask_arr lane is 0xFF if that lane is space or tab, else 0x00
-        for (&byte, &mask_byte) in chunk_arr.iter().zip(mask_arr.iter()) {
-            let is_whitespace = mask_byte == 0xFF;
-            if is_whitespace {
-                if !last_was_space {
-                    result.push(b' ');
-                    last_was_space = true;
-                }
-            } else {
-                result.push(byte);
-                last_was_space = false;
-            }
-        }
-
-        i += 16;
-    }
+    // Safety: The kernel only ever removes spaces/tabs or writes a space
+    unsafe { String::from_utf8_unchecked(kernel::collapse_bytes(input.as_bytes())) }
+}
 
-    // Handle leftover bytes (scalar pass)
-    while i < len {
-        let b = bytes[i];
-        if b == b' ' || b == b'\t' {
-            if !last_was_space {
-                result.push(b' ');
-                last_was_space = true;
-            }
-        } else {
-            result.push(b);
-            last_was_space = false;
-        }
-        i += 1;
+/// Collapses consecutive spaces and tabs like [`collapse_whitespace`], borrowing the
+/// input when it is already collapsed.
+///
+/// Most real-world text needs no change, and for it this returns `Cow::Borrowed`
+/// without allocating or copying anything.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+///
+/// # Returns
+/// - `Cow::Borrowed(input)` if collapsing would not change it, or the collapsed
+///   `String` otherwise.
+///
+/// # Example
+/// ```
+/// use std::borrow::Cow;
+/// use fast_whitespace_collapse::collapse_whitespace_cow;
+/// assert!(matches!(collapse_whitespace_cow("Already clean text."), Cow::Borrowed(_)));
+/// assert_eq!(collapse_whitespace_cow("  Not \t quite  "), "Not quite");
+/// ```
+///
+/// # Performance
+/// - Pre-scans the input with SIMD (`u8x16`, or AVX2 where the CPU supports it), 64
+///   bytes at a time, for a leading or trailing blank, a tab or two adjacent blanks, and
+///   stops at the first one, so input that needs collapsing costs little more than with
+///   `collapse_whitespace`.
+/// - `collapse_whitespace` itself does not pre-scan: its SIMD kernels collapse clean text
+///   about as fast as the scan and a copy, and the AVX2 and AVX-512 ones faster.
+#[cfg(feature = "std")]
+pub fn collapse_whitespace_cow(input: &str) -> Cow<'_, str> {
+    if kernel::is_collapsed(input.as_bytes()) {
+        Cow::Borrowed(input)
+    } else {
+        Cow::Owned(collapse_whitespace(input))
     }
+}
 
-    // Remove trailing space if any
-    if result.last() == Some(&b' ') {
-        result.pop();
-    }
+/// Collapses consecutive spaces and tabs like [`collapse_whitespace`], without letting
+/// a huge input and its output evict the rest of the working set from the caches.
+///
+/// Meant for batch jobs collapsing inputs of hundreds of megabytes next to other hot
+/// data, such as lookup tables; otherwise `collapse_whitespace` is faster.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+///
+/// # Returns
+/// - The same `String` as `collapse_whitespace(input)`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_whitespace_huge;
+/// let export = "id   name\t\tvalue\n".repeat(100_000);
+/// assert_eq!(collapse_whitespace_huge(&export), "id name value\n".repeat(100_000));
+/// ```
+///
+/// # Performance
+/// - On x86_64, collapses 16 KiB of input at a time into a buffer that stays in the
+///   caches, and copies it to the output with non-temporal (`MOVNTDQ`) stores, which
+///   bypass the caches.
+/// - Prefetches the next 16 KiB of input with the non-temporal hint, so it is read
+///   without displacing other cache lines.
+/// - The non-temporal stores cost throughput: into a freshly allocated output, whose
+///   pages the OS zeroes through the caches first, this measured about half as fast as
+///   `collapse_whitespace`, so only use it where keeping the caches warm matters more.
+/// - Other targets have no such stores, and collapse like `collapse_whitespace`.
+#[cfg(feature = "std")]
+pub fn collapse_whitespace_huge(input: &str) -> String {
+    // Safety: As in `collapse_whitespace`, and the blocks are joined by one space where
+    // a whitespace run was split
+    unsafe { String::from_utf8_unchecked(kernel::collapse_bytes_huge(input.as_bytes())) }
+}
 
-    // Safety: We only push valid UTF-8 bytes
-    unsafe { String::from_utf8_unchecked(result) }
+/// Collapses every Unicode whitespace character into a single ASCII space.
+///
+/// Unlike `collapse_whitespace`, this treats everything matched by [`char::is_whitespace`]
+/// as collapsible: line breaks, vertical tab, form feed, and non-ASCII spaces such as
+/// NBSP (U+00A0), EM SPACE (U+2003) and IDEOGRAPHIC SPACE (U+3000).
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+///
+/// # Returns
+/// - A single-line `String` where each whitespace run is replaced by one ASCII space.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_unicode_whitespace;
+/// let input = "\u{3000}こんにちは\u{3000}\u{3000}世界\u{a0}\n  again ";
+/// let output = collapse_unicode_whitespace(input);
+/// assert_eq!(output, "こんにちは 世界 again");
+/// ```
+///
+/// # Performance
+/// - ASCII text is pre-filtered with SIMD (`u8x16`) when available.
+/// - Only multi-byte sequences that may be whitespace are decoded.
+#[cfg(feature = "std")]
+pub fn collapse_unicode_whitespace(input: &str) -> String {
+    Collapser::single_line().unicode_whitespace(true).collapse(input)
 }
 
+/// Unfolds and collapses email header fields following RFC 5322 folding rules.
+///
+/// A line break followed by a space or tab (folding whitespace) is replaced by a single
+/// space, and every other run of spaces and tabs collapses to one space. Line breaks
+/// that end a header field are kept, so a whole header block can be normalized at once.
+/// `CRLF` is the line break required by RFC 5322, but bare `LF` is unfolded as well.
+///
+/// # Parameters
+/// - `input`: One or more header fields, possibly folded over several lines.
+///
+/// # Returns
+/// - A `String` with one line per header field and collapsed whitespace.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::unfold_headers;
+/// let input = "Subject: This is a\r\n   folded\t subject\r\nTo: a@example.com\r\n";
+/// assert_eq!(unfold_headers(input), "Subject: This is a folded subject\r\nTo: a@example.com\r\n");
+/// ```
+///
+/// # Performance
+/// - Text between line breaks and spaces is copied in bulk, found with SIMD (`u8x16`)
+///   when available.
+#[cfg(feature = "std")]
+pub fn unfold_headers(input: &str) -> String {
+    Collapser::new().line_breaks(LineBreaks::Unfold).collapse(input)
+}
 
-/// Collapses consecutive spaces and tabs into a single space in the input string.
+/// Collapses consecutive spaces and tabs and lowercases ASCII letters in one pass.
+///
+/// Search and deduplication keys are usually built by lowercasing and then collapsing;
+/// this fuses both into a single SIMD pass with a single allocation. Non-ASCII
+/// characters are copied through unchanged.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+///
+/// # Returns
+/// - A `String` with collapsed whitespace and ASCII letters in lowercase.
 ///
-/// This function provides a **scalar (non-SIMD) fallback** for `collapse_whitespace`,
-/// ensuring compatibility with architectures that do not support `wide::u8x16` SIMD processing.
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_and_ascii_lowercase;
+/// let input = "  The QUICK \t Brown   Fox ";
+/// assert_eq!(collapse_and_ascii_lowercase(input), "the quick brown fox");
+/// ```
+///
+/// # Performance
+/// - Uses SIMD (`u8x16`) to lowercase and classify 16 bytes at a time.
+/// - Falls back to scalar processing for remaining bytes.
+#[cfg(feature = "std")]
+pub fn collapse_and_ascii_lowercase(input: &str) -> String {
+    // Safety: Lowercasing only changes ASCII bytes into other ASCII bytes
+    unsafe { String::from_utf8_unchecked(kernel::collapse_bytes_ascii_lowercase(input.as_bytes())) }
+}
+
+/// Returns the byte length of `collapse_whitespace(input)`, without building it.
 ///
-/// It processes the input byte by byte, replacing multiple consecutive spaces and tabs with
-/// a single space while preserving non-whitespace characters.
+/// Callers of fixed-size output buffers, such as
+/// [`StreamCollapser::push_to_slice`], can size them exactly, and serializers can write
+/// a length prefix before the collapsed data.
 ///
 /// # Parameters
 /// - `input`: A string slice (`&str`) containing text with irregular spacing.
 ///
 /// # Returns
-/// - A `String` with collapsed whitespace, where multiple spaces/tabs are replaced by a single space.
+/// - The exact length in bytes of the collapsed string.
 ///
 /// # Example
 /// ```
-/// use fast_whitespace_collapse::collapse_whitespace;
-/// let input = "Hello   \t world   !";
-/// let output = collapse_whitespace(input);
-/// assert_eq!(output, "Hello world !");
+/// use fast_whitespace_collapse::{collapse_whitespace, collapsed_len};
+/// let input = "  This   is \t  a   test.  ";
+/// assert_eq!(collapsed_len(input), collapse_whitespace(input).len());
 /// ```
 ///
 /// # Performance
-/// - This function **does not** use SIMD instructions.
-/// - It **iterates over bytes one by one**, making it slower than SIMD versions.
-/// - It ensures **compatibility across all architectures**, including those without AVX2, SSE2, or NEON.
-///
-/// # When is This Used?
-/// - If compiling for a **non-x86** or **non-aarch64** target.
-/// - If **SIMD is not available** on the target CPU.
-/// - If the Rust compiler **cannot enable** the required SIMD features.
-#[cfg(not(any(
-    all(target_arch = "x86_64", target_feature = "sse2"),
-    all(target_arch = "x86_64", target_feature = "avx2"),
-    all(target_arch = "aarch64", target_feature = "neon")
-)))]
-pub fn collapse_whitespace(input: &str) -> String {
-    let bytes = input.as_bytes();
-    let mut result = Vec::with_capacity(bytes.len());
-    
-    let mut last_was_space = true;
-
-    for &b in bytes {
-        if b == b' ' || b == b'\t' {
-            if !last_was_space {
-                result.push(b' ');
-                last_was_space = true;
-            }
-        } else {
-            result.push(b);
-            last_was_space = false;
-        }
-    }
+/// - Uses SIMD (`u8x16`) to trim the ends and count the bytes removed inside runs, 16
+///   bytes at a time.
+/// - Reads the input once and writes nothing.
+#[cfg(feature = "std")]
+pub fn collapsed_len(input: &str) -> usize {
+    kernel::collapsed_len(input.as_bytes())
+}
 
-    // Trim trailing space if exists
-    if result.last() == Some(&b' ') {
-        result.pop();
+/// Joins string pieces with single spaces, collapsing the whitespace inside each piece.
+///
+/// Every piece is collapsed and trimmed like [`collapse_whitespace`], and the pieces
+/// that are not empty afterwards are concatenated with exactly one space between them.
+/// This rebuilds a sentence from tokens or fields in one pass, without collapsing the
+/// joined string again; it is the `String` form of collecting a [`CollapsedString`].
+///
+/// # Parameters
+/// - `pieces`: The string pieces to join, such as `&str`s or `String`s.
+///
+/// # Returns
+/// - A `String` with the collapsed pieces separated by single spaces.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::join_collapsed;
+/// let fields = ["  Jane ", "", " \t ", "van   der\tBerg  "];
+/// assert_eq!(join_collapsed(fields), "Jane van der Berg");
+/// ```
+///
+/// # Performance
+/// - Each piece is collapsed with the SIMD kernel of `collapse_whitespace`, straight
+///   into the output.
+#[cfg(feature = "std")]
+pub fn join_collapsed<I>(pieces: I) -> String
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut result = CollapsedString::new();
+    for piece in pieces {
+        result.push_piece(piece.as_ref());
     }
-
-    // Convert back to a String
-    unsafe { String::from_utf8_unchecked(result) }
+    result.into_string()
 }
 
-
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
-    use super::collapse_whitespace;
+    use std::borrow::Cow;
+
+    use super::{
+        collapse_and_ascii_lowercase, collapse_unicode_whitespace, collapse_whitespace, collapse_whitespace_cow,
+        collapsed_len, join_collapsed, unfold_headers,
+    };
     
     #[test]
     fn test_basic_collapse() {
@@ -221,4 +474,71 @@ mod tests {
         assert_eq!(collapse_whitespace("こんにちは\t\t世界"), "こんにちは 世界");
         assert_eq!(collapse_whitespace("你好\t世界\t"), "你好 世界");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn unicode_whitespace_collapse() {
+        assert_eq!(collapse_unicode_whitespace("a\u{a0}\u{2003}\u{3000}b"), "a b");
+        assert_eq!(collapse_unicode_whitespace("\u{85}a\r\n\x0bb\u{2028}\u{2029}"), "a b");
+        assert_eq!(collapse_unicode_whitespace("no\u{200b}break"), "no\u{200b}break");
+        assert_eq!(collapse_unicode_whitespace("\u{3000}\u{3000}"), "");
+    }
+
+    #[test]
+    fn header_unfolding() {
+        assert_eq!(unfold_headers("Subject: a\r\n b"), "Subject: a b");
+        assert_eq!(unfold_headers("Subject: a  \r\n\t\t b  \r\nFrom: c \r\n"), "Subject: a b\r\nFrom: c\r\n");
+        assert_eq!(unfold_headers("X-Long: one\r\n two\r\n three\r\n\r\nbody"), "X-Long: one two three\r\n\r\nbody");
+        assert_eq!(unfold_headers("To: a,\n b"), "To: a, b");
+    }
+
+    #[test]
+    fn ascii_lowercase_collapse() {
+        assert_eq!(collapse_and_ascii_lowercase("  HeLLo   WORLD\t!"), "hello world !");
+        assert_eq!(collapse_and_ascii_lowercase("ÀÉ  ÎÕ  Straße"), "ÀÉ ÎÕ straße");
+        assert_eq!(collapse_and_ascii_lowercase("@[`{ AZ az"), "@[`{ az az");
+
+        let long = "ABCDEFGHIJKLMNOPQRSTUVWXYZ  0123456789  @[`{";
+        assert_eq!(collapse_and_ascii_lowercase(long), collapse_whitespace(&long.to_ascii_lowercase()));
+    }
+
+    #[test]
+    fn joins_collapsed_pieces() {
+        assert_eq!(join_collapsed([""; 0]), "");
+        assert_eq!(join_collapsed(["", " \t "]), "");
+        assert_eq!(join_collapsed([" a  b", "", "c ", " ", "\td"]), "a b c d");
+        assert_eq!(join_collapsed(vec![String::from("x"), String::from("  y  ")]), "x y");
+        assert_eq!(join_collapsed(["  こんにちは\t", "\t世界  "]), "こんにちは 世界");
+    }
+
+    #[test]
+    fn borrows_collapsed_input() {
+        let clean = "Already collapsed text, long enough to span a few 64-byte chunks of the pre-scan.".repeat(3);
+        assert!(matches!(collapse_whitespace_cow(&clean), Cow::Borrowed(_)));
+        assert!(matches!(collapse_whitespace_cow(""), Cow::Borrowed(_)));
+        for input in [" x", "x ", "a\tb", "a  b", &format!("{clean}  {clean}"), &format!("{}\t", "x".repeat(64))] {
+            let collapsed = collapse_whitespace_cow(input);
+            assert!(matches!(collapsed, Cow::Owned(_)), "{input:?}");
+            assert_eq!(collapsed, collapse_whitespace(input), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn collapsed_length() {
+        let inputs = [
+            "",
+            " \t ",
+            "x",
+            "This   is \t  a   test.",
+            "  Leading and trailing  ",
+            "こんにちは\t\t世界 😀  😃",
+        ];
+        for input in inputs {
+            assert_eq!(collapsed_len(input), collapse_whitespace(input).len(), "{input:?}");
+        }
+        for offset in 0..40 {
+            // Runs straddling the 16-byte chunks
+            let input = format!("{}  {}{}", "x".repeat(offset), " \t".repeat(offset % 9), "y ".repeat(10));
+            assert_eq!(collapsed_len(&input), collapse_whitespace(&input).len(), "{input:?}");
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! Line-by-line collapsing that keeps the shape of multi-line text.
+
+use std::borrow::Cow;
+use std::iter::FusedIterator;
+use std::str::Lines;
+
+use crate::kernel;
+
+/// Collapses and trims every line of `input` independently, keeping the line breaks.
+///
+/// Unlike [`collapse_whitespace`](crate::collapse_whitespace), which only trims the ends
+/// of the whole input, this removes leading and trailing spaces and tabs from every
+/// line. Line breaks (`\n`, `\r\n` and lone `\r`) are copied through exactly, so the
+/// document keeps its line structure, including empty lines.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing lines with irregular spacing.
+///
+/// # Returns
+/// - A `String` with the same line breaks as `input` and every line collapsed.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_lines;
+/// let input = "  Dear   team, \r\n\r\n\tThanks \t for   all\n  ";
+/// assert_eq!(collapse_lines(input), "Dear team,\r\n\r\nThanks for all\n");
+/// ```
+///
+/// # Performance
+/// - Each line is collapsed with the SIMD kernel of `collapse_whitespace`.
+/// - All lines are written into a single allocation.
+pub fn collapse_lines(input: &str) -> String {
+    let mut result = Vec::with_capacity(input.len());
+    for (line, ending) in RawLines(input) {
+        kernel::collapse_bytes_into(line.as_bytes(), &mut result);
+        result.extend_from_slice(ending.as_bytes());
+    }
+
+    // Safety: The kernel only ever removes spaces/tabs or writes a space
+    unsafe { String::from_utf8_unchecked(result) }
+}
+
+/// Returns an iterator over the collapsed lines of `input`.
+///
+/// This is the iterator form of [`collapse_lines`]: each item is one collapsed and
+/// trimmed line followed by its original line break, so concatenating the items gives
+/// the same result as `collapse_lines(input)`. The last line has no line break if
+/// `input` does not end with one.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_lines_iter;
+/// let lines: Vec<String> = collapse_lines_iter(" a  b \n\n c\r\nd ").collect();
+/// assert_eq!(lines, ["a b\n", "\n", "c\r\n", "d"]);
+/// ```
+pub fn collapse_lines_iter(input: &str) -> CollapseLines<'_> {
+    CollapseLines { lines: RawLines(input) }
+}
+
+/// Iterator over collapsed lines, created by [`collapse_lines_iter`].
+#[derive(Debug, Clone)]
+pub struct CollapseLines<'a> {
+    lines: RawLines<'a>,
+}
+
+impl Iterator for CollapseLines<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let (line, ending) = self.lines.next()?;
+        let mut result = Vec::with_capacity(line.len() + ending.len());
+        kernel::collapse_bytes_into(line.as_bytes(), &mut result);
+        result.extend_from_slice(ending.as_bytes());
+
+        // Safety: The kernel only ever removes spaces/tabs or writes a space
+        Some(unsafe { String::from_utf8_unchecked(result) })
+    }
+}
+
+/// Extension trait adding [`lines_collapsed`](Self::lines_collapsed) to `str`.
+pub trait LinesCollapsedExt {
+    /// Returns an iterator over the lines of the string, each collapsed and trimmed.
+    ///
+    /// This is equivalent to `s.lines().map(collapse_whitespace)`: lines are split at
+    /// `\n` and `\r\n` like [`str::lines`], and collapsed like
+    /// [`collapse_whitespace`](crate::collapse_whitespace). A line that is already
+    /// collapsed is borrowed from the string instead of being copied.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::LinesCollapsedExt;
+    /// use std::borrow::Cow;
+    ///
+    /// let lines: Vec<Cow<str>> = "clean line\r\n  messy \t line \n".lines_collapsed().collect();
+    /// assert_eq!(lines, ["clean line", "messy line"]);
+    /// assert!(matches!(lines[0], Cow::Borrowed(_)));
+    /// ```
+    ///
+    /// # Performance
+    /// - Whether a line needs collapsing is checked with the SIMD scan used by
+    ///   [`collapsed_len`](crate::collapsed_len), so clean lines are never allocated.
+    /// - The other lines are collapsed with the SIMD kernel of `collapse_whitespace`.
+    fn lines_collapsed(&self) -> LinesCollapsed<'_>;
+}
+
+impl LinesCollapsedExt for str {
+    fn lines_collapsed(&self) -> LinesCollapsed<'_> {
+        LinesCollapsed { lines: self.lines() }
+    }
+}
+
+/// Iterator over collapsed lines, created by [`LinesCollapsedExt::lines_collapsed`].
+#[derive(Debug, Clone)]
+pub struct LinesCollapsed<'a> {
+    lines: Lines<'a>,
+}
+
+impl<'a> Iterator for LinesCollapsed<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Cow<'a, str>> {
+        self.lines.next().map(collapse_line)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.lines.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for LinesCollapsed<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.lines.next_back().map(collapse_line)
+    }
+}
+
+impl FusedIterator for LinesCollapsed<'_> {}
+
+/// Collapses `line`, borrowing it if it is already collapsed.
+fn collapse_line(line: &str) -> Cow<'_, str> {
+    if kernel::is_collapsed(line.as_bytes()) {
+        Cow::Borrowed(line)
+    } else {
+        // Safety: The kernel only ever removes spaces/tabs or writes a space
+        Cow::Owned(unsafe { String::from_utf8_unchecked(kernel::collapse_bytes(line.as_bytes())) })
+    }
+}
+
+/// Collapses every line of `input` and re-wraps it to at most `width` columns.
+///
+/// Each line is collapsed and trimmed as by [`collapse_lines`], then broken at spaces
+/// so that no line is longer than `width` characters. Existing line breaks are kept
+/// as hard breaks, and inserted breaks use the line break of the line being wrapped
+/// (`\n` for a final line without one). A word longer than `width` is never split and
+/// gets a line of its own.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+/// - `width`: The maximum line length, counted in `char`s.
+///
+/// # Returns
+/// - A `String` with collapsed whitespace, wrapped to `width` columns.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_and_wrap;
+/// let input = "Fix   the parser   so that\t long  lines wrap\n\nDetails  follow.";
+/// assert_eq!(
+///     collapse_and_wrap(input, 16),
+///     "Fix the parser\nso that long\nlines wrap\n\nDetails follow."
+/// );
+/// ```
+///
+/// # Performance
+/// - Each line is collapsed with the SIMD kernel of `collapse_whitespace` before it is
+///   wrapped, and all lines are written into a single allocation.
+pub fn collapse_and_wrap(input: &str, width: usize) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut collapsed = Vec::new();
+    for (line, ending) in RawLines(input) {
+        collapsed.clear();
+        kernel::collapse_bytes_into(line.as_bytes(), &mut collapsed);
+        // Safety: The kernel only ever removes spaces/tabs or writes a space
+        let collapsed = unsafe { std::str::from_utf8_unchecked(&collapsed) };
+
+        let line_break = if ending.is_empty() { "\n" } else { ending };
+        let mut column = 0;
+        for word in collapsed.split(' ') {
+            let word_width = word.chars().count();
+            if column > 0 && column + 1 + word_width > width {
+                result.push_str(line_break);
+                column = 0;
+            } else if column > 0 {
+                result.push(' ');
+                column += 1;
+            }
+            result.push_str(word);
+            column += word_width;
+        }
+        result.push_str(ending);
+    }
+    result
+}
+
+/// Splits text into `(line, line break)` pairs, where the line break is `\n`, `\r\n`,
+/// `\r`, or empty for a final line without one.
+#[derive(Debug, Clone)]
+struct RawLines<'a>(&'a str);
+
+impl<'a> Iterator for RawLines<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<(&'a str, &'a str)> {
+        if self.0.is_empty() {
+            return None;
+        }
+
+        let end = self.0.find(['\n', '\r']).unwrap_or(self.0.len());
+        let ending_len = match &self.0.as_bytes()[end..] {
+            [b'\r', b'\n', ..] => 2,
+            [] => 0,
+            _ => 1,
+        };
+        let (line, rest) = self.0.split_at(end);
+        let (ending, rest) = rest.split_at(ending_len);
+        self.0 = rest;
+        Some((line, ending))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{collapse_and_wrap, collapse_lines, collapse_lines_iter, LinesCollapsedExt};
+    use crate::collapse_whitespace;
+    use std::borrow::Cow;
+
+    #[test]
+    fn collapses_each_line() {
+        assert_eq!(collapse_lines(""), "");
+        assert_eq!(collapse_lines(" \t "), "");
+        assert_eq!(collapse_lines("  a   b  \n  c  "), "a b\nc");
+        assert_eq!(collapse_lines("Line1\n   Line2\nLine3"), "Line1\nLine2\nLine3");
+    }
+
+    #[test]
+    fn keeps_exact_line_breaks() {
+        assert_eq!(collapse_lines(" a \r\n b \r c \n"), "a\r\nb\rc\n");
+        assert_eq!(collapse_lines("\n \n\t\r\n\r"), "\n\n\r\n\r");
+        assert_eq!(collapse_lines("a\r\r\nb"), "a\r\r\nb");
+    }
+
+    #[test]
+    fn iterator_matches_string_form() {
+        let inputs = ["", "a", "a\n", " a  b \n\n c\r\nd ", "\r\r\n \n x\t\ty \r"];
+        for input in inputs {
+            assert_eq!(collapse_lines_iter(input).collect::<String>(), collapse_lines(input), "{input:?}");
+        }
+        assert_eq!(collapse_lines_iter("a\n").count(), 1);
+    }
+
+    #[test]
+    fn lines_collapsed_matches_mapping_lines() {
+        let inputs = ["", "a", "a\n", " a  b \n\n c\r\nd ", "\r\r\n \n x\t\ty \r", "ok\nfine line\r\n\t"];
+        for input in inputs {
+            let expected: Vec<String> = input.lines().map(collapse_whitespace).collect();
+            assert_eq!(input.lines_collapsed().collect::<Vec<_>>(), expected, "{input:?}");
+            let mut backward: Vec<_> = input.lines_collapsed().rev().collect();
+            backward.reverse();
+            assert_eq!(backward, expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn lines_collapsed_borrows_clean_lines() {
+        let input = "clean line\n\n one\ttab\nx  y\n";
+        let borrowed: Vec<bool> = input.lines_collapsed().map(|line| matches!(line, Cow::Borrowed(_))).collect();
+        assert_eq!(borrowed, [true, true, false, false]);
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        assert_eq!(collapse_and_wrap("", 10), "");
+        assert_eq!(collapse_and_wrap("a b c d", 3), "a b\nc d");
+        assert_eq!(collapse_and_wrap("  one   two\tthree  ", 9), "one two\nthree");
+        assert_eq!(collapse_and_wrap("aa bb", 5), "aa bb");
+        assert_eq!(collapse_and_wrap("aa bb", 4), "aa\nbb");
+    }
+
+    #[test]
+    fn wraps_each_line_with_its_line_break() {
+        assert_eq!(collapse_and_wrap("a b c\r\nd e f\r\n", 3), "a b\r\nc\r\nd e\r\nf\r\n");
+        assert_eq!(collapse_and_wrap("one two\n\nthree", 3), "one\ntwo\n\nthree");
+    }
+
+    #[test]
+    fn keeps_long_words_whole() {
+        assert_eq!(collapse_and_wrap("a https://example.com/long b", 5), "a\nhttps://example.com/long\nb");
+        assert_eq!(collapse_and_wrap("x y", 0), "x\ny");
+    }
+
+    #[test]
+    fn counts_chars_not_bytes() {
+        assert_eq!(collapse_and_wrap("日本 語の 文章", 5), "日本 語の\n文章");
+        for line in collapse_and_wrap(&"wörd ".repeat(30), 20).lines() {
+            assert!(line.chars().count() <= 20, "{line:?}");
+        }
+    }
+
+    #[test]
+    fn collapses_long_lines() {
+        let line = format!("  {}   {}  ", "x".repeat(20), "y".repeat(20));
+        let input = format!("{line}\n{line}");
+        let expected = format!("{0} {1}\n{0} {1}", "x".repeat(20), "y".repeat(20));
+        assert_eq!(collapse_lines(&input), expected);
+    }
+}
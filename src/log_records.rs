@@ -0,0 +1,158 @@
+//! A `log::Log` wrapper collapsing whitespace in the message of every record before an
+//! inner logger writes it.
+
+use log::{Log, Metadata, Record};
+
+use crate::Collapser;
+
+/// A logger that collapses whitespace in the message of every record, then forwards the
+/// record to an inner logger.
+///
+/// Installed in place of the inner logger, it normalizes every message without changing
+/// any call site. Messages are collapsed like
+/// [`collapse_whitespace`](crate::collapse_whitespace), or by the [`Collapser`] set with
+/// [`collapser`](Self::collapser), such as [`Collapser::log_line`] to keep every record
+/// on one line.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::{CollapseLogger, Collapser};
+///
+/// struct Stdout;
+///
+/// impl log::Log for Stdout {
+///     fn enabled(&self, _: &log::Metadata) -> bool {
+///         true
+///     }
+///
+///     fn log(&self, record: &log::Record) {
+///         println!("{} {}", record.level(), record.args());
+///     }
+///
+///     fn flush(&self) {}
+/// }
+///
+/// let logger = CollapseLogger::new(Stdout).collapser(Collapser::log_line());
+/// log::set_logger(Box::leak(Box::new(logger))).unwrap();
+/// log::set_max_level(log::LevelFilter::Info);
+/// // Prints "INFO request failed:\n caused by timeout", on one line
+/// log::info!("request  failed:\n\tcaused by {}", "timeout");
+/// ```
+///
+/// # Performance
+/// - Formats every message that is not a plain string literal into a `String` before
+///   collapsing it, but only for records the inner logger is enabled for.
+/// - Records are forwarded with their metadata, module path, file and line, but not the
+///   structured key-values of `log`'s `kv` feature.
+#[derive(Debug, Clone, Default)]
+pub struct CollapseLogger<L> {
+    inner: L,
+    collapser: Collapser,
+}
+
+impl<L> CollapseLogger<L> {
+    /// Wraps `inner`, collapsing spaces and tabs in every message it is given.
+    pub fn new(inner: L) -> Self {
+        Self { inner, collapser: Collapser::new() }
+    }
+
+    /// Sets the collapser the messages go through, such as [`Collapser::log_line`].
+    pub fn collapser(mut self, collapser: Collapser) -> Self {
+        self.collapser = collapser;
+        self
+    }
+
+    /// Returns a reference to the inner logger.
+    pub fn get_ref(&self) -> &L {
+        &self.inner
+    }
+
+    /// Returns the inner logger.
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+}
+
+impl<L: Log> Log for CollapseLogger<L> {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+        let formatted;
+        let message = match record.args().as_str() {
+            Some(message) => message,
+            None => {
+                formatted = record.args().to_string();
+                &formatted
+            }
+        };
+        let message = self.collapser.collapse(message);
+
+        let mut collapsed = Record::builder();
+        collapsed.metadata(record.metadata().clone()).line(record.line());
+        match record.module_path_static() {
+            Some(module_path) => collapsed.module_path_static(Some(module_path)),
+            None => collapsed.module_path(record.module_path()),
+        };
+        match record.file_static() {
+            Some(file) => collapsed.file_static(Some(file)),
+            None => collapsed.file(record.file()),
+        };
+        self.inner.log(&collapsed.args(format_args!("{message}")).build());
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use log::{Level, Log, Metadata, Record};
+
+    use super::CollapseLogger;
+    use crate::Collapser;
+
+    /// Keeps the records it is enabled for, as `level target:line message`.
+    #[derive(Default)]
+    struct Captured(Mutex<Vec<String>>);
+
+    impl Log for Captured {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.level() <= Level::Info
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            let line = format!("{} {}:{} {}", record.level(), record.target(), record.line().unwrap_or(0), record.args());
+            self.0.lock().unwrap().push(line);
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn record(logger: &impl Log, level: Level, args: std::fmt::Arguments<'_>) {
+        logger.log(&Record::builder().args(args).level(level).target("app").line(Some(7)).build());
+    }
+
+    #[test]
+    fn collapses_messages_before_forwarding() {
+        let logger = CollapseLogger::new(Captured::default());
+        record(&logger, Level::Info, format_args!("  signed   in\tfrom {}  ", "a\n  b"));
+        record(&logger, Level::Warn, format_args!("static  message"));
+        record(&logger, Level::Debug, format_args!("not  enabled"));
+        assert_eq!(*logger.get_ref().0.lock().unwrap(), ["INFO app:7 signed in from a\n b", "WARN app:7 static message"]);
+    }
+
+    #[test]
+    fn uses_the_given_collapser() {
+        let logger = CollapseLogger::new(Captured::default()).collapser(Collapser::log_line());
+        record(&logger, Level::Error, format_args!("panicked at:\n  {}\n", "src/main.rs:3"));
+        assert_eq!(logger.into_inner().0.into_inner().unwrap(), ["ERROR app:7 panicked at:\\n src/main.rs:3"]);
+    }
+}
@@ -0,0 +1,77 @@
+//! Whitespace collapsing fused with Unicode normalization, via `unicode-normalization`.
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+/// Applies NFC normalization and collapses consecutive spaces and tabs in one pass.
+///
+/// Normalization and whitespace collapsing are both part of nearly every text
+/// deduplication pipeline; this produces the result of
+/// `collapse_whitespace(&input.nfc().collect::<String>())` with a single allocation.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+///
+/// # Returns
+/// - An NFC-normalized `String` with collapsed whitespace.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_whitespace_nfc;
+/// let input = "  Cafe\u{301}   au \t lait ";
+/// assert_eq!(collapse_whitespace_nfc(input), "Café au lait");
+/// ```
+///
+/// # Performance
+/// - Input that is already in NFC (the common case) is detected with a quick check and
+///   collapsed directly with the SIMD kernel.
+/// - Other input is normalized as a stream of characters and collapsed on the fly.
+pub fn collapse_whitespace_nfc(input: &str) -> String {
+    if is_nfc_quick(input.chars()) == IsNormalized::Yes {
+        return crate::collapse_whitespace(input);
+    }
+
+    let mut result = String::with_capacity(input.len());
+    let mut last_was_space = true;
+
+    for c in input.nfc() {
+        if c == ' ' || c == '\t' {
+            if !last_was_space {
+                result.push(' ');
+                last_was_space = true;
+            }
+        } else {
+            result.push(c);
+            last_was_space = false;
+        }
+    }
+
+    // Trim trailing space if exists
+    if result.ends_with(' ') {
+        result.pop();
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::collapse_whitespace_nfc;
+    use crate::collapse_whitespace;
+    use unicode_normalization::UnicodeNormalization;
+
+    #[test]
+    fn normalizes_and_collapses() {
+        assert_eq!(collapse_whitespace_nfc("e\u{301}  e\u{301}"), "\u{e9} \u{e9}");
+        assert_eq!(collapse_whitespace_nfc("\u{212b}ngstr\u{f6}m \t unit"), "\u{c5}ngstr\u{f6}m unit");
+        assert_eq!(collapse_whitespace_nfc("  \u{1100}\u{1161}  "), "\u{ac00}");
+    }
+
+    #[test]
+    fn matches_two_pass_result() {
+        let inputs = ["", "   ", "already  normalized", "a\u{301}\u{316}\t\tb", "한\u{1100}\u{1161}  x "];
+        for input in inputs {
+            let expected = collapse_whitespace(&input.nfc().collect::<String>());
+            assert_eq!(collapse_whitespace_nfc(input), expected, "{input:?}");
+        }
+    }
+}
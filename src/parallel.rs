@@ -0,0 +1,213 @@
+//! Collapsing huge inputs on many cores, split into pieces between whitespace runs and
+//! their next byte.
+
+use rayon::iter::plumbing::{Consumer, ProducerCallback, UnindexedConsumer};
+use rayon::prelude::*;
+
+use crate::pieces::{self, MIN_PIECE_LEN};
+use crate::{batch, kernel};
+
+/// Collapses consecutive spaces and tabs like [`collapse_whitespace`](crate::collapse_whitespace),
+/// on all the threads of the Rayon pool.
+///
+/// Meant for documents of gigabytes on many-core machines; below a few megabytes, or
+/// with a single thread, it collapses on the calling thread like `collapse_whitespace`.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+///
+/// # Returns
+/// - The same `String` as `collapse_whitespace(input)`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_whitespace_parallel;
+/// let document = "Some  text,\tand   more ".repeat(200_000);
+/// assert_eq!(collapse_whitespace_parallel(&document), fast_whitespace_collapse::collapse_whitespace(&document));
+/// ```
+///
+/// # Performance
+/// - Splits the input into about four pieces per thread, of at least 1 MiB, each after
+///   the first starting right after a run of spaces and tabs, so every piece collapses
+///   on its own with the SIMD kernels and no run straddles two of them.
+/// - Joins the collapsed pieces with one space each, which stands for the run the split
+///   followed, copying them into the output in parallel too.
+/// - Holds the collapsed pieces and the output at once, so it needs about twice the
+///   memory of the output.
+pub fn collapse_whitespace_parallel(input: &str) -> String {
+    let piece_len = (input.len() / (4 * rayon::current_num_threads())).max(MIN_PIECE_LEN);
+    collapse_in_pieces(input, piece_len)
+}
+
+/// Collapses consecutive spaces and tabs in every string of `inputs` like
+/// [`collapse_many`](crate::collapse_many), on all the threads of the Rayon pool.
+///
+/// # Parameters
+/// - `inputs`: The strings to collapse, such as a `&[&str]` or a `&[String]`.
+///
+/// # Returns
+/// - The collapsed strings, in the order of `inputs`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_many_parallel;
+/// let rows = ["  Jane  Doe ", "clean", "\t(admin)\t\tEMEA"];
+/// assert_eq!(collapse_many_parallel(&rows), ["Jane Doe", "clean", "(admin) EMEA"]);
+/// ```
+///
+/// # Performance
+/// - Every Rayon task collapses its strings into a scratch buffer of its own, like
+///   `collapse_many` does for the whole batch.
+pub fn collapse_many_parallel<S: AsRef<str> + Sync>(inputs: &[S]) -> Vec<String> {
+    inputs.par_iter().map_init(Vec::new, |scratch, input| batch::collapse_with_scratch(input.as_ref(), scratch)).collect()
+}
+
+/// Collapses consecutive spaces and tabs in every string of `strings` in place like
+/// [`collapse_all_in_place`](crate::collapse_all_in_place), on all the threads of the
+/// Rayon pool.
+///
+/// # Parameters
+/// - `strings`: The strings to collapse, such as a `&mut Vec<String>`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_all_in_place_parallel;
+/// let mut rows = vec!["  Jane  Doe ".to_string(), "clean".into(), "\t(admin)\t\tEMEA".into()];
+/// collapse_all_in_place_parallel(&mut rows);
+/// assert_eq!(rows, ["Jane Doe", "clean", "(admin) EMEA"]);
+/// ```
+///
+/// # Performance
+/// - Every string keeps its allocation, and every Rayon task collapses its strings
+///   through a scratch buffer of its own.
+pub fn collapse_all_in_place_parallel(strings: &mut [String]) {
+    strings.par_iter_mut().for_each_init(Vec::new, |scratch, string| batch::collapse_in_place_with_scratch(string, scratch));
+}
+
+/// Extension trait collapsing whitespace in every string of a Rayon parallel iterator.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::ParallelCollapseWhitespaceExt;
+/// use rayon::prelude::*;
+///
+/// let rows = vec!["  Jane  Doe ".to_string(), "clean".into(), "\t(admin)\t\tEMEA".into()];
+/// let collapsed: Vec<String> = rows.par_iter().collapse_whitespace().collect();
+/// assert_eq!(collapsed, ["Jane Doe", "clean", "(admin) EMEA"]);
+/// ```
+pub trait ParallelCollapseWhitespaceExt: ParallelIterator + Sized
+where
+    Self::Item: AsRef<str>,
+{
+    /// Returns a parallel iterator over the strings collapsed like
+    /// [`collapse_whitespace`](crate::collapse_whitespace).
+    ///
+    /// It is indexed whenever `self` is, so it keeps the order of the strings when
+    /// collected and supports `zip`, `enumerate` and the like.
+    ///
+    /// # Performance
+    /// - Every Rayon task collapses its strings into a scratch buffer of its own, and
+    ///   gives each output an allocation of exactly its length, like
+    ///   [`collapse_many`](crate::collapse_many).
+    fn collapse_whitespace(self) -> ParallelCollapsed<Self> {
+        ParallelCollapsed { base: self }
+    }
+}
+
+impl<I: ParallelIterator> ParallelCollapseWhitespaceExt for I where I::Item: AsRef<str> {}
+
+/// Parallel iterator over collapsed strings, created by
+/// [`ParallelCollapseWhitespaceExt::collapse_whitespace`].
+#[derive(Debug, Clone)]
+pub struct ParallelCollapsed<I> {
+    base: I,
+}
+
+fn collapse_item<S: AsRef<str>>(scratch: &mut Vec<u8>, input: S) -> String {
+    batch::collapse_with_scratch(input.as_ref(), scratch)
+}
+
+impl<I: ParallelIterator> ParallelIterator for ParallelCollapsed<I>
+where
+    I::Item: AsRef<str>,
+{
+    type Item = String;
+
+    fn drive_unindexed<C: UnindexedConsumer<String>>(self, consumer: C) -> C::Result {
+        self.base.map_init(Vec::new, collapse_item).drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        self.base.opt_len()
+    }
+}
+
+impl<I: IndexedParallelIterator> IndexedParallelIterator for ParallelCollapsed<I>
+where
+    I::Item: AsRef<str>,
+{
+    fn len(&self) -> usize {
+        self.base.len()
+    }
+
+    fn drive<C: Consumer<String>>(self, consumer: C) -> C::Result {
+        self.base.map_init(Vec::new, collapse_item).drive(consumer)
+    }
+
+    fn with_producer<CB: ProducerCallback<String>>(self, callback: CB) -> CB::Output {
+        self.base.map_init(Vec::new, collapse_item).with_producer(callback)
+    }
+}
+
+/// Collapses `input` in parallel, split into pieces of at least `piece_len` bytes.
+fn collapse_in_pieces(input: &str, piece_len: usize) -> String {
+    let pieces = pieces::split_pieces(input.as_bytes(), piece_len);
+    if pieces.len() == 1 {
+        return crate::collapse_whitespace(input);
+    }
+    let collapsed: Vec<Vec<u8>> = pieces.par_iter().map(|piece| kernel::collapse_bytes(piece)).collect();
+    pieces::join_pieces(&collapsed, |slots| slots.into_par_iter().for_each(pieces::fill))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::collapse_in_pieces;
+    use crate::collapse_whitespace;
+
+    #[test]
+    fn matches_collapse_whitespace_at_every_piece_len() {
+        let inputs = ["    ".to_string(), "  lead and  \t trail  ".repeat(9), "こんにちは  世界\t\t, Привет   мир ".repeat(12)];
+        for input in &inputs {
+            for piece_len in [1, 7, 50, 1000] {
+                assert_eq!(collapse_in_pieces(input, piece_len), collapse_whitespace(input), "{piece_len} {input:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn collapse_many_parallel_matches_collapse_many() {
+        let rows: Vec<String> = (0..1000).map(|n| format!("{:n$}row\t\t{n}  ", "", n = n % 7)).collect();
+        assert_eq!(super::collapse_many_parallel(&rows), crate::collapse_many(&rows));
+    }
+
+    #[test]
+    fn in_place_parallel_matches_collapse_many() {
+        let mut rows: Vec<String> = (0..1000).map(|n| format!("{:n$}row\t\t{n}  ", "", n = n % 7)).collect();
+        let expected = crate::collapse_many(&rows);
+        super::collapse_all_in_place_parallel(&mut rows);
+        assert_eq!(rows, expected);
+    }
+
+    #[test]
+    fn parallel_iterator_matches_collapse_many() {
+        use super::ParallelCollapseWhitespaceExt;
+        use rayon::prelude::*;
+        let rows: Vec<String> = (0..1000).map(|n| format!("{:n$}row\t\t{n}  ", "", n = n % 7)).collect();
+        let expected = crate::collapse_many(&rows);
+        let collapsed: Vec<String> = rows.par_iter().collapse_whitespace().collect();
+        assert_eq!(collapsed, expected);
+        assert_eq!(rows.par_iter().collapse_whitespace().len(), rows.len());
+        let unindexed: Vec<String> = rows.into_par_iter().filter(|row| row.starts_with("row")).collapse_whitespace().collect();
+        assert_eq!(unindexed.len(), 143);
+    }
+}
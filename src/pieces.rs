@@ -0,0 +1,101 @@
+//! Splitting huge inputs into pieces that collapse independently, between whitespace
+//! runs and their next byte, and joining the collapsed pieces back together.
+
+use crate::scan;
+
+/// The shortest piece the input is split into, long enough for the tasks and the
+/// joining of their outputs to cost little next to collapsing it.
+pub(crate) const MIN_PIECE_LEN: usize = 1 << 20;
+
+/// Splits `bytes` into pieces of at least `piece_len` bytes, each ending right after a
+/// run of spaces and tabs, but the last.
+///
+/// A piece thus never splits a run or a UTF-8 sequence, and every piece but the first
+/// starts with a byte that is no blank.
+pub(crate) fn split_pieces(bytes: &[u8], piece_len: usize) -> Vec<&[u8]> {
+    let mut pieces = Vec::new();
+    let mut rest = bytes;
+    while rest.len() > piece_len {
+        let blank = piece_len + scan::find_blank(&rest[piece_len..], true);
+        let end = blank + scan::find_blank(&rest[blank..], false);
+        if end == rest.len() {
+            // No blank, or only blanks, left after `piece_len`
+            break;
+        }
+        let (piece, tail) = rest.split_at(end);
+        pieces.push(piece);
+        rest = tail;
+    }
+    pieces.push(rest);
+    pieces
+}
+
+/// A collapsed piece and the slot of the output it is copied to, along with the space
+/// separating it from the previous piece.
+pub(crate) type Slot<'a> = (&'a mut [u8], &'a [u8]);
+
+/// Joins the collapsed pieces of [`split_pieces`] with one space each, which stands for
+/// the run the split followed.
+///
+/// `copy` is given the slot of every piece, to [`fill`] them, in parallel or not.
+pub(crate) fn join_pieces(collapsed: &[Vec<u8>], copy: impl FnOnce(Vec<Slot<'_>>)) -> String {
+    // Every piece but the first starts with a byte that is no blank, so only the first
+    // can collapse to nothing, and every other one is preceded by the space of its run
+    let collapsed: Vec<&[u8]> = collapsed.iter().map(Vec::as_slice).filter(|piece| !piece.is_empty()).collect();
+    let len = collapsed.iter().map(|piece| piece.len()).sum::<usize>() + collapsed.len().saturating_sub(1);
+    let mut result = vec![0; len];
+    let mut slots = Vec::with_capacity(collapsed.len());
+    let mut rest = result.as_mut_slice();
+    for (n, piece) in collapsed.into_iter().enumerate() {
+        let (slot, tail) = std::mem::take(&mut rest).split_at_mut(usize::from(n > 0) + piece.len());
+        slots.push((slot, piece));
+        rest = tail;
+    }
+    copy(slots);
+
+    // Safety: Every piece ends at an ASCII blank or at the end of the input, so it is
+    // valid UTF-8, and collapsing and joining with spaces keeps it so
+    unsafe { String::from_utf8_unchecked(result) }
+}
+
+/// Copies a collapsed piece into its slot, after the separating space if it has one.
+pub(crate) fn fill((slot, piece): Slot<'_>) {
+    let (space, body) = slot.split_at_mut(slot.len() - piece.len());
+    space.fill(b' ');
+    body.copy_from_slice(piece);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fill, join_pieces, split_pieces};
+    use crate::{collapse_whitespace, kernel};
+
+    #[test]
+    fn pieces_collapse_independently_at_every_piece_len() {
+        let inputs = [
+            "".to_string(),
+            "    ".to_string(),
+            "  lead and  \t trail  ".repeat(9),
+            "no_blanks_".repeat(30),
+            format!("{}x{}", " ".repeat(100), " ".repeat(100)),
+            "こんにちは  世界\t\t, Привет   мир ".repeat(12),
+        ];
+        for input in &inputs {
+            for piece_len in [1, 2, 3, 7, 16, 50, 1000] {
+                let pieces = split_pieces(input.as_bytes(), piece_len);
+                for piece in &pieces {
+                    assert!(std::str::from_utf8(piece).is_ok(), "{piece_len} {input:?}");
+                }
+                let collapsed: Vec<Vec<u8>> = pieces.iter().map(|piece| kernel::collapse_bytes(piece)).collect();
+                let joined = join_pieces(&collapsed, |slots| slots.into_iter().for_each(fill));
+                assert_eq!(joined, collapse_whitespace(input), "{piece_len} {input:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn splits_after_blank_runs() {
+        let pieces = split_pieces(b"ab  cd\te  f", 2);
+        assert_eq!(pieces, [&b"ab  "[..], b"cd\t", b"e  ", b"f"]);
+    }
+}
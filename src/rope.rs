@@ -0,0 +1,188 @@
+//! Collapsing whitespace in `ropey` ropes, chunk by chunk, for editor tooling.
+
+use std::ops::Range;
+
+use ropey::{Rope, RopeBuilder};
+
+use crate::Collapser;
+
+/// Collapses consecutive spaces and tabs in `rope`, like
+/// [`collapse_whitespace`](crate::collapse_whitespace), into a new rope.
+///
+/// # Parameters
+/// - `rope`: The rope to collapse.
+///
+/// # Returns
+/// - A `Rope` with collapsed whitespace, equal to collapsing `rope.to_string()`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_whitespace_rope;
+/// use ropey::Rope;
+///
+/// let rope = Rope::from_str("  Hello  \t world ");
+/// assert_eq!(collapse_whitespace_rope(&rope), "Hello world");
+/// ```
+///
+/// # Performance
+/// - Collapses the chunks of `rope` one after the other with a [`StreamCollapser`](crate::StreamCollapser),
+///   and builds the output with a `RopeBuilder` from there, so the document is never
+///   copied into one `String`.
+pub fn collapse_whitespace_rope(rope: &Rope) -> Rope {
+    let mut stream = Collapser::new().stream();
+    let mut builder = RopeBuilder::new();
+    let mut collapsed = String::new();
+    for chunk in rope.chunks() {
+        stream.push_str(chunk, &mut collapsed);
+        builder.append(&collapsed);
+        collapsed.clear();
+    }
+    stream.finish_str(&mut collapsed);
+    builder.append(&collapsed);
+    builder.finish()
+}
+
+/// Collapses consecutive spaces and tabs in `rope` in place, with the fewest edits.
+///
+/// Only the whitespace runs that change are edited, never the text between them: a run keeps
+/// its first space and loses the other characters, and a run of tabs only has its
+/// first tab replaced by a space. Leading and trailing runs are removed. Editors can
+/// thus keep cursors, marks and undo history as small as the change itself.
+///
+/// # Parameters
+/// - `rope`: The rope to collapse, updated in place.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_whitespace_rope_in_place;
+/// use ropey::Rope;
+///
+/// let mut rope = Rope::from_str("  Hello  \t world ");
+/// collapse_whitespace_rope_in_place(&mut rope);
+/// assert_eq!(rope, "Hello world");
+/// ```
+///
+/// # Performance
+/// - Scans the chunks of `rope` one after the other for the runs to edit, then applies
+///   the edits from the end, each in time logarithmic in the length of the rope. A rope
+///   that is already collapsed is left untouched.
+pub fn collapse_whitespace_rope_in_place(rope: &mut Rope) {
+    for (range, replacement) in rope_edits(rope).into_iter().rev() {
+        rope.remove(range.clone());
+        if let Some(replacement) = replacement {
+            rope.insert_char(range.start, replacement);
+        }
+    }
+}
+
+/// Returns the edits collapsing `rope`, in ascending order: the char ranges to remove,
+/// each with the character to insert in its place, if any.
+fn rope_edits(rope: &Rope) -> Vec<(Range<usize>, Option<char>)> {
+    let mut edits = Vec::new();
+    // The char range of the current whitespace run, and the index of its first space
+    let mut run: Option<(Range<usize>, Option<usize>)> = None;
+    let mut seen_text = false;
+    let mut index = 0;
+    for chunk in rope.chunks() {
+        for byte in chunk.bytes() {
+            match byte {
+                b' ' | b'\t' => {
+                    let (range, space) = run.get_or_insert((index..index, None));
+                    range.end = index + 1;
+                    if byte == b' ' && space.is_none() {
+                        *space = Some(index);
+                    }
+                }
+                // UTF-8 continuation bytes do not start a char
+                _ if byte & 0xC0 == 0x80 => continue,
+                _ => {
+                    if let Some((range, space)) = run.take() {
+                        if seen_text {
+                            push_run_edits(&mut edits, range, space);
+                        } else {
+                            edits.push((range, None));
+                        }
+                    }
+                    seen_text = true;
+                }
+            }
+            index += 1;
+        }
+    }
+    if let Some((range, _)) = run {
+        edits.push((range, None));
+    }
+    edits
+}
+
+/// Pushes the edits turning the whitespace run `range` between two words into a space,
+/// keeping its first space at `space`, if any.
+fn push_run_edits(edits: &mut Vec<(Range<usize>, Option<char>)>, range: Range<usize>, space: Option<usize>) {
+    match space {
+        Some(space) => {
+            if range.start < space {
+                edits.push((range.start..space, None));
+            }
+            if space + 1 < range.end {
+                edits.push((space + 1..range.end, None));
+            }
+        }
+        None => {
+            edits.push((range.start..range.start + 1, Some(' ')));
+            if range.start + 1 < range.end {
+                edits.push((range.start + 1..range.end, None));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ropey::{Rope, RopeBuilder};
+
+    use super::{collapse_whitespace_rope, collapse_whitespace_rope_in_place, rope_edits};
+    use crate::collapse_whitespace;
+
+    const CASES: [&str; 8] = ["", "   ", "clean text", "  Hello  \t world ", "a\t\tb", "こんにちは \t 世界\t", "\ta b  c\t", "line one  \n\t line two"];
+
+    /// Builds a rope of `text` split into chunks of `chunk` bytes or more, on char boundaries.
+    fn chunked(text: &str, chunk: usize) -> Rope {
+        let mut builder = RopeBuilder::new();
+        let mut start = 0;
+        while start < text.len() {
+            let mut end = (start + chunk).min(text.len());
+            while !text.is_char_boundary(end) {
+                end += 1;
+            }
+            builder.append(&text[start..end]);
+            start = end;
+        }
+        builder.finish()
+    }
+
+    #[test]
+    fn rope_matches_collapse_whitespace() {
+        for text in CASES {
+            let long = text.repeat(300);
+            let rope = chunked(&long, 3);
+            assert_eq!(collapse_whitespace_rope(&rope), collapse_whitespace(&long).as_str(), "{text:?}");
+            assert_eq!(collapse_whitespace_rope(&Rope::from_str(text)), collapse_whitespace(text).as_str(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn in_place_matches_collapse_whitespace() {
+        for text in CASES {
+            let long = text.repeat(300);
+            let mut rope = chunked(&long, 3);
+            collapse_whitespace_rope_in_place(&mut rope);
+            assert_eq!(rope, collapse_whitespace(&long).as_str(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn in_place_makes_minimal_edits() {
+        assert!(rope_edits(&Rope::from_str("already collapsed")).is_empty());
+        assert_eq!(rope_edits(&Rope::from_str(" a\t \tb\t\tc ")), [(0..1, None), (2..3, None), (4..5, None), (6..7, Some(' ')), (7..8, None), (9..10, None)]);
+    }
+}
@@ -0,0 +1,388 @@
+//! Byte scanning helpers shared by the configurable [`Collapser`](crate::Collapser) and
+//! the word iterators.
+
+/// Which bytes, beyond those `<= b' '`, end a plain run in [`plain_prefix_len`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Stops {
+    /// Stop at the first byte of every multi-byte sequence.
+    pub(crate) non_ascii: bool,
+    /// Stop at U+00A0 NO-BREAK SPACE (`C2 A0`).
+    pub(crate) nbsp: bool,
+    /// Stop at sequences starting like U+3000 IDEOGRAPHIC SPACE (`E3 80`).
+    pub(crate) ideographic_space: bool,
+    /// Stop at sequences in U+2000..=U+207F GENERAL PUNCTUATION (`E2 80`, `E2 81`).
+    pub(crate) general_punctuation: bool,
+    /// Stop at U+FEFF ZERO WIDTH NO-BREAK SPACE, also known as the BOM (`EF BB`).
+    pub(crate) bom: bool,
+    /// Stop at `,`.
+    pub(crate) comma: bool,
+    /// Stop at `"` and `\`.
+    pub(crate) quote: bool,
+    /// Stop at DEL (`7F`) and the C1 controls U+0080..=U+009F (`C2 80..=C2 9F`).
+    pub(crate) controls: bool,
+}
+
+/// Returns the index of the first byte in `bytes` that is `<= b' '` or matched by
+/// `stops`, or `bytes.len()`.
+///
+/// Every ASCII byte the collapser may rewrite (spaces, tabs, line breaks) sits at or
+/// below `b' '`, so everything before the returned index can be copied verbatim.
+///
+/// # Performance
+/// - Uses SIMD (`u8x16`) to test 16 bytes at a time.
+/// - Multi-byte sequences such as NBSP are matched in SIMD by comparing each lane with
+///   the next byte, so they do not force a scalar pass. For three-byte sequences only
+///   the first two bytes are compared; the caller checks the rest.
+/// - Falls back to a scalar search for the remaining bytes.
+#[cfg(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+))]
+#[cfg(feature = "std")]
+pub(crate) fn plain_prefix_len(bytes: &[u8], stops: Stops) -> usize {
+    use wide::u8x16;
+    let limit = u8x16::splat(b' ');
+    let high = u8x16::splat(0x80);
+    let nbsp_lead = u8x16::splat(0xC2);
+    let nbsp_tail = u8x16::splat(0xA0);
+    let ideographic_lead = u8x16::splat(0xE3);
+    let ideographic_second = u8x16::splat(0x80);
+    let punctuation_lead = u8x16::splat(0xE2);
+    let punctuation_second = u8x16::splat(0x80);
+    let punctuation_second_high = u8x16::splat(0x81);
+    let bom_lead = u8x16::splat(0xEF);
+    let bom_second = u8x16::splat(0xBB);
+    let comma = u8x16::splat(b',');
+    let quote = u8x16::splat(b'"');
+    let backslash = u8x16::splat(b'\\');
+    let delete = u8x16::splat(0x7F);
+    let c1_lead = u8x16::splat(0xC2);
+    let c1_low = u8x16::splat(0x80);
+    let c1_high = u8x16::splat(0x9F);
+
+    let mut i = 0;
+    while i + 16 <= bytes.len() {
+        let arr: [u8; 16] = bytes[i..i + 16].try_into().unwrap();
+        let chunk = u8x16::from(arr);
+
+        // A lane is at or below the limit exactly when `min` leaves it unchanged
+        let mut mask = chunk.min(limit).cmp_eq(chunk);
+        if stops.non_ascii {
+            mask |= chunk.max(high).cmp_eq(chunk);
+        }
+        if stops.comma {
+            mask |= chunk.cmp_eq(comma);
+        }
+        if stops.quote {
+            mask |= chunk.cmp_eq(quote) | chunk.cmp_eq(backslash);
+        }
+        if stops.controls {
+            mask |= chunk.cmp_eq(delete);
+            let lead = chunk.cmp_eq(c1_lead);
+            mask |= match bytes.get(i + 1..i + 17) {
+                // The second byte is in range exactly when clamping it is a no-op
+                Some(next) => {
+                    let next = u8x16::from(<[u8; 16]>::try_from(next).unwrap());
+                    lead & next.max(c1_low).min(c1_high).cmp_eq(next)
+                }
+                None => lead,
+            };
+        }
+        if stops.nbsp || stops.ideographic_space || stops.general_punctuation || stops.bom {
+            // Compare lead bytes together with the following byte of every lane
+            let next = bytes
+                .get(i + 1..i + 17)
+                .map(|next| u8x16::from(<[u8; 16]>::try_from(next).unwrap()));
+            let mut pair = |enabled: bool, lead: u8x16, second: u8x16, second_high: u8x16| {
+                if enabled {
+                    let lead = chunk.cmp_eq(lead);
+                    mask |= match next {
+                        Some(next) => lead & (next.cmp_eq(second) | next.cmp_eq(second_high)),
+                        None => lead,
+                    };
+                }
+            };
+            pair(stops.nbsp, nbsp_lead, nbsp_tail, nbsp_tail);
+            pair(stops.ideographic_space, ideographic_lead, ideographic_second, ideographic_second);
+            pair(stops.general_punctuation, punctuation_lead, punctuation_second, punctuation_second_high);
+            pair(stops.bom, bom_lead, bom_second, bom_second);
+        }
+
+        let mask = mask.move_mask();
+        if mask != 0 {
+            return i + mask.trailing_zeros() as usize;
+        }
+
+        i += 16;
+    }
+
+    i + scalar_plain_prefix_len(&bytes[i..], stops)
+}
+
+/// Scalar version of `plain_prefix_len`, used when SIMD is unavailable.
+#[cfg(not(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+)))]
+#[cfg(feature = "std")]
+pub(crate) fn plain_prefix_len(bytes: &[u8], stops: Stops) -> usize {
+    scalar_plain_prefix_len(bytes, stops)
+}
+
+#[cfg(feature = "std")]
+fn scalar_plain_prefix_len(bytes: &[u8], stops: Stops) -> usize {
+    // A lead byte at the very end may start a sequence continued by later input
+    let pair = |i: usize, lead: u8, second: core::ops::RangeInclusive<u8>| {
+        bytes[i] == lead && (i + 1 == bytes.len() || second.contains(&bytes[i + 1]))
+    };
+    (0..bytes.len())
+        .position(|i| {
+            let b = bytes[i];
+            b <= b' '
+                || (stops.non_ascii && b >= 0x80)
+                || (stops.comma && b == b',')
+                || (stops.quote && matches!(b, b'"' | b'\\'))
+                || (stops.controls && (b == 0x7F || pair(i, 0xC2, 0x80..=0x9F)))
+                || (stops.nbsp && pair(i, 0xC2, 0xA0..=0xA0))
+                || (stops.ideographic_space && pair(i, 0xE3, 0x80..=0x80))
+                || (stops.general_punctuation && pair(i, 0xE2, 0x80..=0x81))
+                || (stops.bom && pair(i, 0xEF, 0xBB..=0xBB))
+        })
+        .unwrap_or(bytes.len())
+}
+
+/// Returns the index of the first space or tab in `bytes` if `blank` is `true`, or of
+/// the first other byte if it is `false`, or `bytes.len()`.
+///
+/// # Performance
+/// - Uses SIMD (`u8x16`) to test 16 bytes at a time.
+/// - Falls back to a scalar search for the remaining bytes.
+#[cfg(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+))]
+pub(crate) fn find_blank(bytes: &[u8], blank: bool) -> usize {
+    use wide::u8x16;
+    let space = u8x16::splat(b' ');
+    let tab = u8x16::splat(b'\t');
+
+    let mut i = 0;
+    while i + 16 <= bytes.len() {
+        let arr: [u8; 16] = bytes[i..i + 16].try_into().unwrap();
+        let chunk = u8x16::from(arr);
+
+        let mut mask = (chunk.cmp_eq(space) | chunk.cmp_eq(tab)).move_mask();
+        if !blank {
+            mask = !mask & 0xFFFF;
+        }
+        if mask != 0 {
+            return i + mask.trailing_zeros() as usize;
+        }
+
+        i += 16;
+    }
+
+    i + scalar_find_blank(&bytes[i..], blank)
+}
+
+/// Scalar version of `find_blank`, used when SIMD is unavailable.
+#[cfg(not(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+)))]
+pub(crate) fn find_blank(bytes: &[u8], blank: bool) -> usize {
+    scalar_find_blank(bytes, blank)
+}
+
+fn scalar_find_blank(bytes: &[u8], blank: bool) -> usize {
+    bytes.iter().position(|&b| (b == b' ' || b == b'\t') == blank).unwrap_or(bytes.len())
+}
+
+/// Returns the index just past the last space or tab in `bytes` if `blank` is `true`,
+/// or past the last other byte if it is `false`, or `0`.
+///
+/// # Performance
+/// - Uses SIMD (`u8x16`) to test 16 bytes at a time, from the end.
+/// - Falls back to a scalar search for the remaining bytes.
+#[cfg(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+))]
+pub(crate) fn rfind_blank(bytes: &[u8], blank: bool) -> usize {
+    use wide::u8x16;
+    let space = u8x16::splat(b' ');
+    let tab = u8x16::splat(b'\t');
+
+    let mut i = bytes.len();
+    while i >= 16 {
+        let arr: [u8; 16] = bytes[i - 16..i].try_into().unwrap();
+        let chunk = u8x16::from(arr);
+
+        let mut mask = (chunk.cmp_eq(space) | chunk.cmp_eq(tab)).move_mask();
+        if !blank {
+            mask = !mask & 0xFFFF;
+        }
+        if mask != 0 {
+            return i - 16 + (32 - mask.leading_zeros() as usize);
+        }
+
+        i -= 16;
+    }
+
+    scalar_rfind_blank(&bytes[..i], blank)
+}
+
+/// Scalar version of `rfind_blank`, used when SIMD is unavailable.
+#[cfg(not(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+)))]
+pub(crate) fn rfind_blank(bytes: &[u8], blank: bool) -> usize {
+    scalar_rfind_blank(bytes, blank)
+}
+
+fn scalar_rfind_blank(bytes: &[u8], blank: bool) -> usize {
+    bytes.iter().rposition(|&b| (b == b' ' || b == b'\t') == blank).map_or(0, |i| i + 1)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::{find_blank, plain_prefix_len, rfind_blank, Stops};
+
+    #[test]
+    fn finds_first_low_byte() {
+        assert_eq!(plain_prefix_len(b"", Stops::default()), 0);
+        assert_eq!(plain_prefix_len(b"abc", Stops::default()), 3);
+        assert_eq!(plain_prefix_len(b" abc", Stops::default()), 0);
+        assert_eq!(plain_prefix_len(b"abc\tdef", Stops::default()), 3);
+    }
+
+    #[test]
+    fn finds_low_byte_past_first_chunk() {
+        let mut input = vec![b'x'; 40];
+        input[37] = b'\n';
+        assert_eq!(plain_prefix_len(&input, Stops::default()), 37);
+        assert_eq!(plain_prefix_len(&input[..37], Stops::default()), 37);
+    }
+
+    #[test]
+    fn optionally_stops_at_non_ascii() {
+        let non_ascii = Stops { non_ascii: true, ..Stops::default() };
+        let input = "0123456789abcdefghijé ".as_bytes();
+        assert_eq!(plain_prefix_len(input, Stops::default()), 22);
+        assert_eq!(plain_prefix_len(input, non_ascii), 20);
+        assert_eq!(plain_prefix_len("é".as_bytes(), non_ascii), 0);
+    }
+
+    #[test]
+    fn optionally_stops_at_list_syntax() {
+        let comma = Stops { comma: true, ..Stops::default() };
+        let quote = Stops { quote: true, ..Stops::default() };
+        for offset in 0..40 {
+            let input = format!("{}\\\",{}", "x".repeat(offset), "z".repeat(20));
+            assert_eq!(plain_prefix_len(input.as_bytes(), comma), offset + 2, "{input:?}");
+            assert_eq!(plain_prefix_len(input.as_bytes(), quote), offset, "{input:?}");
+            assert_eq!(plain_prefix_len(&input.as_bytes()[offset + 1..], quote), 0, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn optionally_stops_at_controls() {
+        let controls = Stops { controls: true, ..Stops::default() };
+        for offset in 0..40 {
+            // `¢` (`C2 A2`) shares the lead byte of the C1 controls
+            let input = format!("{}¢\u{85}\u{7f}{}", "x".repeat(offset), "z".repeat(20));
+            let expected = input.find('\u{85}').unwrap();
+            assert_eq!(plain_prefix_len(input.as_bytes(), controls), expected, "{input:?}");
+            assert_eq!(plain_prefix_len(&input.as_bytes()[expected + 2..], controls), 0);
+        }
+    }
+
+    #[test]
+    fn optionally_stops_at_nbsp() {
+        let nbsp = Stops { nbsp: true, ..Stops::default() };
+        for offset in 0..40 {
+            // `Â` is also encoded with a `C2` lead byte and must not stop the scan
+            let input = format!("{}Â{}\u{a0}{}", "x".repeat(offset), "y".repeat(offset % 7), "z".repeat(20));
+            let expected = input.find('\u{a0}').unwrap();
+            assert_eq!(plain_prefix_len(input.as_bytes(), nbsp), expected, "{input:?}");
+            assert_eq!(plain_prefix_len(input.as_bytes(), Stops::default()), input.len());
+        }
+    }
+
+    #[test]
+    fn optionally_stops_at_ideographic_space() {
+        let ideographic = Stops { ideographic_space: true, ..Stops::default() };
+        for offset in 0..40 {
+            // `を` shares the `E3` lead byte but not the second byte
+            let input = format!("{}を\u{3000}{}", "x".repeat(offset), "z".repeat(20));
+            let expected = input.find('\u{3000}').unwrap();
+            assert_eq!(plain_prefix_len(input.as_bytes(), ideographic), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn optionally_stops_at_general_punctuation() {
+        let punctuation = Stops { general_punctuation: true, ..Stops::default() };
+        for offset in 0..40 {
+            // `€` (`E2 82 AC`) is outside the block
+            let input = format!("{}€\u{205f}{}", "x".repeat(offset), "z".repeat(20));
+            let expected = input.find('\u{205f}').unwrap();
+            assert_eq!(plain_prefix_len(input.as_bytes(), punctuation), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn finds_blank_and_non_blank_bytes() {
+        for offset in 0..40 {
+            let input = format!("{}\t  é{}", "x".repeat(offset), " ".repeat(20));
+            assert_eq!(find_blank(input.as_bytes(), true), offset, "{input:?}");
+            assert_eq!(find_blank(&input.as_bytes()[offset..], false), 3, "{input:?}");
+            assert_eq!(find_blank(&input.as_bytes()[offset + 5..], false), 20, "{input:?}");
+        }
+        assert_eq!(find_blank(b"", true), 0);
+    }
+
+    #[test]
+    fn finds_blank_and_non_blank_bytes_from_the_end() {
+        for offset in 0..40 {
+            let input = format!("{}é  \t{}", " ".repeat(20), "x".repeat(offset));
+            let len = input.len();
+            assert_eq!(rfind_blank(input.as_bytes(), true), len - offset, "{input:?}");
+            assert_eq!(rfind_blank(&input.as_bytes()[..len - offset], false), len - offset - 3, "{input:?}");
+            assert_eq!(rfind_blank(&input.as_bytes()[..20], false), 0, "{input:?}");
+        }
+        assert_eq!(rfind_blank(b"", true), 0);
+    }
+}
@@ -0,0 +1,97 @@
+//! Word segmentation of collapsed text, via `unicode-segmentation`.
+
+use std::iter::Peekable;
+use unicode_segmentation::{UWordBounds, UnicodeSegmentation};
+
+/// Returns an iterator over the UAX #29 word segments of the collapsed view of `input`.
+///
+/// The segments are those of `collapse_whitespace(input).split_word_bounds()`, but no
+/// collapsed string is allocated: words, punctuation and newlines are borrowed from
+/// `input`, and every run of spaces and tabs is yielded as a single `" "`. Leading and
+/// trailing runs are skipped, exactly as [`collapse_whitespace`](crate::collapse_whitespace)
+/// trims them.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+///
+/// # Returns
+/// - A [`UnicodeWordsCollapsed`] iterator yielding `&str` segments. To keep only the
+///   words, filter for segments containing an alphanumeric character.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::unicode_words_collapsed;
+/// let segments: Vec<&str> = unicode_words_collapsed("  Hello, \t wide   world!  ").collect();
+/// assert_eq!(segments, ["Hello", ",", " ", "wide", " ", "world", "!"]);
+/// ```
+pub fn unicode_words_collapsed(input: &str) -> UnicodeWordsCollapsed<'_> {
+    UnicodeWordsCollapsed {
+        segments: input.split_word_bounds().peekable(),
+        started: false,
+    }
+}
+
+/// Iterator over the word segments of collapsed text, created by [`unicode_words_collapsed`].
+#[derive(Debug, Clone)]
+pub struct UnicodeWordsCollapsed<'a> {
+    segments: Peekable<UWordBounds<'a>>,
+    started: bool,
+}
+
+impl<'a> Iterator for UnicodeWordsCollapsed<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        loop {
+            let segment = self.segments.next()?;
+            if !is_blank(segment) {
+                self.started = true;
+                return Some(segment);
+            }
+
+            // Spaces and tabs may be split over several segments; merge them into one run
+            while self.segments.next_if(|next| is_blank(next)).is_some() {}
+            if self.started && self.segments.peek().is_some() {
+                return Some(" ");
+            }
+        }
+    }
+}
+
+/// Returns `true` if `segment` consists only of spaces and tabs.
+fn is_blank(segment: &str) -> bool {
+    segment.bytes().all(|b| b == b' ' || b == b'\t')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unicode_words_collapsed;
+    use crate::collapse_whitespace;
+    use unicode_segmentation::UnicodeSegmentation;
+
+    #[test]
+    fn matches_segmenting_collapsed_string() {
+        let inputs = [
+            "",
+            " \t ",
+            "This   is \t  a   test.",
+            "  Leading and trailing  ",
+            "Line1\n   Line2 \t\nLine3",
+            "can't stop 3.14 \t naïve",
+            "こんにちは\t\t世界 😀  😃",
+        ];
+        for input in inputs {
+            let collapsed = collapse_whitespace(input);
+            let expected: Vec<&str> = collapsed.split_word_bounds().collect();
+            let segments: Vec<&str> = unicode_words_collapsed(input).collect();
+            assert_eq!(segments, expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn borrows_segments_from_input() {
+        let input = "alpha  beta";
+        let first = unicode_words_collapsed(input).next().unwrap();
+        assert_eq!(first.as_ptr(), input.as_ptr());
+    }
+}
@@ -0,0 +1,286 @@
+//! Helpers collapsing string fields while Serde deserializes or serializes them, for use
+//! with `#[serde(deserialize_with = "...")]`, `#[serde(serialize_with = "...")]`, or
+//! both at once with `#[serde(with = "...")]`.
+//!
+//! # Example
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Contact {
+//!     #[serde(deserialize_with = "fast_whitespace_collapse::serde::collapsed")]
+//!     name: String,
+//!     #[serde(default, deserialize_with = "fast_whitespace_collapse::serde::collapsed_option")]
+//!     title: Option<String>,
+//!     #[serde(with = "fast_whitespace_collapse::serde::collapse")]
+//!     team: String,
+//! }
+//!
+//! let mut contact: Contact = serde_json::from_str(r#"{"name": "  Jane \t Doe ", "title": "Head  of\tSales", "team": "EMEA"}"#).unwrap();
+//! assert_eq!(contact.name, "Jane Doe");
+//! assert_eq!(contact.title.as_deref(), Some("Head of Sales"));
+//!
+//! contact.team.push_str("  North ");
+//! assert_eq!(serde_json::to_string(&contact).unwrap(), r#"{"name":"Jane Doe","title":"Head of Sales","team":"EMEA North"}"#);
+//! ```
+
+use std::fmt;
+
+use ::serde::de::{self, Deserialize, Deserializer, Visitor};
+use ::serde::{Serialize, Serializer};
+
+use crate::{kernel, CollapsedString};
+
+/// Deserializes a string and collapses it like [`collapse_whitespace`](crate::collapse_whitespace).
+///
+/// # Parameters
+/// - `deserializer`: The Serde deserializer of the field.
+///
+/// # Returns
+/// - The collapsed `String`, or the error of `deserializer` if the field is no string.
+///
+/// # Example
+/// ```
+/// #[derive(serde::Deserialize)]
+/// struct Row {
+///     #[serde(deserialize_with = "fast_whitespace_collapse::serde::collapsed")]
+///     city: String,
+/// }
+///
+/// let row: Row = serde_json::from_str(r#"{"city": "\tNew   York "}"#).unwrap();
+/// assert_eq!(row.city, "New York");
+/// ```
+///
+/// # Performance
+/// - Collapses the string straight from the deserializer's buffer where it lends one,
+///   as `serde_json` does, so the field costs one allocation, of its collapsed length.
+/// - A string the deserializer hands over as a `String` is kept as is when it is
+///   already collapsed.
+pub fn collapsed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Collapsed::deserialize(deserializer).map(|collapsed| collapsed.0)
+}
+
+/// Deserializes an optional string and collapses it like [`collapsed`].
+///
+/// With `deserialize_with`, Serde no longer treats a missing field as `None` by
+/// itself, so the field needs `#[serde(default)]` too.
+///
+/// # Parameters
+/// - `deserializer`: The Serde deserializer of the field.
+///
+/// # Returns
+/// - `None` for a null, or the collapsed `String`.
+///
+/// # Example
+/// ```
+/// #[derive(serde::Deserialize)]
+/// struct Row {
+///     #[serde(default, deserialize_with = "fast_whitespace_collapse::serde::collapsed_option")]
+///     note: Option<String>,
+/// }
+///
+/// let rows: Vec<Row> = serde_json::from_str(r#"[{"note": " a  b "}, {"note": null}, {}]"#).unwrap();
+/// assert_eq!(rows.iter().map(|row| row.note.as_deref()).collect::<Vec<_>>(), [Some("a b"), None, None]);
+/// ```
+pub fn collapsed_option<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Option::<Collapsed>::deserialize(deserializer).map(|collapsed| collapsed.map(|collapsed| collapsed.0))
+}
+
+/// Serializes a string collapsed like [`collapse_whitespace`](crate::collapse_whitespace),
+/// whatever the field holds.
+///
+/// # Parameters
+/// - `value`: The field, such as a `String`.
+/// - `serializer`: The Serde serializer of the field.
+///
+/// # Returns
+/// - The result of `serializer`.
+///
+/// # Example
+/// ```
+/// #[derive(serde::Serialize)]
+/// struct Row {
+///     #[serde(serialize_with = "fast_whitespace_collapse::serde::serialize_collapsed")]
+///     city: String,
+/// }
+///
+/// let row = Row { city: "\tNew   York ".into() };
+/// assert_eq!(serde_json::to_string(&row).unwrap(), r#"{"city":"New York"}"#);
+/// ```
+///
+/// # Performance
+/// - A string that is already collapsed is serialized as is, without allocating.
+pub fn serialize_collapsed<T: AsRef<str> + ?Sized, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&crate::collapse_whitespace_cow(value.as_ref()))
+}
+
+/// Serializes an optional string collapsed like [`serialize_collapsed`], and `None` as
+/// Serde does.
+///
+/// # Parameters
+/// - `value`: The field, such as an `Option<String>`.
+/// - `serializer`: The Serde serializer of the field.
+///
+/// # Returns
+/// - The result of `serializer`.
+pub fn serialize_collapsed_option<T: AsRef<str>, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+    value.as_ref().map(|value| SerializeCollapsed(value.as_ref())).serialize(serializer)
+}
+
+/// [`collapsed`] and [`serialize_collapsed`] as one module, collapsing a string field
+/// both ways with `#[serde(with = "fast_whitespace_collapse::serde::collapse")]`.
+pub mod collapse {
+    use ::serde::{Deserializer, Serializer};
+
+    /// Deserializes a string collapsed, like [`collapsed`](super::collapsed).
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        super::collapsed(deserializer)
+    }
+
+    /// Serializes a string collapsed, like [`serialize_collapsed`](super::serialize_collapsed).
+    pub fn serialize<T: AsRef<str> + ?Sized, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        super::serialize_collapsed(value, serializer)
+    }
+}
+
+/// [`collapsed_option`] and [`serialize_collapsed_option`] as one module, collapsing an
+/// optional string field both ways with
+/// `#[serde(default, with = "fast_whitespace_collapse::serde::collapse_option")]`.
+pub mod collapse_option {
+    use ::serde::{Deserializer, Serializer};
+
+    /// Deserializes an optional string collapsed, like [`collapsed_option`](super::collapsed_option).
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+        super::collapsed_option(deserializer)
+    }
+
+    /// Serializes an optional string collapsed, like
+    /// [`serialize_collapsed_option`](super::serialize_collapsed_option).
+    pub fn serialize<T: AsRef<str>, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        super::serialize_collapsed_option(value, serializer)
+    }
+}
+
+impl Serialize for CollapsedString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for CollapsedString {
+    /// Deserializes a string and collapses it, like [`collapsed`].
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        collapsed(deserializer).map(CollapsedString::from_collapsed)
+    }
+}
+
+/// A string serialized collapsed.
+struct SerializeCollapsed<'a>(&'a str);
+
+impl Serialize for SerializeCollapsed<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_collapsed(self.0, serializer)
+    }
+}
+
+/// A string collapsed as it is deserialized.
+struct Collapsed(String);
+
+impl<'de> Deserialize<'de> for Collapsed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_string(CollapsedVisitor)
+    }
+}
+
+struct CollapsedVisitor;
+
+impl Visitor<'_> for CollapsedVisitor {
+    type Value = Collapsed;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Collapsed, E> {
+        Ok(Collapsed(crate::collapse_whitespace(value)))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Collapsed, E> {
+        if kernel::is_collapsed(value.as_bytes()) {
+            Ok(Collapsed(value))
+        } else {
+            self.visit_str(&value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::CollapsedString;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Row {
+        #[serde(deserialize_with = "super::collapsed")]
+        name: String,
+        #[serde(default, deserialize_with = "super::collapsed_option")]
+        note: Option<String>,
+    }
+
+    #[test]
+    fn collapses_fields_while_deserializing() {
+        let rows: Vec<Row> = serde_json::from_str(r#"[{"name": " Jane \t Doe ", "note": "a\tb  　c\\n"}, {"name": "clean", "note": null}, {"name": ""}]"#).unwrap();
+        assert_eq!(
+            rows,
+            [
+                Row { name: "Jane Doe".into(), note: Some("a b \u{3000}c\\n".into()) },
+                Row { name: "clean".into(), note: None },
+                Row { name: String::new(), note: None },
+            ]
+        );
+    }
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Normalized {
+        #[serde(with = "super::collapse")]
+        name: String,
+        #[serde(default, with = "super::collapse_option")]
+        note: Option<String>,
+        #[serde(skip_deserializing, serialize_with = "super::serialize_collapsed")]
+        raw: &'static str,
+    }
+
+    #[test]
+    fn collapses_fields_while_serializing() {
+        let row = Normalized { name: " Jane \t Doe ".into(), note: Some("a\tb  c".into()), raw: "  x  y" };
+        assert_eq!(serde_json::to_string(&row).unwrap(), r#"{"name":"Jane Doe","note":"a b c","raw":"x y"}"#);
+        let row = Normalized { note: None, ..row };
+        assert_eq!(serde_json::to_string(&row).unwrap(), r#"{"name":"Jane Doe","note":null,"raw":"x y"}"#);
+    }
+
+    #[test]
+    fn with_modules_collapse_both_ways() {
+        let rows: Vec<Normalized> = serde_json::from_str(r#"[{"name": "  a  b", "note": " c "}, {"name": "d"}]"#).unwrap();
+        assert_eq!(
+            rows,
+            [
+                Normalized { name: "a b".into(), note: Some("c".into()), raw: "" },
+                Normalized { name: "d".into(), note: None, raw: "" },
+            ]
+        );
+    }
+
+    #[test]
+    fn collapsed_string_collapses_when_deserialized() {
+        let strings: Vec<CollapsedString> = serde_json::from_str(r#"["  a  b ", "clean"]"#).unwrap();
+        assert_eq!(strings, [CollapsedString::from("a b"), CollapsedString::from("clean")]);
+        assert_eq!(serde_json::to_string(&strings).unwrap(), r#"["a b","clean"]"#);
+    }
+
+    #[test]
+    fn rejects_fields_that_are_no_strings() {
+        let error = serde_json::from_str::<Row>(r#"{"name": 42}"#).unwrap_err();
+        assert!(error.to_string().contains("expected a string"), "{error}");
+    }
+}
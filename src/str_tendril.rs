@@ -0,0 +1,123 @@
+//! Collapsing whitespace in `tendril` strings, such as the text nodes of `html5ever`.
+
+use tendril::fmt::UTF8;
+use tendril::{Atomicity, Tendril};
+
+use crate::kernel;
+
+/// Collapses consecutive spaces and tabs in `tendril`, like
+/// [`collapse_whitespace`](crate::collapse_whitespace), into a new tendril.
+///
+/// Works on `StrTendril` and on its atomic variant alike.
+///
+/// # Parameters
+/// - `tendril`: The tendril to collapse, such as a text node from `html5ever`.
+///
+/// # Returns
+/// - A tendril with collapsed whitespace, sharing the buffer of `tendril` where it can.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_whitespace_tendril;
+/// use tendril::StrTendril;
+///
+/// let text = StrTendril::from_slice("  Hello  \t world ");
+/// assert_eq!(&*collapse_whitespace_tendril(&text), "Hello world");
+/// ```
+///
+/// # Performance
+/// - A tendril that is already collapsed is cloned, and one that only needs trimming
+///   is sliced: both share its buffer instead of copying it.
+/// - Any other tendril is collapsed with the SIMD kernel of `collapse_whitespace` and
+///   copied once into the new tendril, with no `String` in between.
+pub fn collapse_whitespace_tendril<A: Atomicity>(tendril: &Tendril<UTF8, A>) -> Tendril<UTF8, A> {
+    let bytes = tendril.as_bytes();
+    let (start, end) = trimmed_range(bytes);
+    if kernel::is_collapsed(&bytes[start..end]) {
+        return tendril.subtendril(start as u32, (end - start) as u32);
+    }
+    let mut collapsed = Vec::with_capacity(end - start);
+    kernel::collapse_bytes_into(&bytes[start..end], &mut collapsed);
+    // Safety: The kernel only ever removes spaces/tabs or writes a space
+    Tendril::from_slice(unsafe { std::str::from_utf8_unchecked(&collapsed) })
+}
+
+/// Collapses consecutive spaces and tabs in `tendril` in place, like
+/// [`collapse_whitespace_tendril`].
+///
+/// # Parameters
+/// - `tendril`: The tendril to collapse, updated in place.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_whitespace_tendril_in_place;
+/// use tendril::StrTendril;
+///
+/// let mut text = StrTendril::from_slice("\t Hello world  ");
+/// collapse_whitespace_tendril_in_place(&mut text);
+/// assert_eq!(&*text, "Hello world");
+/// ```
+///
+/// # Performance
+/// - A tendril that is already collapsed is left untouched, and one that only needs
+///   trimming is shortened where it is, without copying.
+pub fn collapse_whitespace_tendril_in_place<A: Atomicity>(tendril: &mut Tendril<UTF8, A>) {
+    let bytes = tendril.as_bytes();
+    let (start, end) = trimmed_range(bytes);
+    if kernel::is_collapsed(&bytes[start..end]) {
+        let trailing = (bytes.len() - end) as u32;
+        tendril.pop_back(trailing);
+        tendril.pop_front(start as u32);
+    } else {
+        *tendril = collapse_whitespace_tendril(tendril);
+    }
+}
+
+/// Returns the start and end of `bytes` without its leading and trailing spaces/tabs.
+fn trimmed_range(bytes: &[u8]) -> (usize, usize) {
+    let is_blank = |b: &u8| *b == b' ' || *b == b'\t';
+    let start = bytes.iter().position(|b| !is_blank(b)).unwrap_or(bytes.len());
+    let end = bytes.iter().rposition(|b| !is_blank(b)).map_or(start, |last| last + 1);
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use tendril::fmt::UTF8;
+    use tendril::{Atomic, StrTendril, Tendril};
+
+    use super::{collapse_whitespace_tendril, collapse_whitespace_tendril_in_place};
+    use crate::collapse_whitespace;
+
+    const CASES: [&str; 7] = ["", "   ", "clean text", "  Hello  \t world ", "\t trimmed only  ", "こんにちは \t 世界\t", "line one  \n\t line two"];
+
+    #[test]
+    fn matches_collapse_whitespace() {
+        for text in CASES {
+            let long = text.repeat(20);
+            for text in [text, &long] {
+                let tendril = StrTendril::from_slice(text);
+                assert_eq!(&*collapse_whitespace_tendril(&tendril), collapse_whitespace(text), "{text:?}");
+                let mut in_place = tendril.clone();
+                collapse_whitespace_tendril_in_place(&mut in_place);
+                assert_eq!(&*in_place, collapse_whitespace(text), "{text:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn shares_the_buffer_of_clean_and_trimmed_input() {
+        let clean = StrTendril::from_slice(&"clean text ".repeat(10)[..109]);
+        assert!(collapse_whitespace_tendril(&clean).is_shared_with(&clean));
+        let padded = StrTendril::from_slice(&format!("  {}\t", "clean text ".repeat(10).trim_end()));
+        let trimmed = collapse_whitespace_tendril(&padded);
+        assert!(trimmed.is_shared_with(&padded));
+        assert_eq!(&*trimmed, padded.trim());
+    }
+
+    #[test]
+    fn collapses_atomic_tendrils() {
+        let tendril: Tendril<UTF8, Atomic> = Tendril::from_slice("  a \t b ");
+        assert_eq!(&*collapse_whitespace_tendril(&tendril), "a b");
+    }
+}
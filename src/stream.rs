@@ -0,0 +1,413 @@
+//! Streaming whitespace collapsing over input that arrives in chunks.
+
+use std::borrow::Cow;
+
+use crate::collapser::{trailing_line_breaks_start, State};
+use crate::Collapser;
+
+/// A [`Collapser`] that processes its input in chunks.
+///
+/// Whitespace runs, `\r\n` pairs and multi-byte characters may straddle chunk
+/// boundaries: the pending state is carried from one chunk to the next, so the
+/// concatenated output is always identical to collapsing the whole input at once.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::{Collapser, StreamCollapser};
+/// let mut stream = StreamCollapser::new(Collapser::new().normalize_line_endings(true));
+/// let mut output = String::new();
+/// for chunk in ["  first  ", " \t  chunk\r", "\nsecond", "  chunk  "] {
+///     stream.push_str(chunk, &mut output);
+/// }
+/// stream.finish_str(&mut output);
+/// assert_eq!(output, "first chunk\nsecond chunk");
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamCollapser {
+    collapser: Collapser,
+    state: State,
+    /// Input that may be continued by the next chunk, such as part of a UTF-8 sequence.
+    carry: Vec<u8>,
+    /// Output that may still be rewritten by the trailing newline policy.
+    held: Vec<u8>,
+    /// Output with content has been written.
+    emitted: bool,
+    /// Output that did not fit into the slice given to [`push_to_slice`](Self::push_to_slice).
+    overflow: Vec<u8>,
+}
+
+impl StreamCollapser {
+    /// Creates a streaming collapser applying the options of `collapser`.
+    pub fn new(collapser: Collapser) -> Self {
+        Self {
+            collapser: collapser.resolved(),
+            state: State::default(),
+            carry: Vec::new(),
+            held: Vec::new(),
+            emitted: false,
+            overflow: Vec::new(),
+        }
+    }
+
+    /// Processes the next chunk of input, appending the collapsed output to `out`.
+    ///
+    /// A chunk may end anywhere, even inside a multi-byte UTF-8 sequence; the incomplete
+    /// part is kept until the next call. Output is written as soon as it is known, so a
+    /// trailing run of whitespace is held back until the next chunk shows whether it is
+    /// trailing.
+    ///
+    /// # Parameters
+    /// - `chunk`: The next bytes of the input.
+    /// - `out`: The buffer the collapsed output is appended to.
+    pub fn push_bytes(&mut self, chunk: &[u8], out: &mut Vec<u8>) {
+        out.append(&mut self.overflow);
+        let start = out.len();
+        out.append(&mut self.held);
+
+        // Complete the input carried over from the previous chunk one byte at a time
+        let mut chunk = chunk;
+        while !self.carry.is_empty() && !chunk.is_empty() {
+            self.carry.push(chunk[0]);
+            chunk = &chunk[1..];
+            let consumed = self.collapser.feed(&mut self.state, &self.carry, out, false);
+            self.carry.drain(..consumed);
+        }
+        if self.carry.is_empty() {
+            let consumed = self.collapser.feed(&mut self.state, chunk, out, false);
+            self.carry.extend_from_slice(&chunk[consumed..]);
+        }
+
+        self.hold_output_end(out, start);
+    }
+
+    /// Processes the next chunk of text, appending the collapsed output to `out`.
+    ///
+    /// Like [`push_bytes`](Self::push_bytes), but for input that is known to be valid
+    /// UTF-8.
+    pub fn push_str(&mut self, chunk: &str, out: &mut String) {
+        self.replace_invalid_sequences();
+        // Safety: the carry and the kept output are now valid UTF-8, and the chunk ends
+        // on a char boundary, so collapsing them writes valid UTF-8
+        unsafe { self.push_bytes(chunk.as_bytes(), out.as_mut_vec()) }
+    }
+
+    /// Finishes the input, appending the rest of the collapsed output to `out`.
+    ///
+    /// The stream is then reset, ready to collapse the next input.
+    pub fn finish(&mut self, out: &mut Vec<u8>) {
+        out.append(&mut self.overflow);
+        let start = out.len();
+        out.append(&mut self.held);
+        self.collapser.feed(&mut self.state, &self.carry, out, true);
+        self.collapser.finish(&mut self.state, out);
+        self.collapser.end_output(out, start, self.emitted);
+
+        self.state = State::default();
+        self.carry.clear();
+        self.emitted = false;
+    }
+
+    /// Finishes the input, appending the rest of the collapsed output to `out`.
+    ///
+    /// Like [`finish`](Self::finish), but for output built with
+    /// [`push_str`](Self::push_str). Invalid UTF-8 left by [`push_bytes`](Self::push_bytes)
+    /// or [`push_to_slice`](Self::push_to_slice), such as an incomplete sequence, is
+    /// written as U+FFFD REPLACEMENT CHARACTER.
+    pub fn finish_str(&mut self, out: &mut String) {
+        self.replace_invalid_sequences();
+        // Safety: as in `push_str`
+        unsafe { self.finish(out.as_mut_vec()) }
+    }
+
+    /// Processes the next chunk of input, writing as much collapsed output as fits into
+    /// `out`.
+    ///
+    /// This is the backpressure-aware form of [`push_bytes`](Self::push_bytes), for
+    /// ring buffers and fixed output buffers: it stops consuming `chunk` once `out` is
+    /// full, so the caller can drain `out` and call again with the rest of the chunk.
+    /// The few bytes of output produced by the last consumed input that do not fit are
+    /// kept and written first by the next call.
+    ///
+    /// # Parameters
+    /// - `chunk`: The next bytes of the input.
+    /// - `out`: The buffer the collapsed output is written to, from its start.
+    ///
+    /// # Returns
+    /// - `(consumed, written)`: The number of bytes of `chunk` consumed, and of `out`
+    ///   written.
+    ///
+    /// # Example
+    /// ```
+    /// use fast_whitespace_collapse::StreamCollapser;
+    /// let mut stream = StreamCollapser::default();
+    /// let mut buf = [0; 4];
+    /// let input = b"one   two";
+    /// let (consumed, written) = stream.push_to_slice(input, &mut buf);
+    /// assert_eq!((consumed, &buf[..written]), (7, &b"one "[..]));
+    /// let (consumed, written) = stream.push_to_slice(&input[consumed..], &mut buf);
+    /// assert_eq!((consumed, &buf[..written]), (2, &b"two"[..]));
+    /// ```
+    ///
+    /// # Performance
+    /// - The output is collapsed into a buffer of the stream, allocated by the first call
+    ///   and reused by the next ones, then copied into `out`, so every output byte is
+    ///   copied twice.
+    /// - Each step consumes at most as many bytes of `chunk` as `out` has room left, so
+    ///   the buffer stays about the size of `out`, plus any output held back until later
+    ///   input shows how to write it, such as a run of trailing line breaks.
+    pub fn push_to_slice(&mut self, chunk: &[u8], out: &mut [u8]) -> (usize, usize) {
+        let mut written = self.drain_overflow(out);
+        let mut consumed = 0;
+        while consumed < chunk.len() && written < out.len() {
+            // Collapsing rarely grows the input, so this seldom overflows `out`
+            let step = (out.len() - written).min(chunk.len() - consumed);
+            let mut overflow = std::mem::take(&mut self.overflow);
+            self.push_bytes(&chunk[consumed..consumed + step], &mut overflow);
+            self.overflow = overflow;
+            consumed += step;
+            written += self.drain_overflow(&mut out[written..]);
+        }
+        (consumed, written)
+    }
+
+    /// Finishes the input, writing as much of the rest of the collapsed output as fits
+    /// into `out`.
+    ///
+    /// This is the backpressure-aware form of [`finish`](Self::finish). Call it again
+    /// until it writes less than `out.len()` bytes: the output is then complete and the
+    /// stream is reset.
+    ///
+    /// # Returns
+    /// - The number of bytes of `out` written.
+    pub fn finish_to_slice(&mut self, out: &mut [u8]) -> usize {
+        // Finishing again once the stream is reset writes nothing more
+        let mut overflow = std::mem::take(&mut self.overflow);
+        self.finish(&mut overflow);
+        self.overflow = overflow;
+        self.drain_overflow(out)
+    }
+
+    /// Moves as much of the overflow as fits into `out`, returning its length.
+    fn drain_overflow(&mut self, out: &mut [u8]) -> usize {
+        let n = self.overflow.len().min(out.len());
+        out[..n].copy_from_slice(&self.overflow[..n]);
+        self.overflow.drain(..n);
+        n
+    }
+
+    /// Replaces the invalid UTF-8 left by [`push_bytes`](Self::push_bytes) and
+    /// [`push_to_slice`](Self::push_to_slice) with U+FFFD, before writing to a `String`.
+    ///
+    /// The carry may hold an incomplete sequence, such as after a quoted-string escape,
+    /// which can never be completed by valid UTF-8, and the output they kept back may be
+    /// cut inside a character or come from invalid input.
+    fn replace_invalid_sequences(&mut self) {
+        replace_invalid_utf8(&mut self.carry);
+        replace_invalid_utf8(&mut self.held);
+        replace_invalid_utf8(&mut self.overflow);
+    }
+
+    /// Moves the end of `out[start..]` that may still be rewritten into `held`.
+    fn hold_output_end(&mut self, out: &mut Vec<u8>, start: usize) {
+        if self.collapser.rewrites_output_end() {
+            let end = trailing_line_breaks_start(out, start);
+            self.emitted |= end > start;
+            self.held.extend_from_slice(&out[end..]);
+            out.truncate(end);
+        }
+    }
+}
+
+/// Replaces every invalid sequence of `bytes` with U+FFFD, keeping the valid UTF-8.
+fn replace_invalid_utf8(bytes: &mut Vec<u8>) {
+    if let Cow::Owned(valid) = String::from_utf8_lossy(bytes) {
+        *bytes = valid.into_bytes();
+    }
+}
+
+impl Default for StreamCollapser {
+    fn default() -> Self {
+        Self::new(Collapser::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StreamCollapser;
+    use crate::{Collapser, LineBreaks, LineSeparators, TrailingNewline};
+
+    const INPUTS: [&str; 6] = [
+        "  This   is \t  a   test.  ",
+        "a \r\n\r\n  b\r\r\n\t c \n\n\n",
+        "\u{a0}こんにちは\u{3000}\u{3000}世界 😀\u{2003} \u{200b}😃\u{2028}\u{2029}",
+        "e\u{301}  \u{301}\u{200d} \u{feff}x \u{85}\u{7f}y",
+        r#"a ,  "q \"  ,\\"  , "é\é"  b"#,
+        "key = a\n  b\n\n\tc\r\n",
+    ];
+
+    fn collapsers() -> [Collapser; 9] {
+        [
+            Collapser::new(),
+            Collapser::single_line(),
+            Collapser::security(),
+            Collapser::log_line(),
+            Collapser::http_field_value(),
+            Collapser::new().line_breaks(LineBreaks::Unfold).separator('_'),
+            Collapser::new().line_breaks(LineBreaks::Paragraphs).trailing_newline(TrailingNewline::Single),
+            Collapser::new().squeeze_blank_lines(true).trim_line_ends(true).nbsp_to_space(true),
+            Collapser::new()
+                .collapse_spaces(false)
+                .line_separators(LineSeparators::Newline)
+                .trailing_newline(TrailingNewline::Strip),
+        ]
+    }
+
+    fn stream(collapser: Collapser, chunks: &[&[u8]]) -> Vec<u8> {
+        let mut stream = StreamCollapser::new(collapser);
+        let mut output = Vec::new();
+        for chunk in chunks {
+            stream.push_bytes(chunk, &mut output);
+        }
+        stream.finish(&mut output);
+        output
+    }
+
+    #[test]
+    fn matches_one_shot_at_every_split() {
+        for collapser in collapsers() {
+            for input in INPUTS {
+                let expected = collapser.collapse(input);
+                let bytes = input.as_bytes();
+                for split in 0..=bytes.len() {
+                    let (head, tail) = bytes.split_at(split);
+                    assert_eq!(stream(collapser, &[head, tail]), expected.as_bytes(), "{collapser:?} {input:?} at {split}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_one_shot_byte_by_byte() {
+        for collapser in collapsers() {
+            for input in INPUTS {
+                let chunks: Vec<&[u8]> = input.as_bytes().chunks(1).collect();
+                assert_eq!(stream(collapser, &chunks), collapser.collapse(input).as_bytes(), "{collapser:?} {input:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn matches_one_shot_into_small_slices() {
+        for collapser in collapsers() {
+            for input in INPUTS {
+                for size in 1..6 {
+                    let mut stream = StreamCollapser::new(collapser);
+                    let mut output = Vec::new();
+                    let mut buf = vec![0; size];
+                    for mut chunk in input.as_bytes().chunks(7) {
+                        while !chunk.is_empty() {
+                            let (consumed, written) = stream.push_to_slice(chunk, &mut buf);
+                            assert!(consumed > 0 || written == size);
+                            output.extend_from_slice(&buf[..written]);
+                            chunk = &chunk[consumed..];
+                        }
+                    }
+                    loop {
+                        let written = stream.finish_to_slice(&mut buf);
+                        output.extend_from_slice(&buf[..written]);
+                        if written < size {
+                            break;
+                        }
+                    }
+                    assert_eq!(output, collapser.collapse(input).as_bytes(), "{collapser:?} {input:?} {size}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn streams_str_chunks() {
+        let mut stream = StreamCollapser::default();
+        let mut output = String::new();
+        for chunk in ["", "  a", "  ", "", "\tb  ", "\n  c  "] {
+            stream.push_str(chunk, &mut output);
+        }
+        stream.finish_str(&mut output);
+        assert_eq!(output, "a b \n c");
+    }
+
+    #[test]
+    fn writes_output_as_it_goes() {
+        let mut stream = StreamCollapser::default();
+        let mut output = String::new();
+        stream.push_str("  one   two  ", &mut output);
+        assert_eq!(output, "one two");
+        stream.push_str("three", &mut output);
+        assert_eq!(output, "one two three");
+    }
+
+    #[test]
+    fn replaces_incomplete_sequences_in_str_output() {
+        let mut stream = StreamCollapser::default();
+        let mut output = Vec::new();
+        stream.push_bytes(&"é".as_bytes()[..1], &mut output);
+        let mut output = String::from_utf8(output).unwrap();
+        stream.push_str(" x", &mut output);
+        stream.finish_str(&mut output);
+        assert_eq!(output, "\u{fffd} x");
+    }
+
+    #[test]
+    fn replaces_incomplete_sequences_after_escapes() {
+        let collapser = Collapser::new().quoted_strings(true);
+        for (bytes, rest, expected) in [(&b"\"\\\xC3"[..], "a", "\"\\\u{fffd}a"), (&b"\"\\\xE3\x81"[..], "\"  b", "\"\\\u{fffd}\"  b")] {
+            let mut stream = StreamCollapser::new(collapser);
+            let mut output = Vec::new();
+            stream.push_bytes(bytes, &mut output);
+            let mut output = String::from_utf8(output).unwrap();
+            stream.push_str(rest, &mut output);
+            stream.finish_str(&mut output);
+            assert!(std::str::from_utf8(output.as_bytes()).is_ok());
+            assert_eq!(output, collapser.collapse(expected), "{bytes:?}");
+
+            let mut output = Vec::new();
+            stream.push_bytes(bytes, &mut output);
+            let mut output = String::from_utf8(output).unwrap();
+            stream.finish_str(&mut output);
+            assert_eq!(output, collapser.collapse(&expected[..expected.len() - rest.len()]), "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn replaces_invalid_overflow_in_str_output() {
+        for (input, written, expected) in [(&b"\"\\\xff"[..], &b"\"\\"[..], "\u{fffd}x"), ("é".as_bytes(), &b"\xc3"[..], "\u{fffd}x")] {
+            let mut stream = StreamCollapser::new(Collapser::new().quoted_strings(true));
+            let mut buf = [0; 1];
+            let mut output = Vec::new();
+            let mut chunk = input;
+            while !chunk.is_empty() {
+                let (consumed, n) = stream.push_to_slice(chunk, &mut buf);
+                output.extend_from_slice(&buf[..n]);
+                chunk = &chunk[consumed..];
+            }
+            assert_eq!(output, written);
+            let mut output = String::new();
+            stream.push_str("x", &mut output);
+            stream.finish_str(&mut output);
+            assert!(std::str::from_utf8(output.as_bytes()).is_ok());
+            assert_eq!(output, expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn can_be_reused_after_finish() {
+        let mut stream = StreamCollapser::new(Collapser::new().trailing_newline(TrailingNewline::Single));
+        let mut first = String::new();
+        stream.push_str(" a \r", &mut first);
+        stream.finish_str(&mut first);
+        let mut second = String::new();
+        stream.push_str("\nb", &mut second);
+        stream.finish_str(&mut second);
+        assert_eq!((first.as_str(), second.as_str()), ("a\n", "\nb\n"));
+    }
+}
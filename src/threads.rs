@@ -0,0 +1,198 @@
+//! Collapsing huge inputs and batches of strings on scoped `std` threads, for builds
+//! that cannot take the Rayon dependency.
+
+use std::sync::Mutex;
+use std::{panic, thread};
+
+use crate::pieces::{self, MIN_PIECE_LEN};
+use crate::{batch, kernel};
+
+/// The fewest strings of a batch given to one thread, many enough for them to take
+/// much longer to collapse than the thread to start.
+const MIN_STRINGS_PER_THREAD: usize = 4096;
+
+/// Collapses consecutive spaces and tabs like [`collapse_whitespace`](crate::collapse_whitespace),
+/// on `threads` scoped threads.
+///
+/// The `std` counterpart of `collapse_whitespace_parallel`,
+/// which needs the `rayon` feature. Below a few megabytes, or with a single thread, it
+/// collapses on the calling thread like `collapse_whitespace`.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+/// - `threads`: The most threads to collapse on, counting the calling one, or 0 for
+///   [`std::thread::available_parallelism`].
+///
+/// # Returns
+/// - The same `String` as `collapse_whitespace(input)`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_whitespace_threaded;
+/// let document = "Some  text,\tand   more ".repeat(200_000);
+/// assert_eq!(collapse_whitespace_threaded(&document, 4), fast_whitespace_collapse::collapse_whitespace(&document));
+/// ```
+///
+/// # Performance
+/// - Splits the input like `collapse_whitespace_parallel`, after runs of spaces and
+///   tabs, but into one piece per thread, of at least 1 MiB, as the threads share no
+///   queue to balance more pieces over.
+/// - Joins the collapsed pieces on the threads too, so it needs about twice the memory
+///   of the output.
+/// - If the target cannot spawn threads, such as `wasm32-wasip1`, every piece is
+///   collapsed on the calling thread.
+pub fn collapse_whitespace_threaded(input: &str, threads: usize) -> String {
+    let piece_len = (input.len() / thread_count(threads)).max(MIN_PIECE_LEN);
+    collapse_in_pieces(input, piece_len)
+}
+
+/// Collapses `input` on one thread per piece of at least `piece_len` bytes.
+fn collapse_in_pieces(input: &str, piece_len: usize) -> String {
+    let pieces = pieces::split_pieces(input.as_bytes(), piece_len);
+    if pieces.len() == 1 {
+        return crate::collapse_whitespace(input);
+    }
+    let collapsed = run_scoped(pieces, kernel::collapse_bytes);
+    pieces::join_pieces(&collapsed, |slots| {
+        run_scoped(slots, pieces::fill);
+    })
+}
+
+/// Collapses consecutive spaces and tabs in every string of `inputs` like
+/// [`collapse_many`](crate::collapse_many), on up to `threads` scoped threads.
+///
+/// # Parameters
+/// - `inputs`: The strings to collapse, such as a `&[&str]` or a `&[String]`.
+/// - `threads`: The most threads to collapse on, counting the calling one, or 0 for
+///   [`std::thread::available_parallelism`].
+///
+/// # Returns
+/// - The collapsed strings, in the order of `inputs`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_many_threaded;
+/// let rows = ["  Jane  Doe ", "clean", "\t(admin)\t\tEMEA"];
+/// assert_eq!(collapse_many_threaded(&rows, 4), ["Jane Doe", "clean", "(admin) EMEA"]);
+/// ```
+///
+/// # Performance
+/// - Gives every thread one run of at least 4096 consecutive strings, which it
+///   collapses like `collapse_many`, so smaller batches stay on the calling thread.
+pub fn collapse_many_threaded<S: AsRef<str> + Sync>(inputs: &[S], threads: usize) -> Vec<String> {
+    let runs: Vec<&[S]> = inputs.chunks(run_len(inputs.len(), threads)).collect();
+    run_scoped(runs, batch::collapse_many).into_iter().flatten().collect()
+}
+
+/// Collapses consecutive spaces and tabs in every string of `strings` in place like
+/// [`collapse_all_in_place`](crate::collapse_all_in_place), on up to `threads` scoped
+/// threads.
+///
+/// # Parameters
+/// - `strings`: The strings to collapse, such as a `&mut Vec<String>`.
+/// - `threads`: The most threads to collapse on, counting the calling one, or 0 for
+///   [`std::thread::available_parallelism`].
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_all_in_place_threaded;
+/// let mut rows = vec!["  Jane  Doe ".to_string(), "clean".into(), "\t(admin)\t\tEMEA".into()];
+/// collapse_all_in_place_threaded(&mut rows, 4);
+/// assert_eq!(rows, ["Jane Doe", "clean", "(admin) EMEA"]);
+/// ```
+///
+/// # Performance
+/// - Every string keeps its allocation, and the batch is split like in
+///   [`collapse_many_threaded`].
+pub fn collapse_all_in_place_threaded(strings: &mut [String], threads: usize) {
+    let run_len = run_len(strings.len(), threads);
+    run_scoped(strings.chunks_mut(run_len).collect(), batch::collapse_all_in_place);
+}
+
+/// Returns `threads`, or the available parallelism if it is 0.
+fn thread_count(threads: usize) -> usize {
+    match threads {
+        0 => thread::available_parallelism().map_or(1, usize::from),
+        threads => threads,
+    }
+}
+
+/// Returns the number of strings of a batch of `len` to give each of at most `threads`.
+fn run_len(len: usize, threads: usize) -> usize {
+    let threads = thread_count(threads).min(len / MIN_STRINGS_PER_THREAD).max(1);
+    len.div_ceil(threads).max(1)
+}
+
+/// Runs `job` on every item, the first on the calling thread and every other one on a
+/// scoped thread of its own, and returns the results in the order of the items.
+///
+/// Items whose thread the target cannot spawn are run on the calling thread instead, and
+/// a panic in a job is resumed on the calling thread.
+fn run_scoped<I: Send, T: Send>(items: Vec<I>, job: impl Fn(I) -> T + Sync) -> Vec<T> {
+    let len = items.len();
+    // Every item is taken by whichever thread runs it, even one that failed to spawn
+    let items: Vec<Mutex<Option<I>>> = items.into_iter().map(|item| Mutex::new(Some(item))).collect();
+    let run = |n: usize| {
+        let item = items[n].lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take();
+        job(item.expect("every item is run once"))
+    };
+    let run = &run;
+    thread::scope(|scope| {
+        let handles: Vec<_> = (1..len).map(|n| thread::Builder::new().spawn_scoped(scope, move || run(n)).map_err(|_| n)).collect();
+        let mut results = Vec::with_capacity(len);
+        if len > 0 {
+            results.push(run(0));
+        }
+        for handle in handles {
+            results.push(match handle {
+                Ok(handle) => handle.join().unwrap_or_else(|payload| panic::resume_unwind(payload)),
+                Err(n) => run(n),
+            });
+        }
+        results
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collapse_many, collapse_whitespace};
+
+    #[test]
+    fn matches_collapse_whitespace_at_every_piece_len() {
+        let inputs = ["    ".to_string(), "  lead and  \t trail  ".repeat(9), "こんにちは  世界\t\t, Привет   мир ".repeat(12)];
+        for input in &inputs {
+            for piece_len in [7, 50, 1000] {
+                assert_eq!(collapse_in_pieces(input, piece_len), collapse_whitespace(input), "{piece_len} {input:?}");
+            }
+        }
+        let document = "Some  text,\tand   more ".repeat(200_000);
+        assert_eq!(collapse_whitespace_threaded(&document, 0), collapse_whitespace(&document));
+    }
+
+    #[test]
+    fn batches_match_collapse_many() {
+        let mut rows: Vec<String> = (0..10_000).map(|n| format!("{:n$}row\t\t{n}  ", "", n = n % 7)).collect();
+        let expected = collapse_many(&rows);
+        for threads in [0, 1, 3] {
+            assert_eq!(collapse_many_threaded(&rows, threads), expected);
+        }
+        collapse_all_in_place_threaded(&mut rows, 3);
+        assert_eq!(rows, expected);
+        assert!(collapse_many_threaded::<&str>(&[], 3).is_empty());
+    }
+
+    #[test]
+    fn splits_batches_into_runs_of_enough_strings() {
+        assert_eq!(run_len(0, 8), 1);
+        assert_eq!(run_len(100, 8), 100);
+        assert_eq!(run_len(10_000, 8), 5000);
+        assert_eq!(run_len(100_000, 8), 12_500);
+    }
+
+    #[test]
+    #[should_panic(expected = "job panicked")]
+    fn resumes_panics_of_jobs() {
+        run_scoped(vec![0, 1, 2], |n| assert!(n != 2, "job panicked"));
+    }
+}
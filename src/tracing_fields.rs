@@ -0,0 +1,149 @@
+//! A `tracing-subscriber` field formatter collapsing whitespace in event messages and
+//! fields, so they cannot break a single-line log format.
+
+use std::fmt::{self, Write};
+
+use tracing_core::field::{Field, Visit};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::FormatFields;
+
+use crate::Collapser;
+
+/// A field formatter for `tracing_subscriber::fmt` that collapses whitespace in the
+/// fields of every event and span, including the message.
+///
+/// Fields are written like those of `tracing_subscriber`'s `DefaultFields`: the message
+/// as is, and the other fields as `name=value`, with strings quoted. Every value is
+/// collapsed like [`collapse_whitespace`](crate::collapse_whitespace) before it is
+/// written, or with its line breaks too using [`flatten_newlines`](Self::flatten_newlines).
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::CollapseFields;
+///
+/// let subscriber = tracing_subscriber::fmt()
+///     .fmt_fields(CollapseFields::new().flatten_newlines(true))
+///     .finish();
+/// tracing::subscriber::with_default(subscriber, || {
+///     // Logged as `INFO ...: signed in from a b user="Jane Doe"`
+///     tracing::info!(user = "  Jane \t Doe ", "signed   in\tfrom {}", "a\n  b");
+/// });
+/// ```
+///
+/// # Performance
+/// - Formats every value that is not a `&str`, such as the message, into a `String`
+///   before collapsing it, which costs an allocation per such field.
+#[derive(Debug, Clone, Default)]
+pub struct CollapseFields {
+    collapser: Collapser,
+}
+
+impl CollapseFields {
+    /// Creates a formatter collapsing spaces and tabs in every field.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether line breaks are collapsed too, as by [`Collapser::single_line`], so
+    /// every event stays on one line; off by default.
+    pub fn flatten_newlines(mut self, flatten: bool) -> Self {
+        self.collapser = if flatten { Collapser::single_line() } else { Collapser::new() };
+        self
+    }
+}
+
+impl<'writer> FormatFields<'writer> for CollapseFields {
+    fn format_fields<R: RecordFields>(&self, writer: Writer<'writer>, fields: R) -> fmt::Result {
+        let mut visitor = CollapseVisitor { writer, collapser: &self.collapser, is_empty: true, result: Ok(()) };
+        fields.record(&mut visitor);
+        visitor.result
+    }
+}
+
+/// Writes the fields it visits collapsed, for [`CollapseFields`].
+struct CollapseVisitor<'w, 'c> {
+    writer: Writer<'w>,
+    collapser: &'c Collapser,
+    /// No field has been written yet, so the next needs no separating space.
+    is_empty: bool,
+    result: fmt::Result,
+}
+
+impl CollapseVisitor<'_, '_> {
+    fn write_field(&mut self, field: &Field, value: &str, quoted: bool) {
+        if self.result.is_err() {
+            return;
+        }
+        let value = self.collapser.collapse(value);
+        let separator = if std::mem::take(&mut self.is_empty) { "" } else { " " };
+        self.result = match (field.name(), quoted) {
+            ("message", _) => write!(self.writer, "{separator}{value}"),
+            (name, true) => write!(self.writer, "{separator}{name}={value:?}"),
+            (name, false) => write!(self.writer, "{separator}{name}={value}"),
+        };
+    }
+}
+
+impl Visit for CollapseVisitor<'_, '_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.write_field(field, value, true);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let mut formatted = String::new();
+        if write!(formatted, "{value:?}").is_err() {
+            self.result = Err(fmt::Error);
+        }
+        self.write_field(field, &formatted, false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use super::CollapseFields;
+
+    /// Returns what `log` writes through a subscriber with `fields`.
+    fn capture(fields: CollapseFields, log: impl FnOnce()) -> String {
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let writer = Arc::clone(&output);
+        let subscriber = tracing_subscriber::fmt()
+            .without_time()
+            .with_target(false)
+            .with_level(false)
+            .fmt_fields(fields)
+            .with_writer(move || Captured(Arc::clone(&writer)))
+            .finish();
+        tracing::subscriber::with_default(subscriber, log);
+        let output = output.lock().unwrap();
+        String::from_utf8(output.clone()).unwrap()
+    }
+
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Captured {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn collapses_messages_and_fields() {
+        let output = capture(CollapseFields::new(), || tracing::info!(user = "  Jane \t Doe ", "signed   in\tfrom {}", "a\n  b"));
+        assert_eq!(output, "signed in from a\n b user=\"Jane Doe\"\n");
+    }
+
+    #[test]
+    fn flattens_newlines_on_request() {
+        let output = capture(CollapseFields::new().flatten_newlines(true), || tracing::warn!(count = 3, "panicked at:\n  src/main.rs:3\n\n"));
+        assert_eq!(output, "panicked at: src/main.rs:3 count=3\n");
+    }
+}
@@ -0,0 +1,156 @@
+//! UTF-8 helpers for collapsing non-ASCII whitespace.
+
+/// Returns the length of the UTF-8 sequence introduced by `lead`.
+///
+/// Continuation and invalid bytes are treated as one-byte sequences so that malformed
+/// input is copied through byte by byte.
+pub(crate) fn utf8_len(lead: u8) -> usize {
+    match lead {
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => 1,
+    }
+}
+
+/// Returns the length of the sequence at the end of `bytes` that was cut short, if any.
+pub(crate) fn incomplete_suffix_len(bytes: &[u8]) -> usize {
+    let tail = &bytes[bytes.len().saturating_sub(3)..];
+    tail.iter()
+        .rposition(|&b| utf8_len(b) > 1)
+        .map_or(0, |i| if utf8_len(tail[i]) > tail.len() - i { tail.len() - i } else { 0 })
+}
+
+/// Decodes `sequence` if it is exactly one valid UTF-8 character.
+pub(crate) fn decode(sequence: &[u8]) -> Option<char> {
+    let mut chars = std::str::from_utf8(sequence).ok()?.chars();
+    let c = chars.next()?;
+    chars.next().is_none().then_some(c)
+}
+
+/// Returns the first character of `bytes`, if it is valid UTF-8.
+pub(crate) fn first_char(bytes: &[u8]) -> Option<char> {
+    let len = utf8_len(*bytes.first()?).min(bytes.len());
+    decode(&bytes[..len])
+}
+
+/// Returns the last character of `bytes`, if it is valid UTF-8.
+pub(crate) fn last_char(bytes: &[u8]) -> Option<char> {
+    let start = bytes
+        .iter()
+        .rev()
+        .take(4)
+        .position(|&b| !(0x80..0xC0).contains(&b))
+        .map(|back| bytes.len() - 1 - back)?;
+    decode(&bytes[start..])
+}
+
+/// Returns `true` for the typographic spaces of the General Punctuation block.
+///
+/// These are U+2000..=U+200A (EN QUAD through HAIR SPACE), U+202F NARROW NO-BREAK
+/// SPACE and U+205F MEDIUM MATHEMATICAL SPACE.
+pub(crate) fn is_typographic_space(c: char) -> bool {
+    matches!(c, '\u{2000}'..='\u{200a}' | '\u{202f}' | '\u{205f}')
+}
+
+/// Returns `true` for invisible zero-width characters that break equality checks.
+///
+/// These are U+200B ZERO WIDTH SPACE, U+2060 WORD JOINER and U+FEFF ZERO WIDTH
+/// NO-BREAK SPACE (the byte order mark).
+pub(crate) fn is_zero_width(c: char) -> bool {
+    matches!(c, '\u{200b}' | '\u{2060}' | '\u{feff}')
+}
+
+/// Returns `true` for the bidirectional embedding, override and isolate controls.
+///
+/// These are U+202A..=U+202E and U+2066..=U+2069. U+200E/U+200F marks are not included
+/// because they cannot reorder text on their own.
+pub(crate) fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Returns `true` for characters that render as blank space but are not all whitespace.
+///
+/// These are U+1680 OGHAM SPACE MARK, U+180E MONGOLIAN VOWEL SEPARATOR, the Hangul
+/// fillers U+115F, U+1160, U+3164 and U+FFA0, and U+2800 BRAILLE PATTERN BLANK, all of
+/// which are used to smuggle look-alike spaces into identifiers.
+pub(crate) fn is_confusable_space(c: char) -> bool {
+    matches!(c, '\u{1680}' | '\u{180e}' | '\u{115f}' | '\u{1160}' | '\u{3164}' | '\u{ffa0}' | '\u{2800}')
+}
+
+/// Returns `true` if `sequence` is exactly one non-ASCII [`char::is_whitespace`] character.
+pub(crate) fn is_unicode_whitespace(sequence: &[u8]) -> bool {
+    // Every non-ASCII whitespace character starts with one of these lead bytes
+    if !matches!(sequence.first(), Some(0xC2 | 0xE1 | 0xE2 | 0xE3)) {
+        return false;
+    }
+    decode(sequence).is_some_and(char::is_whitespace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode, first_char, incomplete_suffix_len, is_typographic_space, is_unicode_whitespace, last_char, utf8_len,
+    };
+
+    #[test]
+    fn incomplete_suffixes() {
+        let text = "aé😀".as_bytes();
+        assert_eq!(incomplete_suffix_len(text), 0);
+        assert_eq!(incomplete_suffix_len(&text[..2]), 1);
+        assert_eq!(incomplete_suffix_len(&text[..3]), 0);
+        assert_eq!(incomplete_suffix_len(&text[..6]), 3);
+        assert_eq!(incomplete_suffix_len(&text[..4]), 1);
+        assert_eq!(incomplete_suffix_len(b""), 0);
+        assert_eq!(incomplete_suffix_len(&[0x80, 0x80]), 0);
+    }
+
+    #[test]
+    fn sequence_lengths() {
+        assert_eq!(utf8_len("é".as_bytes()[0]), 2);
+        assert_eq!(utf8_len("世".as_bytes()[0]), 3);
+        assert_eq!(utf8_len("😀".as_bytes()[0]), 4);
+        assert_eq!(utf8_len(0x80), 1);
+        assert_eq!(utf8_len(0xFF), 1);
+    }
+
+    #[test]
+    fn matches_char_is_whitespace() {
+        for c in ('\u{80}'..='\u{FFFF}').chain('\u{10000}'..='\u{10FFFF}') {
+            let mut buf = [0; 4];
+            let sequence = c.encode_utf8(&mut buf).as_bytes();
+            assert_eq!(is_unicode_whitespace(sequence), c.is_whitespace(), "{c:?}");
+        }
+    }
+
+    #[test]
+    fn decodes_single_characters() {
+        assert_eq!(decode("\u{3000}".as_bytes()), Some('\u{3000}'));
+        assert_eq!(decode("ab".as_bytes()), None);
+        assert_eq!(decode(&[0xE3, 0x80]), None);
+        assert_eq!(decode(&[]), None);
+    }
+
+    #[test]
+    fn first_and_last_characters() {
+        assert_eq!(first_char("é1".as_bytes()), Some('é'));
+        assert_eq!(last_char("1世".as_bytes()), Some('世'));
+        assert_eq!(last_char("😀".as_bytes()), Some('😀'));
+        assert_eq!(first_char(b""), None);
+        assert_eq!(last_char(b""), None);
+        assert_eq!(last_char(&[b'a', 0xE3, 0x80]), None);
+    }
+
+    #[test]
+    fn typographic_spaces_are_whitespace() {
+        let spaces = ('\u{2000}'..='\u{206f}').filter(|&c| is_typographic_space(c));
+        assert_eq!(spaces.clone().count(), 13);
+        assert!(spaces.into_iter().all(char::is_whitespace));
+    }
+
+    #[test]
+    fn rejects_truncated_sequences() {
+        assert!(!is_unicode_whitespace(&[0xE3, 0x80]));
+        assert!(!is_unicode_whitespace(&[0xC2]));
+    }
+}
@@ -0,0 +1,172 @@
+//! Whitespace collapsing for UTF-16 text.
+
+const SPACE: u16 = b' ' as u16;
+const TAB: u16 = b'\t' as u16;
+
+/// Collapses consecutive spaces and tabs into a single space in UTF-16 input.
+///
+/// This is the UTF-16 counterpart of [`collapse_whitespace`](crate::collapse_whitespace)
+/// for strings exchanged with JavaScript or Windows APIs, avoiding a round trip through
+/// UTF-8. Leading and trailing whitespace is removed and newlines are preserved.
+///
+/// # Parameters
+/// - `input`: UTF-16 code units containing text with irregular spacing.
+///
+/// # Returns
+/// - A `Vec<u16>` with collapsed whitespace. Code units other than spaces and tabs,
+///   including unpaired surrogates, are copied through unchanged.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::collapse_whitespace_utf16;
+/// let input: Vec<u16> = "This   is \t  a   test.".encode_utf16().collect();
+/// let output = collapse_whitespace_utf16(&input);
+/// assert_eq!(String::from_utf16(&output).unwrap(), "This is a test.");
+/// ```
+///
+/// # Performance
+/// - Uses SIMD (`i16x8`) to classify 8 code units at a time.
+/// - Chunks without any whitespace are copied in bulk.
+/// - Falls back to scalar processing for remaining code units.
+#[cfg(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+))]
+pub fn collapse_whitespace_utf16(input: &[u16]) -> Vec<u16> {
+    use wide::{i16x8, CmpEq};
+    let len = input.len();
+    let mut result = Vec::with_capacity(len);
+
+    let space = i16x8::splat(SPACE as i16);
+    let tab   = i16x8::splat(TAB as i16);
+
+    let mut i = 0;
+    let mut last_was_space = true;
+
+    while i + 8 <= len {
+        // Load an 8-unit chunk
+        let arr: [u16; 8] = input[i..i + 8].try_into().unwrap();
+        let chunk = i16x8::from(arr.map(|unit| unit as i16));
+
+        // Each lane sets one bit if it is a space or tab
+        let mask = (chunk.cmp_eq(space) | chunk.cmp_eq(tab)).move_mask();
+
+        if mask == 0 {
+            result.extend_from_slice(&arr);
+            last_was_space = false;
+        } else {
+            for (lane, &unit) in arr.iter().enumerate() {
+                let is_whitespace = mask & (1 << lane) != 0;
+                push_unit(&mut result, &mut last_was_space, unit, is_whitespace);
+            }
+        }
+
+        i += 8;
+    }
+
+    // Handle leftover code units (scalar pass)
+    for &unit in &input[i..] {
+        push_unit(&mut result, &mut last_was_space, unit, unit == SPACE || unit == TAB);
+    }
+
+    // Remove trailing space if any
+    if result.last() == Some(&SPACE) {
+        result.pop();
+    }
+
+    result
+}
+
+/// Collapses consecutive spaces and tabs into a single space in UTF-16 input.
+///
+/// This is the **scalar (non-SIMD) fallback** for `collapse_whitespace_utf16`, used when
+/// SIMD is unavailable or the `simd-optimized` feature is disabled.
+///
+/// # Parameters
+/// - `input`: UTF-16 code units containing text with irregular spacing.
+///
+/// # Returns
+/// - A `Vec<u16>` with collapsed whitespace.
+#[cfg(not(all(
+    feature = "simd-optimized",
+    any(
+        all(target_arch = "x86_64", target_feature = "sse2"),
+        all(target_arch = "x86_64", target_feature = "avx2"),
+        all(target_arch = "aarch64", target_feature = "neon"),
+        all(target_arch = "wasm32", target_feature = "simd128")
+    )
+)))]
+pub fn collapse_whitespace_utf16(input: &[u16]) -> Vec<u16> {
+    let mut result = Vec::with_capacity(input.len());
+    let mut last_was_space = true;
+
+    for &unit in input {
+        push_unit(&mut result, &mut last_was_space, unit, unit == SPACE || unit == TAB);
+    }
+
+    // Trim trailing space if exists
+    if result.last() == Some(&SPACE) {
+        result.pop();
+    }
+
+    result
+}
+
+/// Appends one code unit, collapsing it into the current run if it is whitespace.
+#[inline]
+fn push_unit(result: &mut Vec<u16>, last_was_space: &mut bool, unit: u16, is_whitespace: bool) {
+    if is_whitespace {
+        if !*last_was_space {
+            result.push(SPACE);
+            *last_was_space = true;
+        }
+    } else {
+        result.push(unit);
+        *last_was_space = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::collapse_whitespace_utf16;
+    use crate::collapse_whitespace;
+
+    fn collapse(input: &str) -> String {
+        let units: Vec<u16> = input.encode_utf16().collect();
+        String::from_utf16(&collapse_whitespace_utf16(&units)).unwrap()
+    }
+
+    #[test]
+    fn matches_utf8_collapse() {
+        let inputs = [
+            "",
+            "   ",
+            "This   is \t  a   test.",
+            "  Leading and trailing  ",
+            "Line1\n   Line2\nLine3",
+            "\t\t\tA\t\tB\t\t",
+            "こんにちは\t\t世界 😀  😃",
+            "a long line of text without much extra spacing at all",
+        ];
+        for input in inputs {
+            assert_eq!(collapse(input), collapse_whitespace(input), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn keeps_unpaired_surrogates() {
+        let input = [0xD800, 0x20, 0x20, 0xDC00, 0x20];
+        assert_eq!(collapse_whitespace_utf16(&input), [0xD800, 0x20, 0xDC00]);
+    }
+
+    #[test]
+    fn collapses_across_chunks() {
+        let input = format!("{}{}{}", "x".repeat(7), " ".repeat(10), "y".repeat(9));
+        assert_eq!(collapse(&input), format!("{} {}", "x".repeat(7), "y".repeat(9)));
+    }
+}
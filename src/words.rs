@@ -0,0 +1,309 @@
+//! Word iterators over the collapsed view of text, borrowing from the input.
+
+use core::iter::FusedIterator;
+use core::ops::Range;
+
+use crate::scan::{find_blank, rfind_blank};
+
+/// Returns an iterator over the words of `input`, as if it were collapsed and split on
+/// single spaces.
+///
+/// The words are those of `collapse_whitespace(input).split(' ')`, but no collapsed
+/// string is allocated: every word is a slice of `input`. Words are separated by runs
+/// of spaces and tabs only, so line breaks stay inside the words, as they do in the
+/// collapsed string. An input without any word yields nothing.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+///
+/// # Returns
+/// - A [`SplitCollapsedWords`] iterator yielding `&str` slices of `input`. It is
+///   double-ended, so the last words can be taken with `.rev()` without going through
+///   the others.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::split_collapsed_words;
+/// let words: Vec<&str> = split_collapsed_words("  This   is \t  a   test.  ").collect();
+/// assert_eq!(words, ["This", "is", "a", "test."]);
+/// ```
+///
+/// # Performance
+/// - Uses SIMD (`u8x16`) to find the boundaries of words and runs 16 bytes at a time.
+pub fn split_collapsed_words(input: &str) -> SplitCollapsedWords<'_> {
+    SplitCollapsedWords { rest: input }
+}
+
+/// Iterator over the words of collapsed text, created by [`split_collapsed_words`].
+#[derive(Debug, Clone)]
+pub struct SplitCollapsedWords<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for SplitCollapsedWords<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        // Spaces and tabs are ASCII, so every boundary found is a char boundary
+        let start = find_blank(self.rest.as_bytes(), false);
+        let rest = &self.rest[start..];
+        if rest.is_empty() {
+            self.rest = rest;
+            return None;
+        }
+        let end = find_blank(rest.as_bytes(), true);
+        let (word, rest) = rest.split_at(end);
+        self.rest = rest;
+        Some(word)
+    }
+}
+
+impl<'a> DoubleEndedIterator for SplitCollapsedWords<'a> {
+    fn next_back(&mut self) -> Option<&'a str> {
+        let end = rfind_blank(self.rest.as_bytes(), false);
+        let rest = &self.rest[..end];
+        if rest.is_empty() {
+            self.rest = rest;
+            return None;
+        }
+        let start = rfind_blank(rest.as_bytes(), true);
+        let (rest, word) = rest.split_at(start);
+        self.rest = rest;
+        Some(word)
+    }
+}
+
+impl FusedIterator for SplitCollapsedWords<'_> {}
+
+/// Returns an iterator over the byte ranges of `input` that survive collapsing.
+///
+/// The ranges are the spans of the words yielded by [`split_collapsed_words`]:
+/// `collapse_whitespace(input)` is exactly the slices `&input[range]` joined with single
+/// spaces. Zero-copy consumers can slice the original buffer themselves and assemble
+/// the output however they like, such as rope inserts or arena strings.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+///
+/// # Returns
+/// - A [`KeptRanges`] iterator yielding ascending, non-overlapping `Range<usize>`s on
+///   `char` boundaries of `input`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::kept_ranges;
+/// let ranges: Vec<_> = kept_ranges("  keep   these\t words ").collect();
+/// assert_eq!(ranges, [2..6, 9..14, 16..21]);
+/// ```
+///
+/// # Performance
+/// - Uses SIMD (`u8x16`) to find the words, like [`split_collapsed_words`].
+pub fn kept_ranges(input: &str) -> KeptRanges<'_> {
+    KeptRanges {
+        input,
+        words: split_collapsed_words(input),
+    }
+}
+
+/// Iterator over the byte ranges of text that survive collapsing, created by
+/// [`kept_ranges`].
+#[derive(Debug, Clone)]
+pub struct KeptRanges<'a> {
+    input: &'a str,
+    words: SplitCollapsedWords<'a>,
+}
+
+impl KeptRanges<'_> {
+    /// Returns the range of `word`, a slice of the input.
+    fn range(&self, word: &str) -> Range<usize> {
+        let start = word.as_ptr() as usize - self.input.as_ptr() as usize;
+        start..start + word.len()
+    }
+}
+
+impl Iterator for KeptRanges<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        self.words.next().map(|word| self.range(word))
+    }
+}
+
+impl DoubleEndedIterator for KeptRanges<'_> {
+    fn next_back(&mut self) -> Option<Range<usize>> {
+        self.words.next_back().map(|word| self.range(word))
+    }
+}
+
+impl FusedIterator for KeptRanges<'_> {}
+
+/// Returns an iterator over the words and whitespace runs of `input`.
+///
+/// Each item borrows from `input`: a [`Segment::Word`] is a maximal run of bytes other
+/// than spaces and tabs, and a [`Segment::Whitespace`] a maximal run of spaces and
+/// tabs, including those at both ends. `collapse_whitespace`
+/// is the case that replaces every inner run with one space and drops the outer ones;
+/// matching on the segments allows other outputs, such as keeping some runs or marking
+/// break opportunities.
+///
+/// # Parameters
+/// - `input`: A string slice (`&str`) containing text with irregular spacing.
+///
+/// # Returns
+/// - A [`Segments`] iterator yielding alternating [`Segment`]s that cover `input`.
+///
+/// # Example
+/// ```
+/// use fast_whitespace_collapse::{split_segments, Segment};
+///
+/// let mut html = String::new();
+/// for segment in split_segments("long   words\tbreak") {
+///     match segment {
+///         Segment::Word(word) => html.push_str(word),
+///         Segment::Whitespace { .. } => html.push_str(" <wbr>"),
+///     }
+/// }
+/// assert_eq!(html, "long <wbr>words <wbr>break");
+/// ```
+///
+/// # Performance
+/// - Uses SIMD (`u8x16`) to find the boundaries of words and runs 16 bytes at a time.
+pub fn split_segments(input: &str) -> Segments<'_> {
+    Segments { rest: input }
+}
+
+/// A word or whitespace run of text, yielded by [`split_segments`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    /// A run of bytes other than spaces and tabs.
+    Word(&'a str),
+    /// A run of spaces and tabs, which collapses to a single space between words.
+    Whitespace {
+        /// The run as it appears in the input.
+        original: &'a str,
+    },
+}
+
+impl<'a> Segment<'a> {
+    /// Returns the text of the segment as it appears in the input.
+    pub fn as_str(&self) -> &'a str {
+        match *self {
+            Segment::Word(word) => word,
+            Segment::Whitespace { original } => original,
+        }
+    }
+}
+
+/// Iterator over the words and whitespace runs of text, created by [`split_segments`].
+#[derive(Debug, Clone)]
+pub struct Segments<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Segment<'a>;
+
+    fn next(&mut self) -> Option<Segment<'a>> {
+        let blank = matches!(self.rest.as_bytes().first()?, b' ' | b'\t');
+        let end = find_blank(self.rest.as_bytes(), !blank);
+        let (segment, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(if blank { Segment::Whitespace { original: segment } } else { Segment::Word(segment) })
+    }
+}
+
+impl FusedIterator for Segments<'_> {}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::{kept_ranges, split_collapsed_words, split_segments, Segment};
+    use crate::collapse_whitespace;
+
+    #[test]
+    fn matches_splitting_collapsed_string() {
+        let inputs = [
+            "",
+            " \t ",
+            "word",
+            "This   is \t  a   test.",
+            "  Leading and trailing  ",
+            "Line1\n   Line2 \t\nLine3",
+            "こんにちは\t\t世界 😀  😃\u{3000}x",
+            &format!("{}  {}\t{}", "a".repeat(40), "b".repeat(17), " c ".repeat(20)),
+        ];
+        for input in inputs {
+            let collapsed = collapse_whitespace(input);
+            let expected: Vec<&str> = collapsed.split(' ').filter(|_| !collapsed.is_empty()).collect();
+            let words: Vec<&str> = split_collapsed_words(input).collect();
+            assert_eq!(words, expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn iterates_from_both_ends() {
+        let input = format!(" Home \t > {} >  Docs  > Page ", "x".repeat(30));
+        let last: Vec<&str> = split_collapsed_words(&input).rev().take(3).collect();
+        assert_eq!(last, ["Page", ">", "Docs"]);
+
+        let forward: Vec<&str> = split_collapsed_words(&input).collect();
+        let mut backward: Vec<&str> = split_collapsed_words(&input).rev().collect();
+        backward.reverse();
+        assert_eq!(backward, forward);
+
+        let mut words = split_collapsed_words("  a b  c\td ");
+        assert_eq!((words.next(), words.next_back()), (Some("a"), Some("d")));
+        assert_eq!((words.next_back(), words.next()), (Some("c"), Some("b")));
+        assert_eq!((words.next(), words.next_back()), (None, None));
+    }
+
+    #[test]
+    fn borrows_words_from_input() {
+        let input = "  alpha  beta";
+        let words: Vec<&str> = split_collapsed_words(input).collect();
+        assert_eq!(words[0].as_ptr(), input[2..].as_ptr());
+        assert_eq!(words[1].as_ptr(), input[9..].as_ptr());
+    }
+
+    #[test]
+    fn kept_ranges_rebuild_collapsed_string() {
+        let input = " \t é  kept\u{3000}words\t\t😀 \n x ";
+        let kept: Vec<&str> = kept_ranges(input).map(|range| &input[range]).collect();
+        assert_eq!(kept.join(" "), collapse_whitespace(input));
+        let mut backward: Vec<_> = kept_ranges(input).rev().collect();
+        backward.reverse();
+        assert_eq!(backward, kept_ranges(input).collect::<Vec<_>>());
+        assert_eq!(kept_ranges(" \t ").next(), None);
+    }
+
+    #[test]
+    fn segments_cover_input() {
+        let input = " \t This   is\u{3000}a\ttest.  ";
+        let segments: Vec<Segment> = split_segments(input).collect();
+        assert_eq!(
+            segments,
+            [
+                Segment::Whitespace { original: " \t " },
+                Segment::Word("This"),
+                Segment::Whitespace { original: "   " },
+                Segment::Word("is\u{3000}a"),
+                Segment::Whitespace { original: "\t" },
+                Segment::Word("test."),
+                Segment::Whitespace { original: "  " },
+            ]
+        );
+        assert_eq!(segments.iter().map(Segment::as_str).collect::<String>(), input);
+        assert_eq!(split_segments("").next(), None);
+    }
+
+    #[test]
+    fn segments_rebuild_collapsed_string() {
+        let input = format!("  {}  {}\t\n{} ", "a".repeat(40), "b".repeat(17), " c ".repeat(20));
+        let words: Vec<&str> = split_segments(&input)
+            .filter_map(|segment| match segment {
+                Segment::Word(word) => Some(word),
+                Segment::Whitespace { .. } => None,
+            })
+            .collect();
+        assert_eq!(words.join(" "), collapse_whitespace(&input));
+    }
+}